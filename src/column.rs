@@ -0,0 +1,168 @@
+// Copyright (c) 2020 rust-mysql-simple contributors
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use crate::{
+    consts::{ColumnFlags, ColumnType},
+    Column,
+};
+
+/// Id of the `binary` character set.
+const BINARY_CHARSET: u16 = 63;
+
+/// Helper methods for [`Column`], that check bits of [`Column::flags`].
+pub trait ColumnExt {
+    /// `NOT_NULL_FLAG` is set.
+    fn is_not_null(&self) -> bool;
+
+    /// `PRI_KEY_FLAG` is set (the column is a part of the primary key).
+    fn is_primary_key(&self) -> bool;
+
+    /// `UNIQUE_KEY_FLAG` is set (the column is a part of a unique key).
+    fn is_unique_key(&self) -> bool;
+
+    /// `AUTO_INCREMENT_FLAG` is set.
+    fn is_auto_increment(&self) -> bool;
+
+    /// `BLOB_FLAG` is set (note that it is also set for `TEXT` columns).
+    fn is_blob(&self) -> bool;
+
+    /// `UNSIGNED_FLAG` is set.
+    fn is_unsigned(&self) -> bool;
+
+    /// `ZEROFILL_FLAG` is set.
+    fn is_zerofill(&self) -> bool;
+
+    /// Returns the SQL name of the column type, e.g. `DATETIME` for `MYSQL_TYPE_DATETIME`.
+    ///
+    /// Binary and text strings are told apart by the `binary` character set, `ENUM` and `SET`
+    /// columns (sent as `MYSQL_TYPE_STRING`) by their flags. Note that the server reports
+    /// every `BLOB` and `TEXT` column as `MYSQL_TYPE_BLOB`, so the name is `BLOB` or `TEXT`
+    /// regardless of the length.
+    fn sql_type_name(&self) -> &'static str;
+}
+
+impl ColumnExt for Column {
+    fn is_not_null(&self) -> bool {
+        self.flags().contains(ColumnFlags::NOT_NULL_FLAG)
+    }
+
+    fn is_primary_key(&self) -> bool {
+        self.flags().contains(ColumnFlags::PRI_KEY_FLAG)
+    }
+
+    fn is_unique_key(&self) -> bool {
+        self.flags().contains(ColumnFlags::UNIQUE_KEY_FLAG)
+    }
+
+    fn is_auto_increment(&self) -> bool {
+        self.flags().contains(ColumnFlags::AUTO_INCREMENT_FLAG)
+    }
+
+    fn is_blob(&self) -> bool {
+        self.flags().contains(ColumnFlags::BLOB_FLAG)
+    }
+
+    fn is_unsigned(&self) -> bool {
+        self.flags().contains(ColumnFlags::UNSIGNED_FLAG)
+    }
+
+    fn is_zerofill(&self) -> bool {
+        self.flags().contains(ColumnFlags::ZEROFILL_FLAG)
+    }
+
+    fn sql_type_name(&self) -> &'static str {
+        let binary = self.character_set() == BINARY_CHARSET;
+        let text_or_binary = |text, binary_name| if binary { binary_name } else { text };
+        match self.column_type() {
+            ColumnType::MYSQL_TYPE_DECIMAL | ColumnType::MYSQL_TYPE_NEWDECIMAL => "DECIMAL",
+            ColumnType::MYSQL_TYPE_TINY => "TINYINT",
+            ColumnType::MYSQL_TYPE_SHORT => "SMALLINT",
+            ColumnType::MYSQL_TYPE_INT24 => "MEDIUMINT",
+            ColumnType::MYSQL_TYPE_LONG => "INT",
+            ColumnType::MYSQL_TYPE_LONGLONG => "BIGINT",
+            ColumnType::MYSQL_TYPE_FLOAT => "FLOAT",
+            ColumnType::MYSQL_TYPE_DOUBLE => "DOUBLE",
+            ColumnType::MYSQL_TYPE_NULL => "NULL",
+            ColumnType::MYSQL_TYPE_TIMESTAMP | ColumnType::MYSQL_TYPE_TIMESTAMP2 => "TIMESTAMP",
+            ColumnType::MYSQL_TYPE_DATE | ColumnType::MYSQL_TYPE_NEWDATE => "DATE",
+            ColumnType::MYSQL_TYPE_TIME | ColumnType::MYSQL_TYPE_TIME2 => "TIME",
+            ColumnType::MYSQL_TYPE_DATETIME | ColumnType::MYSQL_TYPE_DATETIME2 => "DATETIME",
+            ColumnType::MYSQL_TYPE_YEAR => "YEAR",
+            ColumnType::MYSQL_TYPE_BIT => "BIT",
+            ColumnType::MYSQL_TYPE_JSON => "JSON",
+            ColumnType::MYSQL_TYPE_ENUM => "ENUM",
+            ColumnType::MYSQL_TYPE_SET => "SET",
+            ColumnType::MYSQL_TYPE_GEOMETRY => "GEOMETRY",
+            ColumnType::MYSQL_TYPE_VARCHAR | ColumnType::MYSQL_TYPE_VAR_STRING => {
+                text_or_binary("VARCHAR", "VARBINARY")
+            }
+            ColumnType::MYSQL_TYPE_STRING if self.flags().contains(ColumnFlags::ENUM_FLAG) => {
+                "ENUM"
+            }
+            ColumnType::MYSQL_TYPE_STRING if self.flags().contains(ColumnFlags::SET_FLAG) => "SET",
+            ColumnType::MYSQL_TYPE_STRING => text_or_binary("CHAR", "BINARY"),
+            ColumnType::MYSQL_TYPE_TINY_BLOB => text_or_binary("TINYTEXT", "TINYBLOB"),
+            ColumnType::MYSQL_TYPE_MEDIUM_BLOB => text_or_binary("MEDIUMTEXT", "MEDIUMBLOB"),
+            ColumnType::MYSQL_TYPE_LONG_BLOB => text_or_binary("LONGTEXT", "LONGBLOB"),
+            ColumnType::MYSQL_TYPE_BLOB => text_or_binary("TEXT", "BLOB"),
+            ColumnType::MYSQL_TYPE_TYPED_ARRAY | ColumnType::MYSQL_TYPE_UNKNOWN => "UNKNOWN",
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        consts::{ColumnFlags, ColumnType},
+        prelude::*,
+        Column,
+    };
+
+    #[test]
+    fn should_check_column_flags() {
+        let column = Column::new(ColumnType::MYSQL_TYPE_LONG).with_flags(
+            ColumnFlags::NOT_NULL_FLAG
+                | ColumnFlags::PRI_KEY_FLAG
+                | ColumnFlags::AUTO_INCREMENT_FLAG
+                | ColumnFlags::UNSIGNED_FLAG,
+        );
+        assert!(column.is_not_null());
+        assert!(column.is_primary_key());
+        assert!(column.is_auto_increment());
+        assert!(column.is_unsigned());
+        assert!(!column.is_unique_key());
+        assert!(!column.is_blob());
+        assert!(!column.is_zerofill());
+
+        let column = Column::new(ColumnType::MYSQL_TYPE_BLOB)
+            .with_flags(ColumnFlags::UNIQUE_KEY_FLAG | ColumnFlags::BLOB_FLAG);
+        assert!(column.is_unique_key());
+        assert!(column.is_blob());
+        assert!(!column.is_not_null());
+        assert!(!column.is_primary_key());
+    }
+
+    #[test]
+    fn should_name_sql_types() {
+        let column = Column::new(ColumnType::MYSQL_TYPE_DATETIME);
+        assert_eq!(column.sql_type_name(), "DATETIME");
+        let column = Column::new(ColumnType::MYSQL_TYPE_LONGLONG);
+        assert_eq!(column.sql_type_name(), "BIGINT");
+
+        let column = Column::new(ColumnType::MYSQL_TYPE_VAR_STRING).with_character_set(255);
+        assert_eq!(column.sql_type_name(), "VARCHAR");
+        let column = Column::new(ColumnType::MYSQL_TYPE_VAR_STRING).with_character_set(63);
+        assert_eq!(column.sql_type_name(), "VARBINARY");
+        let column = Column::new(ColumnType::MYSQL_TYPE_BLOB).with_character_set(63);
+        assert_eq!(column.sql_type_name(), "BLOB");
+        let column = Column::new(ColumnType::MYSQL_TYPE_STRING)
+            .with_character_set(255)
+            .with_flags(ColumnFlags::ENUM_FLAG);
+        assert_eq!(column.sql_type_name(), "ENUM");
+    }
+}
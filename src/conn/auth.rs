@@ -0,0 +1,207 @@
+// Copyright (c) 2020 rust-mysql-simple contributors
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use std::{
+    fmt,
+    ops::Deref,
+    sync::{Arc, Mutex},
+};
+
+use crate::{DriverError::UnexpectedPacket, Error::DriverError, Result};
+
+/// What to do on the next step of authentication (see [`AuthPlugin::next`]).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum AuthStep {
+    /// Send the given data to the server and wait for its reply.
+    Send(Vec<u8>),
+    /// Send nothing and wait for the server to accept (or reject) the client.
+    Done,
+}
+
+/// Client side of an authentication plugin, that is not supported by the driver.
+///
+/// Custom plugins are only consulted on the auth switch request. The handshake response is
+/// always generated by `mysql_native_password` or `caching_sha2_password`, even if the server
+/// names a custom plugin in the initial handshake, so the server is expected to switch to
+/// the plugin the user account is identified with. Built-in plugins are implemented by
+/// the driver itself and can't be replaced with an `AuthPlugin`.
+/// See [`crate::OptsBuilder::auth_plugin`].
+///
+/// ```
+/// # use mysql::{prelude::*, *};
+/// /// Sends the password as is (`mysql_clear_password` analog).
+/// struct ClearPassword;
+///
+/// impl AuthPlugin for ClearPassword {
+///     fn name(&self) -> &str {
+///         "my_clear_password"
+///     }
+///
+///     fn next(
+///         &mut self,
+///         _salt: &[u8],
+///         password: &[u8],
+///         more_data: Option<&[u8]>,
+///     ) -> Result<AuthStep> {
+///         match more_data {
+///             None => Ok(AuthStep::Send([password, b"\0"].concat())),
+///             Some(_) => Ok(AuthStep::Done),
+///         }
+///     }
+/// }
+///
+/// let opts = OptsBuilder::new().auth_plugin(AuthPluginHandler::new(ClearPassword));
+/// ```
+pub trait AuthPlugin {
+    /// Plugin name, as it is named in the server's auth switch request.
+    fn name(&self) -> &str;
+
+    /// Performs the next step of authentication.
+    ///
+    /// `salt` is the data of the auth switch request and `password` is the one given in
+    /// [`crate::Opts::get_pass`] (empty if not set). `more_data` is `None` on the first step,
+    /// then it is the payload of the server's "more data" packet.
+    fn next(&mut self, salt: &[u8], password: &[u8], more_data: Option<&[u8]>) -> Result<AuthStep>;
+}
+
+/// Shareable [`AuthPlugin`], that could be registered in [`crate::Opts`].
+#[derive(Clone)]
+pub struct AuthPluginHandler(pub(crate) Arc<Mutex<dyn AuthPlugin + Send>>);
+
+impl AuthPluginHandler {
+    pub fn new<T: AuthPlugin + Send + 'static>(plugin: T) -> Self {
+        AuthPluginHandler(Arc::new(Mutex::new(plugin)))
+    }
+
+    /// Returns the name of the plugin (see [`AuthPlugin::name`]).
+    pub fn name(&self) -> String {
+        match self.0.lock() {
+            Ok(plugin) => plugin.name().to_owned(),
+            Err(poisoned) => poisoned.into_inner().name().to_owned(),
+        }
+    }
+}
+
+impl PartialEq for AuthPluginHandler {
+    fn eq(&self, other: &AuthPluginHandler) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for AuthPluginHandler {}
+
+impl fmt::Debug for AuthPluginHandler {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "AuthPluginHandler({})", self.name())
+    }
+}
+
+/// Drives the `plugin` until the server replies with an OK packet.
+///
+/// `exchange` sends the given data (if any) to the server and returns its reply.
+/// Server errors are expected to be returned by `exchange` as errors.
+pub(crate) fn drive_auth_plugin<P, F>(
+    plugin: &mut dyn AuthPlugin,
+    salt: &[u8],
+    password: &[u8],
+    mut exchange: F,
+) -> Result<P>
+where
+    P: Deref<Target = [u8]>,
+    F: FnMut(Option<&[u8]>) -> Result<P>,
+{
+    let mut more_data = None;
+    loop {
+        let reply = match plugin.next(salt, password, more_data.as_deref())? {
+            AuthStep::Send(data) => exchange(Some(&data))?,
+            AuthStep::Done => exchange(None)?,
+        };
+        match reply.first() {
+            Some(0x00) => return Ok(reply),
+            // more data
+            Some(0x01) if reply.len() > 1 => more_data = Some(reply[1..].to_vec()),
+            _ => return Err(DriverError(UnexpectedPacket)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::VecDeque;
+
+    use super::{drive_auth_plugin, AuthPlugin, AuthPluginHandler, AuthStep};
+    use crate::{DriverError::UnexpectedPacket, Error::DriverError, Result};
+
+    /// Sends the password, then echoes every "more data" packet back until told to stop.
+    struct Echo;
+
+    impl AuthPlugin for Echo {
+        fn name(&self) -> &str {
+            "echo"
+        }
+
+        fn next(
+            &mut self,
+            salt: &[u8],
+            password: &[u8],
+            more_data: Option<&[u8]>,
+        ) -> Result<AuthStep> {
+            assert_eq!(salt, b"salt");
+            match more_data {
+                None => Ok(AuthStep::Send(password.to_vec())),
+                Some(b"stop") => Ok(AuthStep::Done),
+                Some(data) => Ok(AuthStep::Send(data.to_vec())),
+            }
+        }
+    }
+
+    /// Runs `Echo` against the given server replies. Returns the data sent by the client.
+    fn drive(replies: Vec<Vec<u8>>) -> (Result<Vec<u8>>, Vec<Option<Vec<u8>>>) {
+        let mut replies = replies.into_iter().collect::<VecDeque<_>>();
+        let mut sent = Vec::new();
+        let result = drive_auth_plugin(&mut Echo, b"salt", b"password", |data| {
+            sent.push(data.map(<[u8]>::to_vec));
+            Ok(replies.pop_front().expect("no more server replies"))
+        });
+        assert!(replies.is_empty(), "server replies are not consumed");
+        (result, sent)
+    }
+
+    #[test]
+    fn should_drive_auth_plugin() {
+        let (result, sent) = drive(vec![
+            b"\x01foo".to_vec(),
+            b"\x01stop".to_vec(),
+            b"\x00\x00\x00\x02\x00\x00\x00".to_vec(),
+        ]);
+        assert_eq!(result.unwrap(), b"\x00\x00\x00\x02\x00\x00\x00");
+        assert_eq!(
+            sent,
+            vec![Some(b"password".to_vec()), Some(b"foo".to_vec()), None]
+        );
+    }
+
+    #[test]
+    fn should_reject_unexpected_auth_reply() {
+        for reply in [&b""[..], b"\x01", b"\xfeother_plugin\0"] {
+            match drive(vec![reply.to_vec()]).0 {
+                Err(DriverError(UnexpectedPacket)) => (),
+                other => panic!("UnexpectedPacket expected, got {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn should_name_auth_plugin_handler() {
+        let handler = AuthPluginHandler::new(Echo);
+        assert_eq!(handler.name(), "echo");
+        assert_eq!(format!("{:?}", handler), "AuthPluginHandler(echo)");
+        assert_eq!(handler.clone(), handler);
+        assert_ne!(AuthPluginHandler::new(Echo), handler);
+    }
+}
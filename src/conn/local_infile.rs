@@ -7,7 +7,8 @@
 // modified, or distributed except according to those terms.
 
 use std::{
-    fmt, io,
+    fmt, fs, io,
+    path::{Path, PathBuf},
     sync::{Arc, Mutex},
 };
 
@@ -74,6 +75,37 @@ impl LocalInfileHandler {
     {
         LocalInfileHandler(Arc::new(Mutex::new(f)))
     }
+
+    /// Creates a handler, that sends the contents of the requested file,
+    /// but only if it resides under the given `root` directory.
+    ///
+    /// Relative file names are resolved against `root`. Requests that point outside
+    /// of `root` (i.e. using `..`, symlinks or an absolute path) are rejected
+    /// with the `PermissionDenied` error.
+    pub fn with_root_dir<P: Into<PathBuf>>(root: P) -> Self {
+        let root = root.into();
+        LocalInfileHandler::new(move |file_name, local_infile| {
+            let path = resolve_under_root(&root, file_name)?;
+            io::copy(&mut fs::File::open(path)?, local_infile)?;
+            Ok(())
+        })
+    }
+}
+
+/// Resolves `file_name` against `root`, making sure that the result is inside `root`.
+fn resolve_under_root(root: &Path, file_name: &[u8]) -> io::Result<PathBuf> {
+    let file_name = std::str::from_utf8(file_name)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    let root = root.canonicalize()?;
+    let path = root.join(file_name).canonicalize()?;
+    if path.starts_with(&root) {
+        Ok(path)
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!("{} is outside of {}", path.display(), root.display()),
+        ))
+    }
 }
 
 impl PartialEq for LocalInfileHandler {
@@ -127,3 +159,52 @@ impl<'a> io::Write for LocalInfile<'a> {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::{fs, io, path::PathBuf, process};
+
+    use super::resolve_under_root;
+
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!("mysql-{}-{}", name, process::id()));
+            fs::create_dir_all(path.join("root")).unwrap();
+            fs::write(path.join("root").join("data.csv"), b"1,2\n").unwrap();
+            fs::write(path.join("secret.csv"), b"3,4\n").unwrap();
+            TempDir(path)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn should_resolve_file_under_root() {
+        let dir = TempDir::new("permitted");
+        let root = dir.0.join("root");
+        let path = resolve_under_root(&root, b"data.csv").unwrap();
+        assert_eq!(path, root.canonicalize().unwrap().join("data.csv"));
+    }
+
+    #[test]
+    fn should_reject_path_traversal() {
+        let dir = TempDir::new("traversal");
+        let err = resolve_under_root(&dir.0.join("root"), b"../secret.csv").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+    }
+
+    #[test]
+    fn should_reject_absolute_path_outside_root() {
+        let dir = TempDir::new("absolute");
+        let secret = dir.0.join("secret.csv").canonicalize().unwrap();
+        let err = resolve_under_root(&dir.0.join("root"), secret.to_str().unwrap().as_bytes())
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+    }
+}
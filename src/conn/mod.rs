@@ -10,17 +10,23 @@ use bytes::{Buf, BufMut};
 #[cfg(feature = "binlog")]
 use mysql_common::packets::binlog_request::BinlogRequest;
 use mysql_common::{
+    collations::{Collation, CollationId},
     constants::UTF8MB4_GENERAL_CI,
     crypto,
     io::{ParseBuf, ReadMysqlExt},
     named_params::ParsedNamedParams,
     packets::{
-        AuthPlugin, AuthSwitchRequest, Column, ComChangeUser, ComChangeUserMoreData, ComStmtClose,
+        session_state_change::SessionStateChange, AuthPlugin, AuthSwitchRequest, Column,
+        ComChangeUser, ComChangeUserMoreData, ComStmtClose, ComStmtExecuteRequest,
         ComStmtExecuteRequestBuilder, ComStmtSendLongData, CommonOkPacket, ErrPacket,
         HandshakePacket, HandshakeResponse, OkPacket, OkPacketDeserializer, OkPacketKind,
         OldAuthSwitchRequest, OldEofPacket, ResultSetTerminator, SessionStateInfo,
     },
-    proto::{codec::Compression, sync_framed::MySyncFramed, MySerialize},
+    proto::{
+        codec::{error::PacketCodecError, Compression},
+        sync_framed::MySyncFramed,
+        MySerialize,
+    },
 };
 
 use mysql_common::{
@@ -33,11 +39,14 @@ use std::{
     cmp,
     collections::HashMap,
     convert::TryFrom,
+    fmt,
     io::{self, Write as _},
     mem,
+    net::SocketAddr,
     ops::{Deref, DerefMut},
     process,
     sync::Arc,
+    time::{Duration, Instant},
 };
 
 #[cfg(unix)]
@@ -49,22 +58,26 @@ use crate::{
         local_infile::LocalInfile,
         pool::{Pool, PooledConn},
         query_result::{Binary, Or, Text},
+        stats::ConnStats,
         stmt::{InnerStmt, Statement},
         stmt_cache::StmtCache,
         transaction::{AccessMode, TxOpts},
     },
-    consts::{CapabilityFlags, Command, StatusFlags, MAX_PAYLOAD_LEN},
+    consts::{
+        CapabilityFlags, ColumnType, Command, StatusFlags, StmtExecuteParamFlags, MAX_PAYLOAD_LEN,
+    },
     from_value, from_value_opt,
     io::Stream,
     prelude::*,
-    ChangeUserOpts,
+    BytesDisplay, ChangeUserOpts,
     DriverError::{
-        CleartextPluginDisabled, MismatchedStmtParams, NamedParamsForPositionalQuery,
-        OldMysqlPasswordDisabled, Protocol41NotSet, ReadOnlyTransNotSupported, SetupError,
-        UnexpectedPacket, UnknownAuthPlugin, UnsupportedProtocol,
+        CleartextPluginDisabled, EmptyQuery, MismatchedStmtParams, NamedParamsForPositionalQuery,
+        NotMySqlServer, OldMysqlPasswordDisabled, PacketTooLarge, Protocol41NotSet,
+        ReadOnlyTransNotSupported, SetupError, TruncatedPrepareResponse, UnexpectedPacket,
+        UnknownAuthPlugin, UnsupportedProtocol,
     },
     Error::{self, DriverError, MySqlError},
-    LocalInfileHandler, Opts, OptsBuilder, Params, QueryResult, Result, Transaction,
+    LocalInfileHandler, Opts, OptsBuilder, Params, QueryResult, Result, ServerStatus, Transaction,
     Value::{self, Bytes, NULL},
 };
 
@@ -74,6 +87,7 @@ use crate::SslOpts;
 #[cfg(feature = "binlog")]
 use self::binlog_stream::BinlogStream;
 
+pub mod auth;
 #[cfg(feature = "binlog")]
 pub mod binlog_stream;
 pub mod local_infile;
@@ -82,6 +96,7 @@ pub mod pool;
 pub mod query;
 pub mod query_result;
 pub mod queryable;
+pub mod stats;
 pub mod stmt;
 mod stmt_cache;
 pub mod transaction;
@@ -157,6 +172,206 @@ impl DerefMut for ConnMut<'_, '_, '_> {
     }
 }
 
+/// `ER_NEED_REPREPARE` server error code.
+const ER_NEED_REPREPARE: u16 = 1615;
+
+/// `ER_LOCK_WAIT_TIMEOUT` server error code.
+const ER_LOCK_WAIT_TIMEOUT: u16 = 1205;
+
+/// `ER_LOCK_DEADLOCK` server error code.
+const ER_LOCK_DEADLOCK: u16 = 1213;
+
+/// Initial delay between attempts of [`Conn::with_retry`] (doubled on every attempt).
+const RETRY_BACKOFF: Duration = Duration::from_millis(10);
+
+/// Length of the `COM_STMT_SEND_LONG_DATA` header
+/// (command byte, statement id and parameter index).
+const LONG_DATA_HEADER_LEN: usize = 1 + 4 + 2;
+
+/// Returns the max length of a parameter chunk sent via `COM_STMT_SEND_LONG_DATA`,
+/// so that the whole packet fits into a single payload and into `max_allowed_packet`.
+fn long_data_chunk_len(max_allowed_packet: usize) -> usize {
+    cmp::min(MAX_PAYLOAD_LEN, max_allowed_packet)
+        .saturating_sub(LONG_DATA_HEADER_LEN)
+        .max(1)
+}
+
+/// `COM_STMT_EXECUTE` request with explicitly given parameter types (see [`Conn::exec_typed`]).
+///
+/// Same as the wrapped request, except for the types of parameters.
+struct TypedExecuteRequest<'a> {
+    body: ComStmtExecuteRequest<'a>,
+    types: &'a [ColumnType],
+}
+
+impl MySerialize for TypedExecuteRequest<'_> {
+    fn serialize(&self, buf: &mut Vec<u8>) {
+        buf.put_u8(Command::COM_STMT_EXECUTE as u8);
+        buf.put_u32_le(self.body.stmt_id());
+        buf.put_u8(self.body.flags().bits());
+        // iteration count
+        buf.put_u32_le(1);
+
+        let params = self.body.params();
+        if !params.is_empty() {
+            buf.put_slice(self.body.bitmap());
+            buf.put_u8(self.body.params_flags().bits());
+        }
+
+        for (param, column_type) in params.iter().zip(self.types) {
+            let flags = match param {
+                Value::UInt(_) => StmtExecuteParamFlags::UNSIGNED,
+                _ => StmtExecuteParamFlags::empty(),
+            };
+            buf.put_slice(&[*column_type as u8, flags.bits()]);
+        }
+
+        for param in params {
+            match param {
+                Bytes(_) if self.body.as_long_data() => (),
+                param => param.serialize(buf),
+            }
+        }
+    }
+}
+
+/// Returns `true` if the socket path should be queried because of [`Opts::get_prefer_socket`].
+///
+/// There is nothing to improve if the socket path is already known. Also the server
+/// socket is only reachable if the server is local, so the address the connection
+/// is actually established with is checked (rather than the host name, that may
+/// resolve to a remote address). Use `prefer_socket(false)` to opt out for a local server.
+fn should_probe_socket(opts: &Opts, peer_addr: Option<SocketAddr>) -> bool {
+    opts.get_prefer_socket()
+        && !opts.get_skip_setup_queries()
+        && opts.get_socket().is_none()
+        && peer_addr.is_some_and(|addr| addr.ip().is_loopback())
+}
+
+/// Handshake packets are about a hundred bytes long, so anything much larger is garbage.
+const MAX_HANDSHAKE_LEN: usize = 1024;
+
+/// Sanity check of the initial handshake packet, that gives a clear error if the server
+/// is not a MySql server, rather than a low-level parse error.
+fn check_handshake_payload(payload: &[u8]) -> Result<()> {
+    match payload.first() {
+        Some(&10) if payload.len() <= MAX_HANDSHAKE_LEN => Ok(()),
+        Some(&9) => Err(DriverError(UnsupportedProtocol(9))),
+        _ => Err(DriverError(NotMySqlServer(
+            payload.iter().copied().take(16).collect(),
+        ))),
+    }
+}
+
+/// Checks that the server speaks the protocol supported by this driver.
+fn check_handshake(handshake: &HandshakePacket<'_>) -> Result<()> {
+    if handshake.protocol_version() != 10u8 {
+        return Err(DriverError(UnsupportedProtocol(
+            handshake.protocol_version(),
+        )));
+    }
+
+    if !handshake
+        .capabilities()
+        .contains(CapabilityFlags::CLIENT_PROTOCOL_41)
+    {
+        return Err(DriverError(Protocol41NotSet {
+            server_version: handshake.server_version_str().into_owned(),
+            capabilities: handshake.capabilities().bits(),
+        }));
+    }
+
+    Ok(())
+}
+
+/// Reads `count` parameter or column (`kind`) definitions of a prepared statement followed
+/// by the terminating EOF packet (not sent if `CLIENT_DEPRECATE_EOF` is negotiated).
+///
+/// Fails if the server terminates definitions prematurely, rather than misreading
+/// the terminator as a definition.
+fn read_stmt_definitions<P, F>(
+    kind: &'static str,
+    count: u16,
+    deprecate_eof: bool,
+    mut read_packet: F,
+) -> Result<Vec<Column>>
+where
+    P: Deref<Target = [u8]>,
+    F: FnMut() -> Result<P>,
+{
+    let is_eof = |pld: &[u8]| pld.first() == Some(&0xfe) && pld.len() < 8;
+
+    let mut definitions = Vec::with_capacity(count as usize);
+    for got in 0..count {
+        let pld = read_packet()?;
+        if is_eof(&pld) {
+            return Err(DriverError(TruncatedPrepareResponse {
+                kind,
+                expected: count,
+                got,
+            }));
+        }
+        definitions.push(ParseBuf(&pld).parse(())?);
+    }
+
+    if !deprecate_eof && !is_eof(&read_packet()?) {
+        return Err(DriverError(UnexpectedPacket));
+    }
+
+    Ok(definitions)
+}
+
+/// Reads the first value of a text protocol row.
+fn first_text_value(mut pld: &[u8]) -> Result<Value> {
+    if pld.first() == Some(&0xfb) {
+        Ok(NULL)
+    } else {
+        Ok(Bytes(pld.read_lenenc_str()?))
+    }
+}
+
+/// Returns the payload of an extra authentication data packet (the `0x01` marker),
+/// that carries a fast auth result or the server public key during authentication.
+fn extra_auth_data(packet: &[u8]) -> Result<&[u8]> {
+    match packet {
+        [0x01, data @ ..] if !data.is_empty() => Ok(data),
+        _ => Err(DriverError(UnexpectedPacket)),
+    }
+}
+
+/// Splits the comma-separated `sql_mode` value.
+fn split_sql_mode(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .filter(|mode| !mode.is_empty())
+        .map(Into::into)
+        .collect()
+}
+
+/// Rejects an empty (or whitespace-only) query text before it hits the wire,
+/// because the server error is opaque in this case.
+fn check_query_not_empty(query: &[u8]) -> Result<()> {
+    if query.iter().all(u8::is_ascii_whitespace) {
+        return Err(DriverError(EmptyQuery));
+    }
+    Ok(())
+}
+
+/// Callback that receives the SQL text and the elapsed time of a statement round trip.
+type TraceHandler = Arc<dyn Fn(&str, Duration) + Send + Sync>;
+
+/// Connection's trace handler, if any (see [`Conn::set_trace_handler`]).
+#[derive(Clone, Default)]
+struct Tracer(Option<TraceHandler>);
+
+impl fmt::Debug for Tracer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Tracer")
+            .field(&self.0.as_ref().map(|_| ".."))
+            .finish()
+    }
+}
+
 /// Connection internals.
 #[derive(Debug)]
 struct ConnInner {
@@ -178,10 +393,29 @@ struct ConnInner {
     connected: bool,
     has_results: bool,
     local_infile_handler: Option<LocalInfileHandler>,
+    tracer: Tracer,
+    bytes_display: BytesDisplay,
+
+    /// Current database, tracked via `CLIENT_SESSION_TRACK` (see [`Conn::current_db`]).
+    current_db: Option<String>,
+    /// Session system variables, tracked via `CLIENT_SESSION_TRACK`
+    /// (see [`Conn::session_variable`]).
+    session_vars: HashMap<String, String>,
+    /// Cached `@@sql_mode` (see [`Conn::sql_mode`]).
+    sql_mode: Option<Vec<String>>,
 
     auth_plugin: AuthPlugin<'static>,
     nonce: Vec<u8>,
 
+    /// Whether the socket path was discovered because of `prefer_socket`.
+    socket_probed: bool,
+
+    /// Time of the last packet received from the server.
+    last_activity: Instant,
+
+    /// See [`Conn::stats`].
+    stats: ConnStats,
+
     /// This flag is to opt-in/opt-out from reset upon return to a pool.
     pub(crate) reset_upon_return: bool,
 }
@@ -202,8 +436,19 @@ impl ConnInner {
             server_version: None,
             mariadb_server_version: None,
             local_infile_handler: None,
+            tracer: Tracer::default(),
+            bytes_display: BytesDisplay::default(),
+            current_db: opts
+                .get_db_name()
+                .filter(|db_name| !db_name.is_empty())
+                .map(Into::into),
+            session_vars: HashMap::new(),
+            sql_mode: None,
             auth_plugin: AuthPlugin::MysqlNativePassword,
             nonce: Vec::new(),
+            socket_probed: false,
+            last_activity: Instant::now(),
+            stats: ConnStats::default(),
             reset_upon_return: opts.get_pool_opts().reset_connection(),
 
             opts,
@@ -212,9 +457,23 @@ impl ConnInner {
 }
 
 /// Mysql connection.
-#[derive(Debug)]
 pub struct Conn(Box<ConnInner>);
 
+impl fmt::Debug for Conn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Conn")
+            .field("connection_id", &self.0.connection_id)
+            .field(
+                "server_version",
+                &self.0.server_version.or(self.0.mariadb_server_version),
+            )
+            .field("db_name", &self.0.opts.get_db_name())
+            .field("capability_flags", &self.0.capability_flags)
+            .field("status_flags", &self.0.status_flags)
+            .finish()
+    }
+}
+
 impl Conn {
     /// Must not be called before handle_handshake.
     const fn has_capability(&self, flag: CapabilityFlags) -> bool {
@@ -229,11 +488,40 @@ impl Conn {
             .unwrap()
     }
 
+    /// Returns time elapsed since the last packet was received from the server.
+    pub(crate) fn idle_time(&self) -> Duration {
+        self.0.last_activity.elapsed()
+    }
+
     /// Returns connection identifier.
     pub fn connection_id(&self) -> u32 {
         self.0.connection_id
     }
 
+    /// Returns the collation id of this connection.
+    ///
+    /// This is the collation requested during the handshake (`utf8mb4_general_ci`
+    /// for MySql 5.5.3+), rather than the server default, so it won't reflect later
+    /// `SET NAMES` statements. See [`Conn::charset_name`].
+    pub fn charset_id(&self) -> u8 {
+        self.0.character_set
+    }
+
+    /// Returns the name of the character set of [`Conn::charset_id`]
+    /// (e.g. `utf8mb4`), or `None` if the collation id is unknown.
+    pub fn charset_name(&self) -> Option<&'static str> {
+        let collation = Collation::from(CollationId::from(u16::from(self.charset_id())));
+        match collation.id {
+            CollationId::UNKNOWN_COLLATION_ID => None,
+            _ => Some(collation.charset),
+        }
+    }
+
+    /// Returns server status flags reported in the last OK or EOF packet.
+    pub fn server_status(&self) -> ServerStatus {
+        ServerStatus::from(self.0.status_flags)
+    }
+
     /// Returns number of rows affected by the last query.
     pub fn affected_rows(&self) -> u64 {
         self.0
@@ -289,6 +577,54 @@ impl Conn {
             .unwrap_or_default()
     }
 
+    /// Returns the current database.
+    ///
+    /// It is kept up to date without extra queries using session state tracking
+    /// (`USE`, `COM_INIT_DB`, etc.), if the server supports it (MySql 5.7+, MariaDb 10.2+).
+    /// Otherwise it is the database of the connection options or [`Conn::select_db`].
+    pub fn current_db(&self) -> Option<&str> {
+        self.0.current_db.as_deref()
+    }
+
+    /// Returns the value of a session system variable, if its change was reported by the server
+    /// (see [`Conn::current_db`]).
+    ///
+    /// Only variables listed in the `session_track_system_variables` server variable are
+    /// reported (e.g. `time_zone`, `autocommit` and `character_set_*` by default).
+    pub fn session_variable(&self, name: &str) -> Option<&str> {
+        self.0.session_vars.get(name).map(String::as_str)
+    }
+
+    /// Returns the session `sql_mode` split into individual modes.
+    ///
+    /// The value is queried once and cached. The cache is refreshed after a query or statement
+    /// that mentions `sql_mode` (e.g. `SET sql_mode = ...`), and also updated via session
+    /// state tracking if `sql_mode` is listed in `session_track_system_variables`.
+    ///
+    /// ## Note
+    ///
+    /// Changes made indirectly (e.g. by a stored procedure) won't be noticed, unless tracked.
+    pub fn sql_mode(&mut self) -> Result<Vec<String>> {
+        if let Some(ref sql_mode) = self.0.sql_mode {
+            return Ok(sql_mode.clone());
+        }
+        let value: Option<String> = self.query_first("SELECT @@SESSION.sql_mode")?;
+        let sql_mode = split_sql_mode(&value.unwrap_or_default());
+        self.0.sql_mode = Some(sql_mode.clone());
+        Ok(sql_mode)
+    }
+
+    /// Drops the cached `sql_mode` if the given query might change it (see [`Conn::sql_mode`]).
+    fn forget_sql_mode_if_mentioned(&mut self, query: &[u8]) {
+        if self.0.sql_mode.is_some()
+            && query
+                .windows(b"sql_mode".len())
+                .any(|x| x.eq_ignore_ascii_case(b"sql_mode"))
+        {
+            self.0.sql_mode = None;
+        }
+    }
+
     pub fn session_state_changes(&self) -> io::Result<Vec<SessionStateInfo<'_>>> {
         self.0
             .ok_packet
@@ -310,14 +646,23 @@ impl Conn {
         self.stream_ref().get_ref().is_insecure()
     }
 
-    fn is_socket(&self) -> bool {
+    /// Returns `true` if connected via Unix socket (or named pipe on windows).
+    ///
+    /// Note that a TCP connection to a local server might be replaced with a socket
+    /// connection (see [`Opts::get_prefer_socket`]).
+    pub fn is_socket(&self) -> bool {
         self.stream_ref().get_ref().is_socket()
     }
 
+    /// Returns `true` if connected via TCP (either plain or TLS).
+    pub fn is_tcp(&self) -> bool {
+        self.stream_ref().get_ref().is_tcp()
+    }
+
     /// Check the connection can be improved.
     #[allow(unused_assignments)]
     fn can_improved(&mut self) -> Result<Option<Opts>> {
-        if self.0.opts.get_prefer_socket() && self.0.opts.addr_is_loopback() {
+        if should_probe_socket(&self.0.opts, self.stream_ref().get_ref().peer_addr()) {
             let mut socket = None;
             #[cfg(test)]
             {
@@ -327,11 +672,9 @@ impl Conn {
                 socket = self.get_system_var("socket")?.map(from_value::<String>);
             }
             if let Some(socket) = socket {
-                if self.0.opts.get_socket().is_none() {
-                    let socket_opts = OptsBuilder::from_opts(self.0.opts.clone());
-                    if !socket.is_empty() {
-                        return Ok(Some(socket_opts.socket(Some(socket)).into()));
-                    }
+                let socket_opts = OptsBuilder::from_opts(self.0.opts.clone());
+                if !socket.is_empty() {
+                    return Ok(Some(socket_opts.socket(Some(socket)).into()));
                 }
             }
         }
@@ -345,31 +688,63 @@ impl Conn {
         crate::Error: From<E>,
     {
         let opts = Opts::try_from(opts)?;
+        Self::connect_with_opts(opts)
+    }
+
+    /// Establishes new connection, switching to socket if possible (see [`Opts::get_prefer_socket`]).
+    fn connect_with_opts(opts: Opts) -> Result<Conn> {
         let mut conn = Conn(Box::new(ConnInner::empty(opts)));
         conn.connect_stream()?;
         conn.connect()?;
-        let mut conn = {
-            if let Some(new_opts) = conn.can_improved()? {
-                let mut improved_conn = Conn(Box::new(ConnInner::empty(new_opts)));
-                improved_conn
-                    .connect_stream()
-                    .and_then(|_| {
-                        improved_conn.connect()?;
-                        Ok(improved_conn)
-                    })
-                    .unwrap_or(conn)
-            } else {
-                conn
+        if let Some(socket_opts) = conn.can_improved()? {
+            // The TCP connection is kept if the socket is unreachable or the server
+            // rejects the client there (the failed socket connection is dropped).
+            if let Ok(socket_conn) = Self::connect_socket(socket_opts) {
+                conn = socket_conn;
             }
-        };
+        }
         for cmd in conn.0.opts.get_init() {
             conn.query_drop(cmd)?;
         }
         Ok(conn)
     }
 
+    /// Establishes the socket connection discovered by [`Conn::can_improved`].
+    fn connect_socket(opts: Opts) -> Result<Conn> {
+        let mut conn = Conn(Box::new(ConnInner::empty(opts)));
+        conn.connect_stream()?;
+        conn.connect()?;
+        conn.0.socket_probed = true;
+        Ok(conn)
+    }
+
+    /// Replaces this connection with a new one, established using the same options.
+    ///
+    /// If the socket path was discovered because of [`Opts::get_prefer_socket`],
+    /// then it will be discovered again, so that the new connection will follow
+    /// the server-side socket path.
+    pub fn reconnect(&mut self) -> Result<()> {
+        let opts = if self.0.socket_probed {
+            OptsBuilder::from_opts(self.0.opts.clone())
+                .socket(None::<String>)
+                .into()
+        } else {
+            self.0.opts.clone()
+        };
+        let tracer = self.0.tracer.clone();
+        let bytes_display = self.0.bytes_display;
+        let stats = self.0.stats;
+        *self = Self::connect_with_opts(opts)?;
+        self.0.tracer = tracer;
+        self.0.bytes_display = bytes_display;
+        self.0.stats = stats;
+        Ok(())
+    }
+
     fn exec_com_reset_connection(&mut self) -> Result<()> {
         self.write_command(Command::COM_RESET_CONNECTION, &[])?;
+        self.0.session_vars.clear();
+        self.0.sql_mode = None;
         let packet = self.read_packet()?;
         self.handle_ok::<CommonOkPacket>(&packet)?;
         self.0.last_command = 0;
@@ -389,18 +764,22 @@ impl Conn {
                     .as_deref(),
             )
             .with_more_data(Some(
-                ComChangeUserMoreData::new(if self.server_version() >= (5, 5, 3) {
-                    UTF8MB4_GENERAL_CI
-                } else {
-                    UTF8_GENERAL_CI
-                })
-                .with_auth_plugin(Some(self.0.auth_plugin.clone()))
-                .with_connect_attributes(self.0.opts.get_connect_attrs().cloned()),
+                ComChangeUserMoreData::new(self.client_collation())
+                    .with_auth_plugin(Some(self.0.auth_plugin.clone()))
+                    .with_connect_attributes(self.0.opts.get_connect_attrs().cloned()),
             ))
             .into_owned();
         self.write_command_raw(&com_change_user)?;
         self.0.last_command = 0;
         self.0.stmt_cache.clear();
+        self.0.session_vars.clear();
+        self.0.sql_mode = None;
+        self.0.current_db = self
+            .0
+            .opts
+            .get_db_name()
+            .filter(|db_name| !db_name.is_empty())
+            .map(Into::into);
         self.continue_auth(false)
     }
 
@@ -470,45 +849,7 @@ impl Conn {
     }
 
     fn connect_stream(&mut self) -> Result<()> {
-        let opts = &self.0.opts;
-        let read_timeout = opts.get_read_timeout().cloned();
-        let write_timeout = opts.get_write_timeout().cloned();
-        let tcp_keepalive_time = opts.get_tcp_keepalive_time_ms();
-        #[cfg(any(target_os = "linux", target_os = "macos",))]
-        let tcp_keepalive_probe_interval_secs = opts.get_tcp_keepalive_probe_interval_secs();
-        #[cfg(any(target_os = "linux", target_os = "macos",))]
-        let tcp_keepalive_probe_count = opts.get_tcp_keepalive_probe_count();
-        #[cfg(target_os = "linux")]
-        let tcp_user_timeout = opts.get_tcp_user_timeout_ms();
-        let tcp_nodelay = opts.get_tcp_nodelay();
-        let tcp_connect_timeout = opts.get_tcp_connect_timeout();
-        let bind_address = opts.bind_address().cloned();
-        let stream = if let Some(socket) = opts.get_socket() {
-            Stream::connect_socket(socket, read_timeout, write_timeout)?
-        } else {
-            let port = opts.get_tcp_port();
-            let ip_or_hostname = match opts.get_host() {
-                url::Host::Domain(domain) => domain,
-                url::Host::Ipv4(ip) => ip.to_string(),
-                url::Host::Ipv6(ip) => ip.to_string(),
-            };
-            Stream::connect_tcp(
-                &ip_or_hostname,
-                port,
-                read_timeout,
-                write_timeout,
-                tcp_keepalive_time,
-                #[cfg(any(target_os = "linux", target_os = "macos",))]
-                tcp_keepalive_probe_interval_secs,
-                #[cfg(any(target_os = "linux", target_os = "macos",))]
-                tcp_keepalive_probe_count,
-                #[cfg(target_os = "linux")]
-                tcp_user_timeout,
-                tcp_nodelay,
-                tcp_connect_timeout,
-                bind_address,
-            )?
-        };
+        let stream = Stream::dial(&self.0.opts)?;
         self.0.stream = Some(MySyncFramed::new(stream));
         Ok(())
     }
@@ -517,6 +858,8 @@ impl Conn {
         if !self.stream_mut().next_packet(buffer)? {
             Err(Error::server_disconnected())
         } else {
+            self.0.stats.packets_received += 1;
+            self.0.stats.bytes_received += buffer.len() as u64;
             Ok(())
         }
     }
@@ -529,6 +872,7 @@ impl Conn {
                     match ParseBuf(&buffer).parse(self.0.capability_flags)? {
                         ErrPacket::Error(server_error) => {
                             self.handle_err();
+                            self.0.stats.errors += 1;
                             return Err(MySqlError(From::from(server_error)));
                         }
                         ErrPacket::Progress(_progress_report) => {
@@ -537,9 +881,14 @@ impl Conn {
                         }
                     }
                 }
-                Ok(()) => return Ok(buffer),
+                Ok(()) => {
+                    self.0.last_activity = Instant::now();
+                    return Ok(buffer);
+                }
                 Err(e) => {
                     self.handle_err();
+                    self.mark_broken();
+                    self.0.stats.errors += 1;
                     return Err(e);
                 }
             }
@@ -556,11 +905,46 @@ impl Conn {
         self.write_packet(&mut &*buf)
     }
 
+    /// Writes the given packet.
+    ///
+    /// Fails with [`crate::DriverError::PacketTooLarge`] without writing anything,
+    /// if the packet exceeds `max_allowed_packet` (the connection stays usable).
     fn write_packet<T: Buf>(&mut self, data: &mut T) -> Result<()> {
-        self.stream_mut().send(data)?;
+        let max = self.stream_ref().codec().max_allowed_packet;
+        if data.remaining() > max {
+            return Err(DriverError(PacketTooLarge {
+                len: data.remaining(),
+                max,
+            }));
+        }
+        let len = data.remaining() as u64;
+        if let Err(e) = self.stream_mut().send(data) {
+            self.mark_broken();
+            self.0.stats.errors += 1;
+            return Err(e.into());
+        }
+        self.0.stats.packets_sent += 1;
+        self.0.stats.bytes_sent += len;
         Ok(())
     }
 
+    /// Marks this connection as unusable after a failure in the middle of a command.
+    ///
+    /// Packet sequence is unknown at this point, so any subsequent command
+    /// will fail instead of running out of sync.
+    fn mark_broken(&mut self) {
+        self.0.connected = false;
+    }
+
+    /// Fails if the connection was broken (see [`Conn::mark_broken`]).
+    fn ensure_connected(&self) -> Result<()> {
+        if self.0.connected {
+            Ok(())
+        } else {
+            Err(Error::server_disconnected())
+        }
+    }
+
     fn handle_handshake(&mut self, hp: &HandshakePacket<'_>) {
         self.0.capability_flags = hp.capabilities() & self.get_client_flags();
         self.0.status_flags = hp.status_flags();
@@ -579,9 +963,43 @@ impl Conn {
             .into_inner();
         self.0.status_flags = ok.status_flags();
         self.0.ok_packet = Some(ok.clone().into_owned());
+        if ok
+            .status_flags()
+            .contains(StatusFlags::SERVER_SESSION_STATE_CHANGED)
+        {
+            self.handle_session_state_changes(&ok);
+        }
         Ok(ok)
     }
 
+    /// Updates the tracked session state (see [`Conn::current_db`]).
+    ///
+    /// Malformed session state info is skipped, because the command itself has succeeded.
+    fn handle_session_state_changes(&mut self, ok: &OkPacket<'_>) {
+        let infos = match ok.session_state_info() {
+            Ok(infos) => infos,
+            Err(_) => return,
+        };
+        for info in infos {
+            match info.decode() {
+                Ok(SessionStateChange::Schema(schema)) => {
+                    self.0.current_db = Some(schema.as_str().into_owned());
+                }
+                Ok(SessionStateChange::SystemVariables(vars)) => {
+                    for var in vars {
+                        if var.name_str().eq_ignore_ascii_case("sql_mode") {
+                            self.0.sql_mode = Some(split_sql_mode(&var.value_str()));
+                        }
+                        self.0
+                            .session_vars
+                            .insert(var.name_str().into_owned(), var.value_str().into_owned());
+                    }
+                }
+                _ => (),
+            }
+        }
+    }
+
     fn handle_err(&mut self) {
         self.0.status_flags = StatusFlags::empty();
         self.0.has_results = false;
@@ -613,6 +1031,19 @@ impl Conn {
 
         self.0.nonce = auth_switch_request.plugin_data().to_vec();
         self.0.auth_plugin = auth_switch_request.auth_plugin().into_owned();
+
+        if let AuthPlugin::Other(ref name) = self.0.auth_plugin {
+            let handler = self
+                .0
+                .opts
+                .get_auth_plugins()
+                .iter()
+                .find(|handler| handler.name().as_bytes() == &**name)
+                .cloned();
+            if let Some(handler) = handler {
+                return self.continue_custom_auth(handler);
+            }
+        }
         let plugin_data = match self.0.auth_plugin {
             ref x @ AuthPlugin::MysqlOldPassword => {
                 if self.0.opts.get_secure_auth() {
@@ -647,23 +1078,40 @@ impl Conn {
         self.continue_auth(true)
     }
 
-    fn do_handshake(&mut self) -> Result<()> {
-        let payload = self.read_packet()?;
-        let handshake = ParseBuf(&payload).parse::<HandshakePacket>(())?;
-
-        if handshake.protocol_version() != 10u8 {
-            return Err(DriverError(UnsupportedProtocol(
-                handshake.protocol_version(),
-            )));
-        }
-
-        if !handshake
-            .capabilities()
-            .contains(CapabilityFlags::CLIENT_PROTOCOL_41)
-        {
-            return Err(DriverError(Protocol41NotSet));
+    /// Reads the initial handshake packet.
+    ///
+    /// The first bytes sent by a non-MySql server (e.g. `HTTP`) usually decode into a packet
+    /// length of megabytes, so the packet length is limited here to fail fast rather than wait
+    /// for data that will never come.
+    fn read_handshake_packet(&mut self) -> Result<Buffer> {
+        let max_allowed_packet = mem::replace(
+            &mut self.stream_mut().codec_mut().max_allowed_packet,
+            MAX_HANDSHAKE_LEN,
+        );
+        let result = self.read_packet();
+        self.stream_mut().codec_mut().max_allowed_packet = max_allowed_packet;
+        match result {
+            Err(Error::CodecError(PacketCodecError::PacketTooLarge)) => {
+                // the packet header is still in the read buffer
+                let (in_buf, out_buf, codec, stream) = self
+                    .0
+                    .stream
+                    .take()
+                    .expect("incomplete connection")
+                    .destruct();
+                let bytes = in_buf.iter().copied().take(16).collect();
+                self.0.stream = Some(MySyncFramed::construct(in_buf, out_buf, codec, stream));
+                Err(DriverError(NotMySqlServer(bytes)))
+            }
+            result => result,
         }
+    }
 
+    fn do_handshake(&mut self) -> Result<()> {
+        let payload = self.read_handshake_packet()?;
+        check_handshake_payload(&payload)?;
+        let handshake = ParseBuf(&payload).parse::<HandshakePacket>(())?;
+        check_handshake(&handshake)?;
         self.handle_handshake(&handshake);
 
         if self.is_insecure() {
@@ -677,15 +1125,10 @@ impl Conn {
             }
         }
 
-        // Handshake scramble is always 21 bytes length (20 + zero terminator)
-        self.0.nonce = {
-            let mut nonce = Vec::from(handshake.scramble_1_ref());
-            nonce.extend_from_slice(handshake.scramble_2_ref().unwrap_or(&[][..]));
-            // Trim zero terminator. Fill with zeroes if nonce
-            // is somehow smaller than 20 bytes (this matches the server behavior).
-            nonce.resize(20, 0);
-            nonce
-        };
+        // Handshake scramble is always 21 bytes length (20 + zero terminator).
+        // The second part is at least 13 bytes long (even if the server reports
+        // zero `auth_plugin_data_len`), so zero terminator gets trimmed here.
+        self.0.nonce = handshake.nonce();
 
         // Allow only CachingSha2Password and MysqlNativePassword here
         // because sha256_password is deprecated and other plugins won't
@@ -721,13 +1164,17 @@ impl Conn {
             | CapabilityFlags::CLIENT_MULTI_RESULTS
             | CapabilityFlags::CLIENT_PS_MULTI_RESULTS
             | CapabilityFlags::CLIENT_PLUGIN_AUTH
+            | CapabilityFlags::CLIENT_SESSION_TRACK
             | (self.0.capability_flags & CapabilityFlags::CLIENT_LONG_FLAG);
         if self.0.opts.get_compress().is_some() {
             client_flags.insert(CapabilityFlags::CLIENT_COMPRESS);
         }
-        if self.0.opts.get_connect_attrs().is_some() {
+        if self.0.opts.get_connect_attrs().is_some() || self.0.opts.get_program_name().is_some() {
             client_flags.insert(CapabilityFlags::CLIENT_CONNECT_ATTRS);
         }
+        if self.0.opts.get_found_rows() {
+            client_flags.insert(CapabilityFlags::CLIENT_FOUND_ROWS);
+        }
         if let Some(db_name) = self.0.opts.get_db_name() {
             if !db_name.is_empty() {
                 client_flags.insert(CapabilityFlags::CLIENT_CONNECT_WITH_DB);
@@ -740,7 +1187,14 @@ impl Conn {
     }
 
     fn connect_attrs(&self) -> Option<HashMap<String, String>> {
-        if let Some(attrs) = self.0.opts.get_connect_attrs() {
+        let empty_attrs = HashMap::new();
+        let attrs = match self.0.opts.get_connect_attrs() {
+            Some(attrs) => Some(attrs),
+            None if self.0.opts.get_program_name().is_some() => Some(&empty_attrs),
+            None => None,
+        };
+
+        if let Some(attrs) = attrs {
             let program_name = match attrs.get("program_name") {
                 Some(program_name) => program_name.clone(),
                 None => {
@@ -763,6 +1217,10 @@ impl Conn {
                 attrs_to_send.insert(name, value);
             }
 
+            if let Some(program_name) = self.0.opts.get_program_name() {
+                attrs_to_send.insert("program_name".into(), program_name.into());
+            }
+
             Some(attrs_to_send)
         } else {
             None
@@ -770,35 +1228,46 @@ impl Conn {
     }
 
     fn do_ssl_request(&mut self) -> Result<()> {
-        let charset = if self.server_version() >= (5, 5, 3) {
-            UTF8MB4_GENERAL_CI
-        } else {
-            UTF8_GENERAL_CI
-        };
-
         let ssl_request = SslRequest::new(
             self.get_client_flags(),
             DEFAULT_MAX_ALLOWED_PACKET as u32,
-            charset as u8,
+            self.client_collation() as u8,
         );
         self.write_struct(&ssl_request)
     }
 
-    fn write_handshake_response(&mut self) -> Result<()> {
+    /// Serializes the handshake response.
+    ///
+    /// Auth response encoding depends on the negotiated capabilities, so the plugin name
+    /// is only sent if the server supports `CLIENT_PLUGIN_AUTH` (same goes for connect
+    /// attributes and `CLIENT_CONNECT_ATTRS`).
+    fn handshake_response(&self) -> Buffer {
         let auth_data = self
             .0
             .auth_plugin
             .gen_data(self.0.opts.get_pass(), &self.0.nonce)
             .map(|x| x.into_owned());
 
+        let auth_plugin = if self.has_capability(CapabilityFlags::CLIENT_PLUGIN_AUTH) {
+            Some(self.0.auth_plugin.clone())
+        } else {
+            None
+        };
+
+        let connect_attrs = if self.has_capability(CapabilityFlags::CLIENT_CONNECT_ATTRS) {
+            self.connect_attrs()
+        } else {
+            None
+        };
+
         let handshake_response = HandshakeResponse::new(
             auth_data.as_deref(),
             self.0.server_version.unwrap_or((0, 0, 0)),
             self.0.opts.get_user().map(str::as_bytes),
             self.0.opts.get_db_name().map(str::as_bytes),
-            Some(self.0.auth_plugin.clone()),
+            auth_plugin,
             self.0.capability_flags,
-            self.connect_attrs(),
+            connect_attrs,
             self.0
                 .opts
                 .get_max_allowed_packet()
@@ -807,7 +1276,25 @@ impl Conn {
 
         let mut buf = get_buffer();
         handshake_response.serialize(buf.as_mut());
-        self.write_packet(&mut &*buf)
+        buf
+    }
+
+    fn write_handshake_response(&mut self) -> Result<()> {
+        let buf = self.handshake_response();
+        self.write_packet(&mut &*buf)?;
+        // server default collation is replaced by the one requested by the client
+        self.0.character_set = self.client_collation() as u8;
+        Ok(())
+    }
+
+    /// Collation requested by the client during the handshake
+    /// (`utf8mb4_general_ci` if supported by the server, `utf8_general_ci` otherwise).
+    fn client_collation(&self) -> u16 {
+        if self.server_version() >= (5, 5, 3) {
+            UTF8MB4_GENERAL_CI
+        } else {
+            UTF8_GENERAL_CI
+        }
     }
 
     fn continue_auth(&mut self, auth_switched: bool) -> Result<()> {
@@ -834,6 +1321,26 @@ impl Conn {
         }
     }
 
+    /// Drives a custom authentication plugin (see [`crate::Opts::get_auth_plugins`]).
+    fn continue_custom_auth(&mut self, handler: auth::AuthPluginHandler) -> Result<()> {
+        let plugin = &mut *handler.0.lock()?;
+        let nonce = self.0.nonce.clone();
+        let pass = self
+            .0
+            .opts
+            .get_pass()
+            .unwrap_or_default()
+            .as_bytes()
+            .to_vec();
+        let payload = auth::drive_auth_plugin(plugin, &nonce, &pass, |data| {
+            if let Some(mut data) = data {
+                self.write_packet(&mut data)?;
+            }
+            self.read_packet()
+        })?;
+        self.handle_ok::<CommonOkPacket>(&payload).map(drop)
+    }
+
     fn continue_mysql_native_password_auth(&mut self, auth_switched: bool) -> Result<()> {
         let payload = self.read_packet()?;
 
@@ -864,20 +1371,23 @@ impl Conn {
                 // ok packet for empty password
                 Ok(())
             }
-            0x01 => match payload[1] {
+            0x01 => match extra_auth_data(&payload)?[0] {
+                // fast auth success
                 0x03 => {
                     let payload = self.read_packet()?;
                     self.handle_ok::<CommonOkPacket>(&payload).map(drop)
                 }
+                // perform full authentication
                 0x04 => {
                     if !self.is_insecure() || self.is_socket() {
                         let mut pass = self.0.opts.get_pass().map(Vec::from).unwrap_or_default();
                         pass.push(0);
                         self.write_packet(&mut pass.as_slice())?;
                     } else {
+                        // request public key
                         self.write_packet(&mut &[0x02][..])?;
                         let payload = self.read_packet()?;
-                        let key = &payload[1..];
+                        let key = extra_auth_data(&payload)?;
                         let mut pass = self.0.opts.get_pass().map(Vec::from).unwrap_or_default();
                         pass.push(0);
                         for (i, c) in pass.iter_mut().enumerate() {
@@ -908,12 +1418,22 @@ impl Conn {
         self.stream_mut().codec_mut().sync_seq_id();
     }
 
+    /// Prepares the connection to send the command, that starts with the given byte.
+    fn begin_command(&mut self, cmd: u8) -> Result<()> {
+        self.ensure_connected()?;
+        self.reset_seq_id();
+        self.0.last_command = cmd;
+        if cmd == Command::COM_QUERY as u8 || cmd == Command::COM_STMT_EXECUTE as u8 {
+            self.0.stats.queries += 1;
+        }
+        Ok(())
+    }
+
     fn write_command_raw<T: MySerialize>(&mut self, cmd: &T) -> Result<()> {
         let mut buf = get_buffer();
         cmd.serialize(buf.as_mut());
-        self.reset_seq_id();
         debug_assert!(buf.len() > 0);
-        self.0.last_command = buf[0];
+        self.begin_command(buf[0])?;
         self.write_packet(&mut &*buf)
     }
 
@@ -922,15 +1442,15 @@ impl Conn {
         buf.as_mut().put_u8(cmd as u8);
         buf.as_mut().extend_from_slice(data);
 
-        self.reset_seq_id();
-        self.0.last_command = buf[0];
+        self.begin_command(cmd as u8)?;
         self.write_packet(&mut &*buf)
     }
 
     fn send_long_data(&mut self, stmt_id: u32, params: &[Value]) -> Result<()> {
         for (i, value) in params.iter().enumerate() {
             if let Bytes(bytes) = value {
-                let chunks = bytes.chunks(MAX_PAYLOAD_LEN - 6);
+                let chunk_len = long_data_chunk_len(self.stream_ref().codec().max_allowed_packet);
+                let chunks = bytes.chunks(chunk_len);
                 let chunks = chunks.chain(if bytes.is_empty() {
                     Some(&[][..])
                 } else {
@@ -946,12 +1466,49 @@ impl Conn {
         Ok(())
     }
 
+    /// Executes the statement. Parameter types are inferred from values,
+    /// unless `types` are given (see [`Conn::exec_typed`]).
     fn _execute(
         &mut self,
         stmt: &Statement,
         params: Params,
+        types: Option<&[ColumnType]>,
+    ) -> Result<Or<Vec<Column>, OkPacket<'static>>> {
+        let params = match params {
+            Params::Named(_) => match stmt.named_params.as_ref() {
+                Some(named_params) => params.into_positional(named_params)?,
+                None => return Err(DriverError(NamedParamsForPositionalQuery)),
+            },
+            params => params,
+        };
+
+        self.forget_sql_mode_if_mentioned(stmt.inner.query());
+
+        match self._execute_positional(stmt, &params, types) {
+            // Table metadata has changed since the statement was prepared
+            // and the server has failed to re-prepare it.
+            Err(MySqlError(ref err)) if err.code == ER_NEED_REPREPARE => {
+                self.0.stmt_cache.evict(stmt.id());
+                let inner = self._prepare(stmt.inner.query())?;
+                if self.0.opts.get_stmt_cache_size() == 0 {
+                    // not cached and not returned to the caller, so it'll be closed once
+                    // unreferenced (see `StmtCache::take_unreferenced`)
+                    self.0.stmt_cache.retain_evicted(inner.clone());
+                }
+                let stmt = Statement::new(inner, stmt.named_params.clone());
+                self._execute_positional(&stmt, &params, types)
+            }
+            result => result,
+        }
+    }
+
+    fn _execute_positional(
+        &mut self,
+        stmt: &Statement,
+        params: &Params,
+        types: Option<&[ColumnType]>,
     ) -> Result<Or<Vec<Column>, OkPacket<'static>>> {
-        let exec_request = match &params {
+        let exec_request = match params {
             Params::Empty => {
                 if stmt.num_params() != 0 {
                     return Err(DriverError(MismatchedStmtParams(stmt.num_params(), 0)));
@@ -977,15 +1534,15 @@ impl Conn {
 
                 body
             }
-            Params::Named(_) => {
-                if let Some(named_params) = stmt.named_params.as_ref() {
-                    return self._execute(stmt, params.into_positional(named_params)?);
-                } else {
-                    return Err(DriverError(NamedParamsForPositionalQuery));
-                }
-            }
+            Params::Named(_) => unreachable!("named params are converted by Conn::_execute"),
         };
-        self.write_command_raw(&exec_request)?;
+        match types {
+            Some(types) => self.write_command_raw(&TypedExecuteRequest {
+                body: exec_request,
+                types,
+            })?,
+            None => self.write_command_raw(&exec_request)?,
+        }
         self.handle_result_set()
     }
 
@@ -1044,6 +1601,16 @@ impl Conn {
         Ok(ok.into_owned())
     }
 
+    /// Handles the first packet of a reply to a query or statement execution.
+    ///
+    /// This is the only place where the reply is dispatched, so every query helper
+    /// (`query_drop`, `query_first`, etc.) and every subsequent result set of
+    /// a multi-result reply goes through here:
+    ///
+    /// *   ERR packet is converted to an error by `Conn::read_packet`;
+    /// *   OK packet is returned as is;
+    /// *   LOCAL INFILE request is served via `Conn::send_local_infile`;
+    /// *   anything else is a column count followed by column definitions.
     fn handle_result_set(&mut self) -> Result<Or<Vec<Column>, OkPacket<'static>>> {
         if self.more_results_exists() {
             self.sync_seq_id();
@@ -1055,10 +1622,7 @@ impl Conn {
                 let ok = self.handle_ok::<CommonOkPacket>(&pld)?;
                 Ok(Or::B(ok.into_owned()))
             }
-            0xfb => match self.send_local_infile(&pld[1..]) {
-                Ok(ok) => Ok(Or::B(ok)),
-                Err(err) => Err(err),
-            },
+            0xfb => self.send_local_infile(&pld[1..]).map(Or::B),
             _ => {
                 let mut reader = &pld[..];
                 let column_count = reader.read_lenenc_int()?;
@@ -1068,15 +1632,19 @@ impl Conn {
                     let column = ParseBuf(&pld).parse(())?;
                     columns.push(column);
                 }
-                // skip eof packet
-                self.drop_packet()?;
-                self.0.has_results = column_count > 0;
+                // skip eof packet (not sent if CLIENT_DEPRECATE_EOF is negotiated)
+                if !self.has_capability(CapabilityFlags::CLIENT_DEPRECATE_EOF) {
+                    self.drop_packet()?;
+                }
+                self.0.has_results = true;
                 Ok(Or::A(columns))
             }
         }
     }
 
     fn _query(&mut self, query: &str) -> Result<Or<Vec<Column>, OkPacket<'static>>> {
+        check_query_not_empty(query.as_bytes())?;
+        self.forget_sql_mode_if_mentioned(query.as_bytes());
         self.write_command(Command::COM_QUERY, query.as_bytes())?;
         self.handle_result_set()
     }
@@ -1088,46 +1656,343 @@ impl Conn {
         self.drop_packet()
     }
 
-    /// Executes [`COM_INIT_DB`](https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_com_init_db.html)
-    /// on `Conn`.
-    pub fn select_db(&mut self, schema: &str) -> Result<(), Error> {
-        self.write_command(Command::COM_INIT_DB, schema.as_bytes())?;
-        self.drop_packet()
+    /// Keeps the connection alive, so that the server won't close it due to `wait_timeout`.
+    ///
+    /// This is the same `COM_PING` as [`Conn::ping`], it is cheap enough to be called on a timer
+    /// (see [`crate::PoolOpts::with_idle_heartbeat`]).
+    pub fn touch(&mut self) -> Result<(), Error> {
+        self.ping()
     }
 
-    /// Starts new transaction with provided options.
-    /// `readonly` is only available since MySQL 5.6.5.
-    pub fn start_transaction(&mut self, tx_opts: TxOpts) -> Result<Transaction> {
-        self._start_transaction(tx_opts)?;
-        Ok(Transaction::new(self.into()))
+    /// Same as [`Conn::ping`] but fails if server won't respond within the given `timeout`.
+    ///
+    /// ## Note
+    ///
+    /// It temporarily replaces the read timeout of the underlying socket, and then restores
+    /// the value of [`Opts::get_read_timeout`] (even if ping fails). If ping fails, then
+    /// the connection is marked as broken (e.g. after a timeout the late response would be
+    /// out of sync), so every subsequent command will fail and a pooled connection
+    /// will be discarded.
+    pub fn ping_with_timeout(&mut self, timeout: Duration) -> Result<(), Error> {
+        let read_timeout = self.0.opts.get_read_timeout().cloned();
+        self.stream_mut()
+            .get_mut()
+            .set_read_timeout(Some(timeout))?;
+        let result = self.ping();
+        let restored = self.stream_mut().get_mut().set_read_timeout(read_timeout);
+        if result.is_err() {
+            self.mark_broken();
+        }
+        result?;
+        Ok(restored?)
     }
 
-    fn _true_prepare(&mut self, query: &[u8]) -> Result<InnerStmt> {
-        self.write_command(Command::COM_STMT_PREPARE, query)?;
-        let pld = self.read_packet()?;
-        let mut stmt = ParseBuf(&pld).parse::<InnerStmt>(self.connection_id())?;
-        if stmt.num_params() > 0 {
-            let mut params: Vec<Column> = Vec::with_capacity(stmt.num_params() as usize);
-            for _ in 0..stmt.num_params() {
-                let pld = self.read_packet()?;
-                params.push(ParseBuf(&pld).parse(())?);
-            }
-            stmt = stmt.with_params(Some(params));
-            self.drop_packet()?;
-        }
-        if stmt.num_columns() > 0 {
-            let mut columns: Vec<Column> = Vec::with_capacity(stmt.num_columns() as usize);
-            for _ in 0..stmt.num_columns() {
-                let pld = self.read_packet()?;
-                columns.push(ParseBuf(&pld).parse(())?);
-            }
-            stmt = stmt.with_columns(Some(columns));
-            self.drop_packet()?;
-        }
-        Ok(stmt)
+    /// Executes [`COM_STMT_RESET`](https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_com_stmt_reset.html)
+    /// for the given statement.
+    ///
+    /// Server discards long data buffered for the statement and closes its cursor, if any.
+    pub fn reset_stmt(&mut self, stmt: &Statement) -> Result<(), Error> {
+        self.write_command(Command::COM_STMT_RESET, &stmt.id().to_le_bytes())?;
+        self.drop_packet()
+    }
+
+    /// Same as [`Queryable::exec_iter`] but issues [`Conn::reset_stmt`] before the execution.
+    ///
+    /// Large `Bytes` params are sent as long data on every execution anyway, so this guarantees
+    /// that nothing is left buffered on the server side for the statement (e.g. after
+    /// an interrupted execution) at the cost of an extra round trip.
+    pub fn exec_fresh<S, P>(
+        &mut self,
+        stmt: S,
+        params: P,
+    ) -> Result<QueryResult<'_, '_, '_, Binary>>
+    where
+        S: AsStatement,
+        P: Into<Params>,
+    {
+        let statement = stmt.as_statement(self)?.into_owned();
+        self.reset_stmt(&statement)?;
+        self.exec_iter(statement, params)
+    }
+
+    /// Executes [`COM_DEBUG`](https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_com_debug.html)
+    /// on `Conn`.
+    ///
+    /// Server will dump debug information to its error log. Requires the `SUPER` privilege,
+    /// otherwise server error will be returned as is.
+    pub fn dump_debug_info(&mut self) -> Result<(), Error> {
+        self.write_command(Command::COM_DEBUG, &[])?;
+        self.drop_packet()
+    }
+
+    /// Executes [`COM_INIT_DB`](https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_com_init_db.html)
+    /// on `Conn`.
+    pub fn select_db(&mut self, schema: &str) -> Result<(), Error> {
+        self.write_command(Command::COM_INIT_DB, schema.as_bytes())?;
+        let packet = self.read_packet()?;
+        self.0.current_db = Some(schema.into());
+        self.handle_ok::<CommonOkPacket>(&packet).map(drop)
+    }
+
+    /// Runs `f` and retries it on a deadlock (`ER_LOCK_DEADLOCK`) or a lock wait timeout
+    /// (`ER_LOCK_WAIT_TIMEOUT`), at most `attempts` times in total (but at least once).
+    ///
+    /// Before the next attempt an active transaction is rolled back and the thread sleeps
+    /// for a backoff delay (10ms, doubled on every attempt). Other errors and the error
+    /// of the last attempt are returned as is.
+    ///
+    /// So `f` should perform the whole unit of work, including the transaction start.
+    pub fn with_retry<T, F>(&mut self, attempts: u32, mut f: F) -> Result<T>
+    where
+        F: FnMut(&mut Conn) -> Result<T>,
+    {
+        let mut backoff = RETRY_BACKOFF;
+        for _ in 1..attempts {
+            match f(self) {
+                Err(MySqlError(ref err))
+                    if err.code == ER_LOCK_DEADLOCK || err.code == ER_LOCK_WAIT_TIMEOUT =>
+                {
+                    if self.server_status().in_transaction() {
+                        self.query_drop("ROLLBACK")?;
+                    }
+                    std::thread::sleep(backoff);
+                    backoff *= 2;
+                }
+                result => return result,
+            }
+        }
+        f(self)
+    }
+
+    /// Inserts `rows` into `table` using multi-row `INSERT` statements
+    /// with up to `chunk_size` rows each. Returns the total number of affected rows.
+    ///
+    /// Values are rendered into the query text (see [`Value::as_sql`]), and a statement
+    /// is sent earlier if the next row would exceed `max_allowed_packet`.
+    ///
+    /// ## Note
+    ///
+    /// `table` and `columns` are inserted into the query text as is, so they must be
+    /// trusted (and quoted, if necessary).
+    pub fn insert_batch(
+        &mut self,
+        table: &str,
+        columns: &[&str],
+        rows: &[Vec<Value>],
+        chunk_size: usize,
+    ) -> Result<u64> {
+        let chunk_size = cmp::max(chunk_size, 1);
+        // command byte is a part of the packet
+        let max_len = self.stream_ref().codec().max_allowed_packet - 1;
+        let no_backslash_escape = self.no_backslash_escape();
+
+        let head = format!("INSERT INTO {} ({}) VALUES ", table, columns.join(", "));
+        let mut query = head.clone();
+        let mut chunk_len = 0;
+        let mut affected_rows = 0;
+
+        for row in rows {
+            let values = row
+                .iter()
+                .map(|value| value.as_sql(no_backslash_escape))
+                .collect::<Vec<_>>();
+            let tuple = format!("({})", values.join(", "));
+
+            if chunk_len > 0 && (chunk_len == chunk_size || query.len() + 1 + tuple.len() > max_len)
+            {
+                affected_rows += self.query_iter(&query)?.affected_rows();
+                query.truncate(head.len());
+                chunk_len = 0;
+            }
+
+            if chunk_len > 0 {
+                query.push(',');
+            }
+            query.push_str(&tuple);
+            chunk_len += 1;
+        }
+
+        if chunk_len > 0 {
+            affected_rows += self.query_iter(&query)?.affected_rows();
+        }
+
+        Ok(affected_rows)
+    }
+
+    /// Executes the given `stmt` with parameters of explicitly given MySql types.
+    ///
+    /// Usually the type of a parameter is inferred from the `Value` variant
+    /// (e.g. `Value::Bytes` is sent as `MYSQL_TYPE_VAR_STRING`), this method allows
+    /// to override it (e.g. to send `Value::Bytes` as `MYSQL_TYPE_JSON`). Values are
+    /// serialized as usual, so the type must be compatible with the value encoding.
+    pub fn exec_typed<S: AsStatement>(
+        &mut self,
+        stmt: S,
+        params: &[(Value, ColumnType)],
+    ) -> Result<QueryResult<'_, '_, '_, Binary>> {
+        let stmt = stmt.as_statement(self)?;
+        let (values, types): (Vec<_>, Vec<_>) = params.iter().cloned().unzip();
+
+        let start = Instant::now();
+        let meta = self._execute(&stmt, Params::Positional(values), Some(&types));
+        self.trace(stmt.inner.query(), start);
+        Ok(QueryResult::new(ConnMut::Mut(self), meta?))
+    }
+
+    /// Performs a text query, that is expected to not return rows (e.g. `UPDATE`),
+    /// and returns its OK packet (affected rows, last insert id, status flags,
+    /// warnings and info).
+    ///
+    /// Fails with [`crate::DriverError::UnexpectedPacket`] if the server sends
+    /// a result set instead (the result set is dropped). Subsequent results of
+    /// a multi-statement query are dropped.
+    pub fn query_ok<Q: AsRef<str>>(&mut self, query: Q) -> Result<OkPacket<'static>> {
+        let start = Instant::now();
+        let meta = self._query(query.as_ref());
+        self.trace(query.as_ref().as_bytes(), start);
+        let meta = meta?;
+        let ok = match meta {
+            Or::A(_) => None,
+            Or::B(ref ok) => Some(ok.clone()),
+        };
+        drop(QueryResult::<Text>::new(ConnMut::Mut(self), meta));
+        ok.ok_or(DriverError(UnexpectedPacket))
+    }
+
+    /// Performs a text query, that is limited to `ms` milliseconds of execution time
+    /// on the server side, and collects its first result set.
+    ///
+    /// For MySql 5.7.8+ a `SELECT` statement gets the `/*+ MAX_EXECUTION_TIME(ms) */`
+    /// optimizer hint. For MariaDb 10.1.2+ any statement is executed as
+    /// `SET STATEMENT max_statement_time = .. FOR ..`.
+    ///
+    /// ## Note
+    ///
+    /// MySql only enforces the execution time of read-only `SELECT` statements,
+    /// so other statements (and any statement on older servers) are executed unbounded.
+    /// The server reports the timeout as the `ER_QUERY_TIMEOUT` (3024) error
+    /// (`ER_STATEMENT_TIMEOUT` (1969) for MariaDb), but note that a query, that was
+    /// interrupted while producing its last row, might return normally
+    /// (e.g. `SELECT SLEEP(5)` returns `1`).
+    pub fn query_with_timeout<T: FromRow>(&mut self, query: &str, ms: u32) -> Result<Vec<T>> {
+        let trimmed = query.trim_start();
+        let is_select = trimmed
+            .get(..6)
+            .map(|keyword| keyword.eq_ignore_ascii_case("SELECT"))
+            .unwrap_or(false)
+            && !trimmed[6..].starts_with(|c: char| c.is_alphanumeric() || c == '_');
+
+        match (self.0.server_version, self.0.mariadb_server_version) {
+            (Some(version), _) if version >= (5, 7, 8) && is_select => {
+                let query = format!(
+                    "{} /*+ MAX_EXECUTION_TIME({}) */{}",
+                    &trimmed[..6],
+                    ms,
+                    &trimmed[6..]
+                );
+                self.query(query)
+            }
+            (_, Some(version)) if version >= (10, 1, 2) => {
+                let query = format!(
+                    "SET STATEMENT max_statement_time = {}.{:03} FOR {}",
+                    ms / 1000,
+                    ms % 1000,
+                    trimmed
+                );
+                self.query(query)
+            }
+            _ => self.query(query),
+        }
+    }
+
+    /// Executes [`COM_FIELD_LIST`](https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_com_field_list.html)
+    /// on `Conn`.
+    ///
+    /// Returns definitions of columns of the given `table`, that matches the given `wildcard`
+    /// (empty `wildcard` matches every column), along with their default values.
+    ///
+    /// ## Note
+    ///
+    /// `COM_FIELD_LIST` is deprecated since MySql 5.7.11.
+    pub fn list_fields(
+        &mut self,
+        table: &str,
+        wildcard: &str,
+    ) -> Result<Vec<(Column, Option<Vec<u8>>)>> {
+        let mut data = Vec::with_capacity(table.len() + 1 + wildcard.len());
+        data.extend_from_slice(table.as_bytes());
+        data.push(0);
+        data.extend_from_slice(wildcard.as_bytes());
+        self.write_command(Command::COM_FIELD_LIST, &data)?;
+
+        let mut fields = Vec::new();
+        loop {
+            let pld = self.read_packet()?;
+
+            if self.has_capability(CapabilityFlags::CLIENT_DEPRECATE_EOF) {
+                if pld[0] == 0xfe && pld.len() < MAX_PAYLOAD_LEN {
+                    self.handle_ok::<ResultSetTerminator>(&pld)?;
+                    break;
+                }
+            } else if pld[0] == 0xfe && pld.len() < 8 {
+                self.handle_ok::<OldEofPacket>(&pld)?;
+                break;
+            }
+
+            let mut buf = ParseBuf(&pld);
+            let column: Column = buf.parse(())?;
+            // column definition is followed by the lenenc default value
+            let mut rest = buf.0;
+            let default_value = match rest.first().copied() {
+                None | Some(0xfb) => None,
+                Some(_) => {
+                    let len = rest.read_lenenc_int()? as usize;
+                    match rest.get(..len) {
+                        Some(default_value) => Some(default_value.to_vec()),
+                        None => return Err(DriverError(UnexpectedPacket)),
+                    }
+                }
+            };
+            fields.push((column, default_value));
+        }
+
+        Ok(fields)
+    }
+
+    /// Starts new transaction with provided options.
+    /// `readonly` is only available since MySQL 5.6.5.
+    pub fn start_transaction(&mut self, tx_opts: TxOpts) -> Result<Transaction> {
+        self._start_transaction(tx_opts)?;
+        Ok(Transaction::new(self.into()))
+    }
+
+    fn _true_prepare(&mut self, query: &[u8]) -> Result<InnerStmt> {
+        self.write_command(Command::COM_STMT_PREPARE, query)?;
+        let pld = self.read_packet()?;
+        let mut stmt = ParseBuf(&pld)
+            .parse::<InnerStmt>(self.connection_id())?
+            .with_query(query);
+        let deprecate_eof = self.has_capability(CapabilityFlags::CLIENT_DEPRECATE_EOF);
+        if stmt.num_params() > 0 {
+            let params =
+                read_stmt_definitions("parameter", stmt.num_params(), deprecate_eof, || {
+                    self.read_packet()
+                })?;
+            stmt = stmt.with_params(Some(params));
+        }
+        if stmt.num_columns() > 0 {
+            let columns =
+                read_stmt_definitions("column", stmt.num_columns(), deprecate_eof, || {
+                    self.read_packet()
+                })?;
+            stmt = stmt.with_columns(Some(columns));
+        }
+        Ok(stmt)
     }
 
     fn _prepare(&mut self, query: &[u8]) -> Result<Arc<InnerStmt>> {
+        check_query_not_empty(query)?;
+
         if let Some(entry) = self.0.stmt_cache.by_query(query) {
             return Ok(entry.stmt.clone());
         }
@@ -1139,7 +2004,12 @@ impl Conn {
             .stmt_cache
             .put(Arc::new(query.into()), inner_st.clone())
         {
-            self.close(Statement::new(old_stmt, None))?;
+            // statement may still be in use, so it'll be closed once unreferenced
+            self.0.stmt_cache.retain_evicted(old_stmt);
+        }
+
+        for stmt in self.0.stmt_cache.take_unreferenced() {
+            self.close(Statement::new(stmt, None))?;
         }
 
         Ok(inner_st)
@@ -1149,27 +2019,60 @@ impl Conn {
         if self.0.connected {
             return Ok(());
         }
-        self.do_handshake()
-            .and_then(|_| match self.0.opts.get_max_allowed_packet() {
-                Some(x) => Ok(x),
-                None => Ok(from_value_opt::<usize>(
-                    self.get_system_var("max_allowed_packet")?.unwrap_or(NULL),
-                )
-                .unwrap_or(0)),
-            })
-            .and_then(|max_allowed_packet| {
-                if max_allowed_packet == 0 {
-                    Err(DriverError(SetupError))
-                } else {
-                    self.stream_mut().codec_mut().max_allowed_packet = max_allowed_packet;
-                    self.0.connected = true;
-                    Ok(())
-                }
-            })
+        self.do_handshake()?;
+        // commands are allowed from now on
+        self.0.connected = true;
+        let max_allowed_packet = match self.0.opts.get_max_allowed_packet() {
+            Some(x) => x,
+            None if self.0.opts.get_skip_setup_queries() => MAX_PAYLOAD_LEN,
+            None => {
+                from_value_opt::<usize>(self.get_system_var("max_allowed_packet")?.unwrap_or(NULL))
+                    .unwrap_or(0)
+            }
+        };
+        if max_allowed_packet == 0 {
+            return Err(DriverError(SetupError));
+        }
+        self.stream_mut().codec_mut().max_allowed_packet = max_allowed_packet;
+        Ok(())
     }
 
     fn get_system_var(&mut self, name: &str) -> Result<Option<Value>> {
-        self.query_first(format!("SELECT @@{}", name))
+        self.query_scalar(&format!("SELECT @@{}", name))
+    }
+
+    /// Performs text query and returns the first value of the first row of the first
+    /// result set (`None` if there are no rows, whereas SQL `NULL` is `Some(Value::NULL)`).
+    ///
+    /// Same as `query_first::<Value, _>` but faster for queries like `SELECT 1`,
+    /// `SELECT LAST_INSERT_ID()` or `SELECT @@var`, because the value is read from
+    /// the row packet directly, without building a [`crate::Row`]. Remaining rows and result
+    /// sets are dropped.
+    pub fn query_scalar(&mut self, query: &str) -> Result<Option<Value>> {
+        let start = Instant::now();
+        let meta = self._query(query);
+        self.trace(query.as_bytes(), start);
+
+        let value = match meta? {
+            Or::A(columns) if !columns.is_empty() => match self.next_row_packet()? {
+                Some(pld) => Some(first_text_value(&pld)?),
+                None => None,
+            },
+            _ => None,
+        };
+        self.drop_results()?;
+        Ok(value)
+    }
+
+    /// Drops rows of the current result set and all the remaining result sets.
+    fn drop_results(&mut self) -> Result<()> {
+        loop {
+            while self.next_row_packet()?.is_some() {}
+            if !self.more_results_exists() {
+                return Ok(());
+            }
+            self.handle_result_set()?;
+        }
     }
 
     fn next_row_packet(&mut self) -> Result<Option<Buffer>> {
@@ -1191,6 +2094,7 @@ impl Conn {
             return Ok(None);
         }
 
+        self.0.stats.rows += 1;
         Ok(Some(pld))
     }
 
@@ -1208,6 +2112,83 @@ impl Conn {
         self.0.local_infile_handler = handler;
     }
 
+    /// Loads CSV `data` into `table` using `LOAD DATA LOCAL INFILE`.
+    ///
+    /// Fields are separated by `,` and optionally enclosed in `"`, lines are separated
+    /// by `\n`. The data is sent by a temporary handler, that replaces the one set
+    /// via [`Conn::set_local_infile_handler`] until this call returns. The data is sent
+    /// at most once, so a repeated request gets an empty file.
+    ///
+    /// ## Note
+    ///
+    /// `table` is inserted into the query text as is, so it must be trusted
+    /// (and quoted, if necessary). The server must allow `local_infile`.
+    pub fn load_data_from_bytes(&mut self, table: &str, data: &[u8]) -> Result<()> {
+        let mut data = Some(data.to_vec());
+        let handler = LocalInfileHandler::new(move |_, local_infile| {
+            if let Some(data) = data.take() {
+                local_infile.write_all(&data)?;
+            }
+            Ok(())
+        });
+        let previous = self.0.local_infile_handler.replace(handler);
+        let result = self.query_drop(format!(
+            "LOAD DATA LOCAL INFILE 'buffer' INTO TABLE {} \
+             FIELDS TERMINATED BY ',' OPTIONALLY ENCLOSED BY '\"' \
+             LINES TERMINATED BY '\\n'",
+            table
+        ));
+        self.0.local_infile_handler = previous;
+        result
+    }
+
+    /// Sets a callback that will be called with the SQL text and the elapsed time
+    /// after every query, statement execution and statement preparation.
+    ///
+    /// The elapsed time covers the command round trip, i.e. it ends as soon as the
+    /// result set metadata (or the OK packet) is received, so it does not include
+    /// the time spent reading rows. Executed statements are reported using their
+    /// prepared form (named parameters are replaced with `?`).
+    ///
+    /// Specifying `None` will remove the handler.
+    pub fn set_trace_handler(&mut self, handler: Option<TraceHandler>) {
+        self.0.tracer = Tracer(handler);
+    }
+
+    /// Calls the trace handler, if any.
+    fn trace(&self, query: &[u8], start: Instant) {
+        // handler is cloned, so that it may freely use other connections
+        if let Some(handler) = self.0.tracer.0.clone() {
+            handler(&String::from_utf8_lossy(query), start.elapsed());
+        }
+    }
+
+    /// Sets the default rendering mode of `Value::Bytes` for this connection
+    /// (defaults to [`BytesDisplay::Auto`]).
+    ///
+    /// The connection itself doesn't render values, this mode is meant to be passed
+    /// to [`ValueExt::display_with`] by the code, that logs values of this connection.
+    pub fn set_bytes_display(&mut self, mode: BytesDisplay) {
+        self.0.bytes_display = mode;
+    }
+
+    /// Returns counters of this connection (queries, rows, packets, etc.).
+    ///
+    /// Useful to spot chatty code paths, e.g. by comparing snapshots taken
+    /// before and after some code.
+    pub fn stats(&self) -> ConnStats {
+        self.0.stats
+    }
+
+    /// Returns the default rendering mode of `Value::Bytes` (see [`Conn::set_bytes_display`]).
+    pub fn bytes_display(&self) -> BytesDisplay {
+        self.0.bytes_display
+    }
+
+    /// Returns `true` if the `NO_BACKSLASH_ESCAPES` SQL mode is active.
+    ///
+    /// It is the status flag reported by the server with every OK packet, so it never needs
+    /// a query (unlike [`Conn::sql_mode`]) and is always up to date.
     pub fn no_backslash_escape(&self) -> bool {
         self.0
             .status_flags
@@ -1248,6 +2229,7 @@ impl Conn {
 
     fn cleanup_for_pool(&mut self) -> Result<()> {
         self.set_local_infile_handler(None);
+        self.set_trace_handler(None);
         if self.0.reset_upon_return {
             self.reset()?;
         }
@@ -1267,8 +2249,10 @@ impl AsRawFd for Conn {
 
 impl Queryable for Conn {
     fn query_iter<T: AsRef<str>>(&mut self, query: T) -> Result<QueryResult<'_, '_, '_, Text>> {
-        let meta = self._query(query.as_ref())?;
-        Ok(QueryResult::new(ConnMut::Mut(self), meta))
+        let start = Instant::now();
+        let meta = self._query(query.as_ref());
+        self.trace(query.as_ref().as_bytes(), start);
+        Ok(QueryResult::new(ConnMut::Mut(self), meta?))
     }
 
     fn prep<T: AsRef<str>>(&mut self, query: T) -> Result<Statement> {
@@ -1281,8 +2265,10 @@ impl Queryable for Conn {
         } else {
             Some(named_params)
         };
-        self._prepare(parsed.borrow().query())
-            .map(|inner| Statement::new(inner, named_params))
+        let start = Instant::now();
+        let inner = self._prepare(parsed.borrow().query());
+        self.trace(query.as_bytes(), start);
+        inner.map(|inner| Statement::new(inner, named_params))
     }
 
     fn close(&mut self, stmt: Statement) -> Result<()> {
@@ -1297,8 +2283,10 @@ impl Queryable for Conn {
         P: Into<Params>,
     {
         let statement = stmt.as_statement(self)?;
-        let meta = self._execute(&statement, params.into())?;
-        Ok(QueryResult::new(ConnMut::Mut(self), meta))
+        let start = Instant::now();
+        let meta = self._execute(&statement, params.into(), None);
+        self.trace(statement.inner.query(), start);
+        Ok(QueryResult::new(ConnMut::Mut(self), meta?))
     }
 }
 
@@ -1306,11 +2294,12 @@ impl Drop for Conn {
     fn drop(&mut self) {
         let stmt_cache = mem::replace(&mut self.0.stmt_cache, StmtCache::new(0));
 
-        for (_, entry) in stmt_cache.into_iter() {
-            let _ = self.close(Statement::new(entry.stmt, None));
+        for stmt in stmt_cache.into_iter() {
+            let _ = self.close(Statement::new(stmt, None));
         }
 
-        if self.0.stream.is_some() {
+        // Best-effort COM_QUIT, so that server won't count this connection as aborted.
+        if self.0.stream.is_some() && self.0.connected && !self.0.has_results {
             let _ = self.write_command(Command::COM_QUIT, &[]);
         }
     }
@@ -1336,13 +2325,17 @@ mod test {
         use time::PrimitiveDateTime;
 
         use crate::{
+            consts::MAX_PAYLOAD_LEN,
             from_row, from_value, params,
             prelude::*,
             test_misc::get_opts,
             Conn,
-            DriverError::{MissingNamedParameter, NamedParamsForPositionalQuery},
+            DriverError::{
+                MissingNamedParameter, NamedParamsForPositionalQuery, NoAddress, PacketTooLarge,
+                UnexpectedPacket,
+            },
             Error::DriverError,
-            LocalInfileHandler, Opts, OptsBuilder, Pool, TxOpts,
+            LocalInfileHandler, Opts, OptsBuilder, Params, Pool, TxOpts,
             Value::{self, Bytes, Date, Float, Int, NULL},
         };
 
@@ -1513,6 +2506,71 @@ mod test {
             let _ = Conn::new(opts).unwrap();
         }
 
+        #[test]
+        #[cfg(unix)]
+        fn should_keep_tcp_connection_if_socket_auth_fails() {
+            use std::os::unix::net::{UnixListener, UnixStream};
+
+            let path = std::env::temp_dir().join(format!("mysql-{}-rejecting.sock", process::id()));
+            let _ = std::fs::remove_file(&path);
+            let listener = UnixListener::bind(&path).unwrap();
+            let rejecting_server = spawn(move || {
+                // "Access denied" error instead of the handshake
+                let err = b"\xff\x15\x04#28000Access denied";
+                let mut packet = vec![err.len() as u8, 0, 0, 0];
+                packet.extend_from_slice(err);
+                let (mut stream, _) = listener.accept().unwrap();
+                stream.write_all(&packet).unwrap();
+            });
+
+            let mut opts = Opts::from(get_opts());
+            opts.0.injected_socket = Some(path.to_string_lossy().into_owned());
+            let mut conn = Conn::new(opts.clone()).unwrap();
+
+            let peer_addr = conn.stream_ref().get_ref().peer_addr();
+            let probed = super::super::should_probe_socket(&opts, peer_addr);
+            // unblock the rejecting server, if it wasn't reached
+            let _client = (!probed).then(|| UnixStream::connect(&path).unwrap());
+            rejecting_server.join().unwrap();
+            let _ = std::fs::remove_file(&path);
+            if !probed {
+                // e.g. a socket connection or a remote server, so there is nothing to test
+                return;
+            }
+
+            assert!(conn.is_tcp());
+            assert!(!conn.0.socket_probed);
+            assert_eq!(conn.query_first("SELECT 1").unwrap(), Some(1_u8));
+        }
+
+        #[test]
+        fn should_fail_without_address() {
+            let opts = OptsBuilder::new().ip_or_hostname(Some(""));
+            match Conn::new(opts) {
+                Err(DriverError(NoAddress)) => (),
+                other => panic!("NoAddress error expected, got {:?}", other),
+            }
+
+            let opts = Opts::from(OptsBuilder::new().ip_or_hostname(None::<String>));
+            assert_eq!(opts.get_ip_or_hostname(), "127.0.0.1");
+        }
+
+        #[test]
+        fn should_not_reveal_password_in_debug_output() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            let stmt = conn.prep("SELECT ?, ?").unwrap();
+            let debug = format!("{:?}", stmt);
+            assert!(debug.contains("num_params: 2"));
+            assert!(debug.contains("num_columns: 2"));
+
+            let debug = format!("{:?}", conn);
+            assert!(debug.contains(&conn.connection_id().to_string()));
+            let opts = Opts::from(get_opts());
+            if let Some(pass) = opts.get_pass().filter(|pass| !pass.is_empty()) {
+                assert!(!debug.contains(pass));
+            }
+        }
+
         #[test]
         fn should_connect_with_database() {
             const DB_NAME: &str = "mysql";
@@ -1532,6 +2590,116 @@ mod test {
             assert!(conn.ping().is_ok());
         }
 
+        #[test]
+        fn should_not_reuse_connection_after_failed_read() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            conn.stream_mut()
+                .get_mut()
+                .set_read_timeout(Some(Duration::from_millis(100)))
+                .unwrap();
+            match conn.query_drop("DO SLEEP(1)") {
+                Err(crate::Error::IoError(_)) => (),
+                other => panic!("IoError expected, got {:?}", other),
+            }
+            match conn.query_drop("SELECT 1") {
+                Err(crate::Error::IoError(e)) if e.kind() == std::io::ErrorKind::BrokenPipe => (),
+                other => panic!("connection lost error expected, got {:?}", other),
+            }
+            assert!(conn.ping().is_err());
+        }
+
+        #[test]
+        fn should_limit_result_rows() {
+            let mut conn = Conn::new(get_opts().max_result_rows(Some(10))).unwrap();
+            conn.query_drop("CREATE TEMPORARY TABLE mysql.tbl (id INT)")
+                .unwrap();
+            conn.exec_batch(
+                "INSERT INTO mysql.tbl (id) VALUES (?)",
+                (0..20).map(|x| (x,)),
+            )
+            .unwrap();
+
+            let mut result = conn.query_iter("SELECT * FROM mysql.tbl").unwrap();
+            for _ in 0..10 {
+                result.next().unwrap().unwrap();
+            }
+            match result.next() {
+                Some(Err(DriverError(crate::DriverError::TooManyRows(10)))) => (),
+                other => panic!("TooManyRows expected, got {:?}", other),
+            }
+            assert!(result.next().is_none());
+            drop(result);
+
+            // connection is unusable because of the rest of the result set
+            assert!(conn.ping().is_err());
+
+            let mut conn = Conn::new(get_opts().max_result_rows(Some(20))).unwrap();
+            let rows: Vec<u8> = conn.query("SELECT 1 UNION ALL SELECT 2").unwrap();
+            assert_eq!(rows, vec![1, 2]);
+        }
+
+        #[test]
+        fn should_ping_with_timeout() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            conn.ping_with_timeout(Duration::from_secs(10)).unwrap();
+            conn.query_drop("DO 1").unwrap();
+
+            let opts = get_opts().read_timeout(Some(Duration::from_secs(30)));
+            let mut conn = Conn::new(opts).unwrap();
+            conn.ping_with_timeout(Duration::from_secs(10)).unwrap();
+            assert_eq!(conn.query_first::<u8, _>("SELECT 1").unwrap(), Some(1));
+        }
+
+        #[test]
+        fn should_touch() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            conn.touch().unwrap();
+            conn.touch().unwrap();
+            assert_eq!(conn.query_first::<u8, _>("SELECT 1").unwrap(), Some(1));
+        }
+
+        #[test]
+        fn should_dump_debug_info() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            match conn.dump_debug_info() {
+                Ok(()) => (),
+                // ER_SPECIFIC_ACCESS_DENIED_ERROR
+                Err(crate::Error::MySqlError(ref err)) if err.code == 1227 => return,
+                Err(err) => panic!("ERROR {}", err),
+            }
+            assert!(conn.ping().is_ok());
+        }
+
+        #[test]
+        fn should_list_fields() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            conn.query_drop(
+                "CREATE TEMPORARY TABLE mysql.tbl (a INT DEFAULT 42, b VARCHAR(8) DEFAULT 'foo')",
+            )
+            .unwrap();
+            conn.select_db("mysql").unwrap();
+            let fields = match conn.list_fields("tbl", "") {
+                Ok(fields) => fields,
+                // ER_UNKNOWN_COM_ERROR
+                Err(crate::Error::MySqlError(ref err)) if err.code == 1047 => return,
+                Err(err) => panic!("ERROR {}", err),
+            };
+            let names = fields
+                .iter()
+                .map(|(column, _)| column.name_str().into_owned())
+                .collect::<Vec<_>>();
+            assert_eq!(names, vec!["a", "b"]);
+            let defaults = fields
+                .iter()
+                .map(|(_, default_value)| default_value.clone())
+                .collect::<Vec<_>>();
+            assert_eq!(defaults, vec![Some(b"42".to_vec()), Some(b"foo".to_vec())]);
+
+            let fields = conn.list_fields("tbl", "b%").unwrap();
+            assert_eq!(fields.len(), 1);
+            assert!(conn.ping().is_ok());
+        }
+
         #[test]
         fn should_select_db() {
             const DB_NAME: &str = "t_select_db";
@@ -1612,969 +2780,3421 @@ mod test {
         }
 
         #[test]
-        fn should_parse_large_text_result() {
+        fn should_keep_bytes_display_mode() {
+            use crate::BytesDisplay;
+
             let mut conn = Conn::new(get_opts()).unwrap();
-            let value: Value = conn
-                .query_first("SELECT REPEAT('A', 20000000)")
-                .unwrap()
-                .unwrap();
-            assert_eq!(value, Bytes(iter::repeat(b'A').take(20_000_000).collect()));
+            assert_eq!(conn.bytes_display(), BytesDisplay::Auto);
+            conn.set_bytes_display(BytesDisplay::HexAlways);
+            conn.reconnect().unwrap();
+            assert_eq!(conn.bytes_display(), BytesDisplay::HexAlways);
+
+            let value: Value = conn.query_first("SELECT 'foo'").unwrap().unwrap();
+            assert_eq!(
+                value.display_with(conn.bytes_display()).to_string(),
+                "0x666F6F"
+            );
         }
 
         #[test]
-        fn should_execute_statements_and_parse_results() {
-            const CREATE_QUERY: &str = r"CREATE TEMPORARY TABLE
-                mysql.tbl (a TEXT, b INT, c INT UNSIGNED, d DATE, e FLOAT)";
-            const INSERT_STMT: &str = r"INSERT
-                INTO mysql.tbl (a, b, c, d, e)
-                VALUES (?, ?, ?, ?, ?)";
+        fn should_reject_empty_queries() {
+            fn status(conn: &mut Conn, name: &str) -> u64 {
+                let query = format!("SHOW SESSION STATUS LIKE '{}'", name);
+                let (_, value): (String, u64) = conn.query_first(query).unwrap().unwrap();
+                value
+            }
 
-            type RowType = (Value, Value, Value, Value, Value);
+            let mut conn = Conn::new(get_opts()).unwrap();
 
-            let row1 = (
-                Bytes(b"hello".to_vec()),
-                Int(-123_i64),
-                Int(123_i64),
-                Date(2014_u16, 5_u8, 5_u8, 0_u8, 0_u8, 0_u8, 0_u32),
-                Float(123.123_f32),
-            );
-            let row2 = (Bytes(b"".to_vec()), NULL, NULL, NULL, Float(321.321_f32));
+            let prepares = status(&mut conn, "Com_stmt_prepare");
+            let questions = status(&mut conn, "Questions");
+            for query in ["", " ", " \t\r\n"] {
+                match conn.prep(query) {
+                    Err(DriverError(crate::DriverError::EmptyQuery)) => (),
+                    other => panic!("EmptyQuery expected, got {:?}", other),
+                }
+                match conn.query_drop(query) {
+                    Err(DriverError(crate::DriverError::EmptyQuery)) => (),
+                    other => panic!("EmptyQuery expected, got {:?}", other),
+                }
+                match conn.exec_drop(query, ()) {
+                    Err(err) => assert_eq!(err.to_string(), "Query is empty"),
+                    other => panic!("EmptyQuery expected, got {:?}", other),
+                }
+            }
+            assert_eq!(status(&mut conn, "Com_stmt_prepare"), prepares);
+            // only the previous `SHOW SESSION STATUS` is counted
+            assert_eq!(status(&mut conn, "Questions"), questions + 1);
+        }
 
+        #[test]
+        fn should_cache_sql_mode() {
             let mut conn = Conn::new(get_opts()).unwrap();
-            conn.query_drop(CREATE_QUERY).unwrap();
+            let sql_mode: String = conn
+                .query_first("SELECT @@SESSION.sql_mode")
+                .unwrap()
+                .unwrap();
+            assert_eq!(conn.sql_mode().unwrap().join(","), sql_mode);
 
-            let insert_stmt = conn.prep(INSERT_STMT).unwrap();
-            assert_eq!(insert_stmt.connection_id(), conn.connection_id());
-            conn.exec_drop(
-                &insert_stmt,
-                (
-                    from_value::<String>(row1.0.clone()),
-                    from_value::<i32>(row1.1.clone()),
-                    from_value::<u32>(row1.2.clone()),
-                    from_value::<PrimitiveDateTime>(row1.3.clone()),
-                    from_value::<f32>(row1.4.clone()),
-                ),
-            )
-            .unwrap();
-            conn.exec_drop(
-                &insert_stmt,
-                (
-                    from_value::<String>(row2.0.clone()),
-                    row2.1.clone(),
-                    row2.2.clone(),
-                    row2.3.clone(),
-                    from_value::<f32>(row2.4.clone()),
-                ),
-            )
-            .unwrap();
+            conn.query_drop("SET SESSION sql_mode = 'NO_BACKSLASH_ESCAPES,ANSI_QUOTES'")
+                .unwrap();
+            assert_eq!(
+                conn.sql_mode().unwrap(),
+                vec![
+                    "ANSI_QUOTES".to_string(),
+                    "NO_BACKSLASH_ESCAPES".to_string()
+                ]
+            );
+            assert!(conn.no_backslash_escape());
 
-            let select_stmt = conn.prep("SELECT * from mysql.tbl").unwrap();
-            let rows: Vec<RowType> = conn.exec(&select_stmt, ()).unwrap();
+            conn.exec_drop("SET @@SQL_MODE = ?", ("",)).unwrap();
+            assert!(conn.sql_mode().unwrap().is_empty());
+            assert!(!conn.no_backslash_escape());
 
-            assert_eq!(rows, vec![row1, row2]);
+            conn.query_drop("SET SESSION sql_mode = 'ANSI_QUOTES'")
+                .unwrap();
+            assert_eq!(conn.sql_mode().unwrap(), vec!["ANSI_QUOTES".to_string()]);
+            conn.reset().unwrap();
+            let sql_mode: String = conn
+                .query_first("SELECT @@SESSION.sql_mode")
+                .unwrap()
+                .unwrap();
+            assert_eq!(conn.sql_mode().unwrap().join(","), sql_mode);
         }
 
         #[test]
-        fn should_parse_large_binary_result() {
+        fn should_count_stats() {
             let mut conn = Conn::new(get_opts()).unwrap();
-            let stmt = conn.prep("SELECT REPEAT('A', 20000000)").unwrap();
-            let value: Value = conn.exec_first(&stmt, ()).unwrap().unwrap();
-            assert_eq!(value, Bytes(iter::repeat(b'A').take(20_000_000).collect()));
+            let before = conn.stats();
+
+            let rows: Vec<u8> = conn.query("SELECT 1 UNION ALL SELECT 2").unwrap();
+            assert_eq!(rows, vec![1, 2]);
+            let rows: Vec<u8> = conn.exec("SELECT ?", (3,)).unwrap();
+            assert_eq!(rows, vec![3]);
+            conn.query_drop("DO 1").unwrap();
+
+            let after = conn.stats();
+            assert_eq!(after.queries() - before.queries(), 3);
+            assert_eq!(after.rows() - before.rows(), 3);
+            assert!(after.packets_sent() > before.packets_sent());
+            assert!(after.packets_received() > before.packets_received());
+            assert!(after.bytes_sent() > before.bytes_sent());
+            assert!(after.bytes_received() > before.bytes_received());
+            assert_eq!(after.errors(), before.errors());
+
+            conn.query_drop("SELECT * FROM unknown.unknown")
+                .unwrap_err();
+            assert_eq!(conn.stats().errors(), before.errors() + 1);
         }
 
         #[test]
-        fn manually_closed_stmt() {
-            let opts = get_opts().stmt_cache_size(1);
-            let mut conn = Conn::new(opts).unwrap();
-            let stmt = conn.prep("SELECT 1").unwrap();
-            conn.exec_drop(&stmt, ()).unwrap();
-            conn.close(stmt).unwrap();
-            let stmt = conn.prep("SELECT 1").unwrap();
-            conn.exec_drop(&stmt, ()).unwrap();
-            conn.close(stmt).unwrap();
-            let stmt = conn.prep("SELECT 2").unwrap();
-            conn.exec_drop(&stmt, ()).unwrap();
+        fn should_read_single_packet_for_ok_reply() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+
+            let before = conn.stats();
+            conn.query_drop("CREATE TEMPORARY TABLE mysql.tbl (id INT)")
+                .unwrap();
+            let after = conn.stats();
+            assert_eq!(after.packets_sent() - before.packets_sent(), 1);
+            assert_eq!(after.packets_received() - before.packets_received(), 1);
+
+            let stmt = conn.prep("INSERT INTO mysql.tbl (id) VALUES (?)").unwrap();
+            let before = conn.stats();
+            conn.exec_drop(&stmt, (1,)).unwrap();
+            let after = conn.stats();
+            assert_eq!(after.packets_sent() - before.packets_sent(), 1);
+            assert_eq!(after.packets_received() - before.packets_received(), 1);
+
+            let before = conn.stats();
+            let first: Option<u8> = conn.query_first("DO 1").unwrap();
+            assert_eq!(first, None);
+            let after = conn.stats();
+            assert_eq!(after.packets_received() - before.packets_received(), 1);
         }
 
         #[test]
-        fn should_start_commit_and_rollback_transactions() {
+        fn should_report_server_status() {
+            use crate::ServerStatus;
+
             let mut conn = Conn::new(get_opts()).unwrap();
-            conn.query_drop(
-                "CREATE TEMPORARY TABLE mysql.tbl(id INT NOT NULL PRIMARY KEY AUTO_INCREMENT, a INT)",
-            )
-            .unwrap();
-            conn.start_transaction(TxOpts::default())
-                .map(|mut t| {
-                    t.query_drop("INSERT INTO mysql.tbl(a) VALUES(1)").unwrap();
-                    assert_eq!(t.last_insert_id(), Some(1));
-                    assert_eq!(t.affected_rows(), 1);
-                    t.query_drop("INSERT INTO mysql.tbl(a) VALUES(2)").unwrap();
-                    t.commit().unwrap();
-                })
-                .unwrap();
+            assert!(conn.server_status().autocommit());
+
+            let result = conn.query_iter("DO 1; DO 2").unwrap();
+            assert!(ServerStatus::from(result.status()).more_results());
+            drop(result);
+            assert!(!conn.server_status().more_results());
+
+            conn.query_drop("START TRANSACTION").unwrap();
+            assert!(conn.server_status().in_transaction());
+            conn.query_drop("ROLLBACK").unwrap();
+            assert!(!conn.server_status().in_transaction());
+        }
+
+        #[test]
+        fn should_track_session_state() {
+            use std::sync::{
+                atomic::{AtomicUsize, Ordering},
+                Arc,
+            };
+
+            use crate::consts::CapabilityFlags;
+
+            let mut conn = Conn::new(get_opts()).unwrap();
+            if !conn.has_capability(CapabilityFlags::CLIENT_SESSION_TRACK) {
+                return;
+            }
+
+            let queries = Arc::new(AtomicUsize::new(0));
+            let counter = queries.clone();
+            conn.set_trace_handler(Some(Arc::new(move |_, _| {
+                counter.fetch_add(1, Ordering::SeqCst);
+            })));
+
+            conn.query_drop("USE mysql").unwrap();
+            assert_eq!(conn.current_db(), Some("mysql"));
+            conn.query_drop("SET time_zone = '+01:00'").unwrap();
+            assert_eq!(conn.session_variable("time_zone"), Some("+01:00"));
+            // state is taken from OK packets
+            assert_eq!(queries.load(Ordering::SeqCst), 2);
+
+            conn.select_db("information_schema").unwrap();
+            assert_eq!(conn.current_db(), Some("information_schema"));
+
+            conn.reset().unwrap();
+            assert_ne!(conn.session_variable("time_zone"), Some("+01:00"));
+        }
+
+        #[test]
+        fn should_call_trace_handler() {
+            use std::sync::{Arc, Mutex};
+
+            let traced = Arc::new(Mutex::new(Vec::new()));
+            let mut conn = Conn::new(get_opts()).unwrap();
+            conn.set_trace_handler(Some(Arc::new({
+                let traced = traced.clone();
+                move |query: &str, _elapsed: Duration| {
+                    traced.lock().unwrap().push(query.to_owned());
+                }
+            })));
+
+            conn.query_drop("SELECT 1").unwrap();
+            let stmt = conn.prep("SELECT :foo").unwrap();
+            conn.exec_drop(&stmt, params! { "foo" => 1 }).unwrap();
+            assert!(conn.query_drop("SELECT * FROM missing_table").is_err());
+
+            conn.set_trace_handler(None);
+            conn.query_drop("SELECT 2").unwrap();
+
             assert_eq!(
-                conn.query_iter("SELECT COUNT(a) from mysql.tbl")
-                    .unwrap()
-                    .next()
-                    .unwrap()
-                    .unwrap()
-                    .unwrap(),
-                vec![Bytes(b"2".to_vec())]
+                *traced.lock().unwrap(),
+                vec![
+                    "SELECT 1",
+                    "SELECT :foo",
+                    "SELECT ?",
+                    "SELECT * FROM missing_table",
+                ]
             );
-            conn.start_transaction(TxOpts::default())
-                .map(|mut t| {
-                    t.query_drop("INSERT INTO tbl2(a) VALUES(1)").unwrap_err();
-                    // implicit rollback
-                })
-                .unwrap();
+        }
+
+        #[test]
+        fn should_name_column_type_in_conversion_error() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            let err = conn
+                .query_first_opt::<i64, _>("SELECT CAST('2020-01-01 00:00:00' AS DATETIME) AS dt")
+                .unwrap()
+                .unwrap()
+                .unwrap_err();
             assert_eq!(
-                conn.query_iter("SELECT COUNT(a) from mysql.tbl")
-                    .unwrap()
-                    .next()
-                    .unwrap()
-                    .unwrap()
-                    .unwrap(),
-                vec![Bytes(b"2".to_vec())]
+                crate::Error::from(err).to_string(),
+                "from row conversion error: couldn't convert row (`dt`: MYSQL_TYPE_DATETIME)"
             );
-            conn.start_transaction(TxOpts::default())
-                .map(|mut t| {
-                    t.query_drop("INSERT INTO mysql.tbl(a) VALUES(1)").unwrap();
-                    t.query_drop("INSERT INTO mysql.tbl(a) VALUES(2)").unwrap();
-                    t.rollback().unwrap();
-                })
+        }
+
+        #[test]
+        #[should_panic(expected = "Couldn't convert row (`dt`: MYSQL_TYPE_DATETIME) to `i64`")]
+        fn should_name_column_and_rust_types_on_conversion_panic() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            let _ =
+                conn.query_first::<i64, _>("SELECT CAST('2020-01-01 00:00:00' AS DATETIME) AS dt");
+        }
+
+        #[test]
+        fn should_collect_values() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            let values = conn
+                .query_collect("SELECT 1, 'foo' UNION ALL SELECT 2, NULL")
                 .unwrap();
             assert_eq!(
-                conn.query_iter("SELECT COUNT(a) from mysql.tbl")
-                    .unwrap()
-                    .next()
-                    .unwrap()
-                    .unwrap()
-                    .unwrap(),
-                vec![Bytes(b"2".to_vec())]
+                values,
+                vec![
+                    vec![Bytes(b"1".to_vec()), Bytes(b"foo".to_vec())],
+                    vec![Bytes(b"2".to_vec()), NULL],
+                ]
             );
-            let mut tx = conn.start_transaction(TxOpts::default()).unwrap();
-            tx.exec_drop("INSERT INTO mysql.tbl(a) VALUES(?)", (3,))
+
+            let values = conn
+                .query_iter("SELECT 1; SELECT 2")
+                .unwrap()
+                .collect_values()
                 .unwrap();
-            tx.exec_drop("INSERT INTO mysql.tbl(a) VALUES(?)", (4,))
+            assert_eq!(values, vec![vec![Bytes(b"1".to_vec())]]);
+            assert!(conn.ping().is_ok());
+        }
+
+        #[test]
+        fn should_release_connection_after_collecting_values() {
+            fn double_all(conn: &mut Conn, rows: Vec<Vec<Value>>) -> Vec<i64> {
+                rows.into_iter()
+                    .map(|row| conn.exec_first("SELECT ? * 2", row).unwrap().unwrap())
+                    .collect()
+            }
+
+            let mut conn = Conn::new(get_opts()).unwrap();
+            let result = conn.query_iter("SELECT 1 UNION ALL SELECT 2").unwrap();
+            let rows = result.collect_values().unwrap();
+            assert_eq!(double_all(&mut conn, rows), vec![2, 4]);
+        }
+
+        #[test]
+        fn should_report_warnings_of_consumed_result_set() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            let mut result = conn.query_iter("SELECT CAST('abc' AS SIGNED)").unwrap();
+            assert_eq!(result.by_ref().count(), 1);
+            assert!(result.warnings() > 0);
+            drop(result);
+
+            let stmt = conn.prep("SELECT CAST(? AS SIGNED)").unwrap();
+            let mut result = conn.exec_iter(&stmt, ("abc",)).unwrap();
+            assert_eq!(result.by_ref().count(), 1);
+            assert!(result.warnings() > 0);
+            assert!(!result
+                .status()
+                .contains(crate::consts::StatusFlags::SERVER_MORE_RESULTS_EXISTS));
+        }
+
+        #[test]
+        fn should_read_max_bigint_unsigned() {
+            use crate::consts::ColumnFlags;
+
+            let mut conn = Conn::new(get_opts()).unwrap();
+            conn.query_drop("CREATE TEMPORARY TABLE mysql.tbl (a BIGINT UNSIGNED)")
                 .unwrap();
-            tx.commit().unwrap();
-            assert_eq!(
-                conn.query_iter("SELECT COUNT(a) from mysql.tbl")
-                    .unwrap()
-                    .next()
-                    .unwrap()
-                    .unwrap()
-                    .unwrap(),
-                vec![Bytes(b"4".to_vec())]
-            );
-            let mut tx = conn.start_transaction(TxOpts::default()).unwrap();
-            tx.exec_drop("INSERT INTO mysql.tbl(a) VALUES(?)", (5,))
+            conn.exec_drop("INSERT INTO mysql.tbl (a) VALUES (?)", (u64::MAX,))
                 .unwrap();
-            tx.exec_drop("INSERT INTO mysql.tbl(a) VALUES(?)", (6,))
+
+            let mut result = conn.query_iter("SELECT a FROM mysql.tbl").unwrap();
+            let columns = result.columns();
+            assert!(columns.as_ref()[0]
+                .flags()
+                .contains(ColumnFlags::UNSIGNED_FLAG));
+            let row = result.next().unwrap().unwrap();
+            assert_eq!(row[0], Bytes(b"18446744073709551615".to_vec()));
+            assert_eq!(row[0].as_u64(), Some(u64::MAX));
+            assert_eq!(row[0].as_i64(), None);
+            drop(result);
+
+            let value: Value = conn
+                .exec_first("SELECT a FROM mysql.tbl", ())
+                .unwrap()
                 .unwrap();
-            drop(tx);
-            assert_eq!(
-                conn.query_first("SELECT COUNT(a) from mysql.tbl").unwrap(),
-                Some(4_usize),
-            );
+            assert_eq!(value, Value::UInt(u64::MAX));
+            assert_eq!(value.as_u64(), Some(u64::MAX));
+            assert_eq!(value.as_i64(), None);
         }
+
         #[test]
-        fn should_handle_LOCAL_INFILE_with_custom_handler() {
+        fn should_accept_bytes_rendered_as_sql() {
             let mut conn = Conn::new(get_opts()).unwrap();
-            conn.query_drop("CREATE TEMPORARY TABLE mysql.tbl(a TEXT)")
-                .unwrap();
-            conn.set_local_infile_handler(Some(LocalInfileHandler::new(|_, stream| {
-                let mut cell_data = vec![b'Z'; 65535];
-                cell_data.push(b'\n');
-                for _ in 0..1536 {
-                    stream.write_all(&cell_data)?;
-                }
-                Ok(())
-            })));
-            match conn.query_drop("LOAD DATA LOCAL INFILE 'file_name' INTO TABLE mysql.tbl") {
-                Ok(_) => {}
-                Err(ref err) if format!("{}", err).find("not allowed").is_some() => {
-                    return;
-                }
-                Err(err) => panic!("ERROR {}", err),
+            for bytes in [vec![], vec![0_u8], vec![0xff, 0x00, 0x1f]] {
+                let sql = Bytes(bytes.clone()).as_sql(conn.no_backslash_escape());
+                let value: Option<Vec<u8>> = conn.query_first(format!("SELECT {}", sql)).unwrap();
+                assert_eq!(value, Some(bytes));
             }
-            let count = conn
-                .query_iter("SELECT * FROM mysql.tbl")
-                .unwrap()
-                .map(|row| {
-                    assert_eq!(from_row::<(Vec<u8>,)>(row.unwrap()).0.len(), 65535);
-                    1
-                })
-                .sum::<usize>();
-            assert_eq!(count, 1536);
         }
 
         #[test]
-        fn should_reset_connection() {
+        fn should_read_same_typed_rows_over_both_protocols() {
             let mut conn = Conn::new(get_opts()).unwrap();
             conn.query_drop(
-                "CREATE TEMPORARY TABLE `mysql`.`test` \
-                 (`test` VARCHAR(255) NULL);",
+                r"CREATE TEMPORARY TABLE mysql.tbl (
+                    i INT, u INT UNSIGNED, f FLOAT, d DOUBLE, s TEXT,
+                    dt DATETIME(6), da DATE, t TIME, n INT
+                )",
             )
             .unwrap();
-            conn.query_drop("INSERT INTO `mysql`.`test` (`test`) VALUES ('foo');")
+            conn.query_drop(
+                r"INSERT INTO mysql.tbl VALUES (
+                    -1, 4294967295, 1.5, 0.25, 'foo',
+                    '2020-01-02 03:04:05.000006', '2020-01-02', '-838:59:59', NULL
+                )",
+            )
+            .unwrap();
+
+            let query = "SELECT * FROM mysql.tbl";
+            let text_row = conn
+                .query_iter(query)
+                .unwrap()
+                .next_typed()
+                .unwrap()
                 .unwrap();
-            assert_eq!(conn.affected_rows(), 1);
-            conn.reset().unwrap();
-            assert_eq!(conn.affected_rows(), 0);
-            conn.query_drop("SELECT * FROM `mysql`.`test`;")
-                .unwrap_err();
+            let bin_row = conn
+                .exec_iter(query, ())
+                .unwrap()
+                .next_typed()
+                .unwrap()
+                .unwrap();
+
+            let expected = vec![
+                Int(-1),
+                Int(4294967295),
+                Value::Float(1.5),
+                Value::Double(0.25),
+                Bytes(b"foo".to_vec()),
+                Date(2020, 1, 2, 3, 4, 5, 6),
+                Date(2020, 1, 2, 0, 0, 0, 0),
+                Value::Time(true, 34, 22, 59, 59, 0),
+                NULL,
+            ];
+            assert_eq!(text_row.unwrap(), expected);
+            assert_eq!(bin_row.unwrap(), expected);
         }
 
         #[test]
-        fn should_change_user() -> crate::Result<()> {
+        fn should_retry_on_deadlock() {
+            use crate::MySqlError;
+
+            fn deadlock() -> crate::Error {
+                crate::Error::MySqlError(MySqlError {
+                    state: "40001".into(),
+                    message: "Deadlock found when trying to get lock".into(),
+                    code: 1213,
+                })
+            }
+
             let mut conn = Conn::new(get_opts()).unwrap();
-            assert_eq!(
-                conn.query_first::<Value, _>("SELECT @foo")
-                    .unwrap()
-                    .unwrap(),
-                Value::NULL
-            );
 
-            conn.query_drop("SET @foo = 'foo'").unwrap();
+            let mut calls = 0;
+            let result = conn.with_retry(3, |conn| {
+                calls += 1;
+                if calls == 1 {
+                    conn.query_drop("START TRANSACTION")?;
+                    Err(deadlock())
+                } else {
+                    // previous transaction is rolled back
+                    assert!(!conn.server_status().in_transaction());
+                    conn.query_first::<u8, _>("SELECT 42")
+                }
+            });
+            assert_eq!(result.unwrap(), Some(42));
+            assert_eq!(calls, 2);
 
-            assert_eq!(
-                conn.query_first::<String, _>("SELECT @foo")
-                    .unwrap()
-                    .unwrap(),
+            let mut calls = 0;
+            let result = conn.with_retry(3, |_| -> crate::Result<()> {
+                calls += 1;
+                Err(deadlock())
+            });
+            assert!(matches!(result, Err(crate::Error::MySqlError(ref e)) if e.code == 1213));
+            assert_eq!(calls, 3);
+
+            let mut calls = 0;
+            let result = conn.with_retry(3, |conn| {
+                calls += 1;
+                conn.query_drop("SELECT * FROM mysql.no_such_table")
+            });
+            assert!(matches!(result, Err(crate::Error::MySqlError(ref e)) if e.code == 1146));
+            assert_eq!(calls, 1);
+        }
+
+        #[test]
+        fn should_map_first_row() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+
+            let result = conn
+                .query_iter("SELECT 1, 'foo' UNION ALL SELECT 2, 'bar'; SELECT 3")
+                .unwrap();
+            let first = result.map_first(|values| values.len()).unwrap();
+            assert_eq!(first, Some(2));
+            // connection is drained
+            assert_eq!(conn.query_first("SELECT 4").unwrap(), Some(4_u8));
+
+            let result = conn
+                .exec_iter("SELECT ? UNION ALL SELECT ?", (5, 6))
+                .unwrap();
+            let first = result.map_first(|values| values[0].clone()).unwrap();
+            assert_eq!(first, Some(Int(5)));
+
+            let result = conn.query_iter("SELECT 1 FROM DUAL WHERE FALSE").unwrap();
+            assert_eq!(result.map_first(|values| values.len()).unwrap(), None);
+
+            let result = conn.query_iter("DO 1").unwrap();
+            assert_eq!(result.map_first(|values| values.len()).unwrap(), None);
+            assert_eq!(conn.query_first("SELECT 7").unwrap(), Some(7_u8));
+        }
+
+        #[test]
+        fn should_exec_query_text() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+
+            let rows: Vec<i64> = conn.exec("SELECT ?", (42_i64,)).unwrap();
+            assert_eq!(rows, vec![42]);
+
+            let rows: Vec<(i64, String)> = conn
+                .exec("SELECT :a, :b", params! { "a" => 42, "b" => "foo" })
+                .unwrap();
+            assert_eq!(rows, vec![(42, "foo".into())]);
+
+            let rows: Vec<i64> = conn.exec("SELECT 42", ()).unwrap();
+            assert_eq!(rows, vec![42]);
+            let rows: Vec<i64> = conn.exec("SELECT 42", Params::Empty).unwrap();
+            assert_eq!(rows, vec![42]);
+
+            // statement is prepared once
+            let stmt = conn.prep("SELECT ?").unwrap();
+            assert_eq!(conn.prep("SELECT ?").unwrap().id(), stmt.id());
+
+            match conn.exec::<i64, _, _>("SELECT ?", ()) {
+                Err(DriverError(crate::DriverError::MismatchedStmtParams(1, 0))) => (),
+                other => panic!("MismatchedStmtParams expected, got {:?}", other),
+            }
+        }
+
+        #[test]
+        fn should_report_num_columns() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+
+            let result = conn.query_iter("SELECT 1 AS a, 2 AS b, 3 AS c").unwrap();
+            assert_eq!(result.num_columns(), 3);
+            drop(result);
+
+            let stmt = conn.prep("SELECT ? AS a, ? AS b, ? AS c").unwrap();
+            assert_eq!(stmt.num_columns(), 3);
+            let result = conn.exec_iter(&stmt, (1, 2, 3)).unwrap();
+            assert_eq!(result.num_columns(), 3);
+            drop(result);
+
+            let result = conn.query_iter("DO 1").unwrap();
+            assert_eq!(result.num_columns(), 0);
+        }
+
+        #[test]
+        fn should_query_scalar() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            assert_eq!(conn.query_scalar("SELECT NULL").unwrap(), Some(NULL));
+            assert_eq!(
+                conn.query_scalar("SELECT 1, 2 UNION ALL SELECT 3, 4")
+                    .unwrap(),
+                Some(Bytes(b"1".to_vec()))
+            );
+            assert_eq!(
+                conn.query_scalar("SELECT 1 FROM DUAL WHERE 1 = 0").unwrap(),
+                None
+            );
+            assert_eq!(conn.query_scalar("DO 1").unwrap(), None);
+            assert_eq!(
+                conn.query_scalar("DO 1; SELECT 'foo'; SELECT 'bar'")
+                    .unwrap(),
+                None
+            );
+            assert_eq!(
+                conn.query_scalar("SELECT @@max_allowed_packet").unwrap(),
+                conn.query_first("SELECT @@max_allowed_packet").unwrap()
+            );
+            // connection is drained
+            assert_eq!(conn.query_first("SELECT 5").unwrap(), Some(5_u8));
+        }
+
+        #[test]
+        fn should_exec_fresh() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            let max_allowed_packet: usize = get_system_variable(&mut conn, "max_allowed_packet");
+            let len = std::cmp::min(max_allowed_packet / 4, 8 * 1024 * 1024);
+
+            let stmt = conn
+                .prep("SELECT LENGTH(?), MD5(?) = MD5(REPEAT(?, ?))")
+                .unwrap();
+            for byte in [b'a', b'b', b'c'] {
+                let blob = vec![byte; len];
+                let fill = (byte as char).to_string();
+                let row: (usize, bool) = conn
+                    .exec_fresh(&stmt, (blob.clone(), blob, fill, len))
+                    .unwrap()
+                    .next()
+                    .map(|row| from_row(row.unwrap()))
+                    .unwrap();
+                assert_eq!(row, (len, true));
+            }
+
+            conn.reset_stmt(&stmt).unwrap();
+            assert_eq!(conn.exec_first("SELECT ?", (1,)).unwrap(), Some(1_u8));
+        }
+
+        #[test]
+        fn should_send_large_param_as_long_data() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            let max_allowed_packet: usize = get_system_variable(&mut conn, "max_allowed_packet");
+            if max_allowed_packet <= 2 * MAX_PAYLOAD_LEN {
+                // the server won't accept the param
+                return;
+            }
+
+            let stmt = conn.prep("SELECT LENGTH(?)").unwrap();
+            let before = conn.stats();
+            let len: Option<usize> = conn
+                .exec_first(&stmt, (vec![b'a'; MAX_PAYLOAD_LEN],))
+                .unwrap();
+            assert_eq!(len, Some(MAX_PAYLOAD_LEN));
+            // two COM_STMT_SEND_LONG_DATA chunks and COM_STMT_EXECUTE
+            assert_eq!(conn.stats().packets_sent() - before.packets_sent(), 3);
+        }
+
+        #[test]
+        fn should_exec_in_list() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            conn.query_drop("CREATE TEMPORARY TABLE mysql.tbl(id INT, name TEXT)")
+                .unwrap();
+            conn.exec_batch(
+                "INSERT INTO mysql.tbl(id, name) VALUES (?, ?)",
+                vec![(1, "foo"), (2, "bar"), (3, "baz"), (4, "qux"), (5, "quux")],
+            )
+            .unwrap();
+
+            let rows: Vec<(u32, String)> = conn
+                .exec_in(
+                    "SELECT id, name FROM mysql.tbl WHERE id IN (?) ORDER BY id",
+                    vec![4, 1, 2],
+                )
+                .unwrap();
+            assert_eq!(
+                rows,
+                vec![(1, "foo".into()), (2, "bar".into()), (4, "qux".into())]
+            );
+
+            let rows: Vec<u32> = conn
+                .exec_in(
+                    "SELECT id FROM mysql.tbl WHERE id IN (?)",
+                    Vec::<u32>::new(),
+                )
+                .unwrap();
+            assert!(rows.is_empty());
+        }
+
+        #[test]
+        fn should_select_typed_rows() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            let query = "SELECT 1, 'foo' UNION ALL SELECT 2, 'bar'";
+
+            let rows: Vec<(i64, String)> = conn.select(query).unwrap();
+            assert_eq!(rows, vec![(1, "foo".into()), (2, "bar".into())]);
+
+            let rows: Vec<(i64, String)> = conn.exec_select(query, ()).unwrap();
+            assert_eq!(rows, vec![(1, "foo".into()), (2, "bar".into())]);
+
+            let err = conn.select::<(i64, i64), _>(query).unwrap_err();
+            assert!(matches!(
+                err,
+                crate::Error::FromRowError(_, Some("(i64, i64)"))
+            ));
+            assert!(err.to_string().contains("MYSQL_TYPE_VAR_STRING"));
+            assert!(err.to_string().ends_with("to `(i64, i64)`"));
+            let err = conn.exec_select::<(i64,), _, _>(query, ()).unwrap_err();
+            assert!(matches!(err, crate::Error::FromRowError(_, Some("(i64,)"))));
+
+            // the connection is drained
+            assert_eq!(conn.query_first("SELECT 1").unwrap(), Some(1_u8));
+        }
+
+        #[test]
+        fn should_execute_with_explicit_param_types() {
+            use crate::{consts::ColumnType, DriverError::MismatchedStmtParams};
+
+            let mut conn = Conn::new(get_opts()).unwrap();
+            let stmt = conn.prep("SELECT JSON_TYPE(?), ? + 1").unwrap();
+
+            let params = [
+                (Value::from(r#"{"a": 1}"#), ColumnType::MYSQL_TYPE_JSON),
+                (Value::from("1.5"), ColumnType::MYSQL_TYPE_NEWDECIMAL),
+            ];
+            let row = conn
+                .exec_typed(&stmt, &params)
+                .unwrap()
+                .collect_values()
+                .unwrap();
+            assert_eq!(
+                row,
+                vec![vec![Bytes(b"OBJECT".to_vec()), Bytes(b"2.5".to_vec())]]
+            );
+
+            match conn.exec_typed(&stmt, &params[..1]) {
+                Err(DriverError(MismatchedStmtParams(2, 1))) => {}
+                other => panic!("expected MismatchedStmtParams, got {:?}", other),
+            }
+            assert_eq!(conn.query_first("SELECT 1").unwrap(), Some(1_u8));
+        }
+
+        #[test]
+        fn should_insert_batch() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            conn.query_drop("CREATE TEMPORARY TABLE mysql.tbl (id INT, name TEXT)")
+                .unwrap();
+
+            let affected_rows = conn.insert_batch("mysql.tbl", &["id", "name"], &[], 100);
+            assert_eq!(affected_rows.unwrap(), 0);
+
+            let rows = (0..500)
+                .map(|i| vec![Int(i), Bytes(format!("name'{}", i).into_bytes())])
+                .collect::<Vec<_>>();
+            let inserts_before: (String, u64) = conn
+                .query_first("SHOW SESSION STATUS LIKE 'Com_insert'")
+                .unwrap()
+                .unwrap();
+            let affected_rows = conn.insert_batch("mysql.tbl", &["id", "name"], &rows, 100);
+            assert_eq!(affected_rows.unwrap(), 500);
+            let inserts_after: (String, u64) = conn
+                .query_first("SHOW SESSION STATUS LIKE 'Com_insert'")
+                .unwrap()
+                .unwrap();
+            assert_eq!(inserts_after.1 - inserts_before.1, 5);
+
+            let (count, name): (u64, String) = conn
+                .query_first(
+                    "SELECT COUNT(*), MAX(CASE WHEN id = 499 THEN name END) FROM mysql.tbl",
+                )
+                .unwrap()
+                .unwrap();
+            assert_eq!(count, 500);
+            assert_eq!(name, "name'499");
+        }
+
+        #[test]
+        fn should_collect_rows_into_map() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            let map = conn
+                .query_to_map(
+                    "SELECT 1, 'foo' UNION ALL SELECT 2, 'bar' UNION ALL SELECT 1, 'baz'",
+                    |(id, name): (u8, String)| (id, name),
+                )
+                .unwrap();
+            assert_eq!(map.len(), 2);
+            assert_eq!(map[&1], "baz");
+            assert_eq!(map[&2], "bar");
+        }
+
+        #[test]
+        fn should_skip_setup_queries() {
+            fn selects_count(conn: &mut Conn) -> u64 {
+                let (_, count): (String, u64) = conn
+                    .query_first("SHOW SESSION STATUS LIKE 'Com_select'")
+                    .unwrap()
+                    .unwrap();
+                count
+            }
+
+            let opts = get_opts().max_allowed_packet(None);
+            let mut conn = Conn::new(opts.clone()).unwrap();
+            assert!(selects_count(&mut conn) > 0);
+
+            let mut conn = Conn::new(opts.skip_setup_queries(true)).unwrap();
+            assert_eq!(selects_count(&mut conn), 0);
+            assert_eq!(
+                conn.stream_ref().codec().max_allowed_packet,
+                crate::consts::MAX_PAYLOAD_LEN
+            );
+            assert_eq!(conn.query_first("SELECT 1").unwrap(), Some(1_u8));
+        }
+
+        #[test]
+        fn should_report_column_flags() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            conn.query_drop(
+                "CREATE TEMPORARY TABLE mysql.tbl \
+                 (id INT UNSIGNED NOT NULL AUTO_INCREMENT PRIMARY KEY, \
+                 code INT(4) ZEROFILL UNIQUE, data BLOB)",
+            )
+            .unwrap();
+
+            let (columns, _) = conn
+                .query_meta("SELECT id, code, data FROM mysql.tbl")
+                .unwrap();
+            assert!(columns[0].is_not_null());
+            assert!(columns[0].is_primary_key());
+            assert!(columns[0].is_auto_increment());
+            assert!(columns[0].is_unsigned());
+            assert!(!columns[0].is_unique_key());
+            assert!(!columns[0].is_blob());
+            assert!(!columns[0].is_zerofill());
+
+            assert!(columns[1].is_unique_key());
+            assert!(columns[1].is_zerofill());
+            assert!(!columns[1].is_not_null());
+
+            assert!(columns[2].is_blob());
+            assert!(!columns[2].is_primary_key());
+        }
+
+        #[test]
+        fn should_return_meta_of_empty_result() {
+            use crate::consts::{ColumnFlags, ColumnType};
+
+            let mut conn = Conn::new(get_opts()).unwrap();
+            conn.query_drop(
+                "CREATE TEMPORARY TABLE mysql.tbl \
+                 (id INT UNSIGNED NOT NULL, name VARCHAR(32) CHARACTER SET utf8mb4 NULL)",
+            )
+            .unwrap();
+
+            let (columns, mut result) = conn
+                .query_meta("SELECT id, name FROM mysql.tbl WHERE 1 = 0")
+                .unwrap();
+            assert!(result.next().is_none());
+            drop(result);
+
+            let names = columns.iter().map(|c| c.name_str()).collect::<Vec<_>>();
+            assert_eq!(names, vec!["id", "name"]);
+            assert_eq!(columns[0].column_type(), ColumnType::MYSQL_TYPE_LONG);
+            assert!(columns[0].flags().contains(ColumnFlags::NOT_NULL_FLAG));
+            assert!(columns[0].flags().contains(ColumnFlags::UNSIGNED_FLAG));
+            assert_eq!(columns[1].column_type(), ColumnType::MYSQL_TYPE_VAR_STRING);
+            assert!(!columns[1].flags().contains(ColumnFlags::NOT_NULL_FLAG));
+            // the length is given in bytes of the results charset
+            // (or of the column charset if results aren't converted)
+            let max_char_len: u32 = conn
+                .query_first(
+                    "SELECT MAXLEN FROM information_schema.CHARACTER_SETS \
+                     WHERE CHARACTER_SET_NAME = COALESCE(@@character_set_results, 'utf8mb4')",
+                )
+                .unwrap()
+                .unwrap();
+            assert_eq!(columns[1].column_length(), 32 * max_char_len);
+
+            let (columns, _) = conn.query_meta("DO 1").unwrap();
+            assert!(columns.is_empty());
+        }
+
+        #[test]
+        fn should_report_column_type() {
+            use crate::consts::ColumnType;
+
+            let mut conn = Conn::new(get_opts()).unwrap();
+            conn.query_drop(
+                "CREATE TEMPORARY TABLE mysql.tbl \
+                 (dt DATETIME(6), d DATE, t TIME, dec_ DECIMAL(10, 2))",
+            )
+            .unwrap();
+
+            let expected = vec![
+                ColumnType::MYSQL_TYPE_DATETIME,
+                ColumnType::MYSQL_TYPE_DATE,
+                ColumnType::MYSQL_TYPE_TIME,
+                ColumnType::MYSQL_TYPE_NEWDECIMAL,
+            ];
+
+            let (columns, _) = conn.query_meta("SELECT * FROM mysql.tbl").unwrap();
+            let types = columns.iter().map(|c| c.column_type()).collect::<Vec<_>>();
+            assert_eq!(types, expected);
+            let names = columns.iter().map(|c| c.sql_type_name()).collect::<Vec<_>>();
+            assert_eq!(names, vec!["DATETIME", "DATE", "TIME", "DECIMAL"]);
+
+            let stmt = conn.prep("SELECT * FROM mysql.tbl").unwrap();
+            let types = stmt
+                .columns()
+                .iter()
+                .map(|c| c.column_type())
+                .collect::<Vec<_>>();
+            assert_eq!(types, expected);
+        }
+
+        #[test]
+        fn should_find_column_index_by_name() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+
+            let query = format!(
+                "SELECT {}",
+                (0..100)
+                    .map(|i| format!("{} AS col{}", i, i))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+            let result = conn.query_iter(query).unwrap();
+            let columns = result.columns();
+            for i in 0..100 {
+                assert_eq!(columns.column_index(format!("col{}", i)), Some(i));
+            }
+            assert_eq!(columns.column_index("col100"), None);
+            assert_eq!(columns.column_index("COL0"), None);
+            drop(result);
+
+            let result = conn.query_iter("SELECT 1 AS a, 2 AS b, 3 AS a").unwrap();
+            assert_eq!(result.columns().column_index("a"), Some(0));
+            assert_eq!(result.columns().column_index("b"), Some(1));
+            drop(result);
+
+            let result = conn.query_iter("DO 1").unwrap();
+            assert_eq!(result.columns().column_index("a"), None);
+        }
+
+        #[test]
+        fn should_handle_zero_column_result_sets() {
+            use crate::consts::CapabilityFlags;
+
+            let mut conn = Conn::new(get_opts()).unwrap();
+            let mut result = conn.query_iter("DO 1; SELECT 1").unwrap();
+            assert!(result.columns().as_ref().is_empty());
+            assert!(result.next().is_none());
+            let rows = result
+                .iter()
+                .unwrap()
+                .collect::<crate::Result<Vec<_>>>()
+                .unwrap();
+            assert_eq!(rows.len(), 1);
+            drop(result);
+
+            conn.query_drop("DROP PROCEDURE IF EXISTS select_nothing")
+                .unwrap();
+            conn.query_drop("CREATE PROCEDURE select_nothing() BEGIN DO 1; END")
+                .unwrap();
+            let mut result = conn.query_iter("CALL select_nothing()").unwrap();
+            assert!(result.next().is_none());
+            drop(result);
+            assert_eq!(conn.query_first("SELECT 1").unwrap(), Some(1_u8));
+
+            // there is no EOF packet after column definitions, if it's deprecated
+            let opts = OptsBuilder::from_opts(get_opts())
+                .additional_capabilities(CapabilityFlags::CLIENT_DEPRECATE_EOF);
+            let mut conn = Conn::new(opts).unwrap();
+            let values: Vec<(u8, u8)> = conn.query("SELECT 1, 2 UNION ALL SELECT 3, 4").unwrap();
+            assert_eq!(values, vec![(1, 2), (3, 4)]);
+            conn.query_drop("DO 1").unwrap();
+            assert_eq!(conn.query_first("SELECT 1").unwrap(), Some(1_u8));
+        }
+
+        #[test]
+        fn should_report_connection_charset() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            if conn.server_version() < (5, 5, 3) {
+                return;
+            }
+            assert_eq!(conn.charset_id(), 45);
+            assert_eq!(conn.charset_name(), Some("utf8mb4"));
+            let (charset, collation): (String, String) = conn
+                .query_first("SELECT @@character_set_connection, @@collation_connection")
+                .unwrap()
+                .unwrap();
+            assert_eq!(charset, "utf8mb4");
+            assert_eq!(collation, "utf8mb4_general_ci");
+        }
+
+        #[test]
+        fn should_return_ok_packet() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            conn.query_drop("CREATE TEMPORARY TABLE mysql.tbl (id INT, a INT)")
+                .unwrap();
+            conn.query_drop("INSERT INTO mysql.tbl VALUES (1, 0), (2, 0), (3, 0)")
+                .unwrap();
+
+            let ok = conn
+                .query_ok("UPDATE mysql.tbl SET a = 1 WHERE id < 3")
+                .unwrap();
+            assert_eq!(ok.affected_rows(), 2);
+            assert_eq!(ok.last_insert_id(), None);
+            assert_eq!(ok.warnings(), 0);
+            assert_eq!(
+                ok.info_str().as_deref(),
+                Some("Rows matched: 2  Changed: 2  Warnings: 0")
+            );
+
+            let ok = conn
+                .query_ok("INSERT INTO mysql.tbl VALUES (4, 0), (5, 0)")
+                .unwrap();
+            let info = ok.parse_info().unwrap();
+            assert_eq!(
+                (info.records(), info.duplicates(), info.warnings()),
+                (2, 0, 0)
+            );
+
+            match conn.query_ok("SELECT id FROM mysql.tbl") {
+                Err(DriverError(UnexpectedPacket)) => {}
+                other => panic!("expected UnexpectedPacket, got {:?}", other),
+            }
+            // result set was dropped
+            assert_eq!(conn.query_first("SELECT 1").unwrap(), Some(1_u8));
+        }
+
+        #[test]
+        fn should_limit_query_execution_time() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            let expected_code = match (conn.0.server_version, conn.0.mariadb_server_version) {
+                (Some(version), _) if version >= (5, 7, 8) => 3024,
+                (_, Some(version)) if version >= (10, 1, 2) => 1969,
+                _ => return,
+            };
+
+            let start = std::time::Instant::now();
+            let result = conn.query_with_timeout::<u8>(
+                "SELECT SLEEP(5) FROM (SELECT 1 UNION ALL SELECT 2) AS t",
+                500,
+            );
+            assert!(start.elapsed() < Duration::from_secs(4));
+            match result {
+                Err(crate::Error::MySqlError(err)) => assert_eq!(err.code, expected_code),
+                other => panic!("expected a timeout error, got {:?}", other),
+            }
+
+            // other statements are executed as is
+            let rows: Vec<u64> = conn.query_with_timeout("DO 1", 500).unwrap();
+            assert!(rows.is_empty());
+            assert_eq!(
+                conn.query_with_timeout::<u8>("  select 1", 500).unwrap(),
+                vec![1]
+            );
+        }
+
+        #[test]
+        fn should_report_packet_size_if_packet_too_large() {
+            let mut conn = Conn::new(get_opts().max_allowed_packet(Some(1024))).unwrap();
+
+            let query = format!("SELECT '{}'", "a".repeat(2048));
+            match conn.query_drop(&query) {
+                Err(DriverError(PacketTooLarge { len, max })) => {
+                    // command byte followed by the query
+                    assert_eq!(len, 1 + query.len());
+                    assert_eq!(max, 1024);
+                }
+                other => panic!("expected PacketTooLarge, got {:?}", other),
+            }
+
+            let param = vec![b'a'; 2048];
+            match conn.exec_drop("SELECT ?", (param.clone(),)) {
+                Err(DriverError(PacketTooLarge { len, max })) => {
+                    assert!(len > param.len() && len - param.len() < 32, "{}", len);
+                    assert_eq!(max, 1024);
+                }
+                other => panic!("expected PacketTooLarge, got {:?}", other),
+            }
+
+            // nothing was sent, so the connection is still usable
+            assert_eq!(conn.query_first("SELECT 1").unwrap(), Some(1_u8));
+        }
+
+        #[test]
+        fn should_iterate_prepared_select() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            let stmt = conn.prep("SELECT ? UNION ALL SELECT ?").unwrap();
+
+            let mut values = Vec::new();
+            for row in conn.exec_iter(&stmt, (1, 2)).unwrap() {
+                let (value,): (u8,) = from_row(row.unwrap());
+                values.push(value);
+            }
+            assert_eq!(values, vec![1, 2]);
+
+            // empty result is still a valid iterator
+            let stmt = conn.prep("SELECT 1 FROM DUAL WHERE ? = 0").unwrap();
+            let result = conn.exec_iter(&stmt, (1,)).unwrap();
+            assert!(result.is_binary());
+            assert_eq!(result.count(), 0);
+        }
+
+        #[test]
+        fn should_report_result_protocol() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            assert!(!conn.query_iter("SELECT 1").unwrap().is_binary());
+            assert!(conn.exec_iter("SELECT 1", ()).unwrap().is_binary());
+        }
+
+        #[test]
+        fn should_read_integer_bounds_over_binary_protocol() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            conn.query_drop(
+                "CREATE TEMPORARY TABLE mysql.tbl ( \
+                    t TINYINT, tu TINYINT UNSIGNED, \
+                    s SMALLINT, su SMALLINT UNSIGNED, \
+                    m MEDIUMINT, mu MEDIUMINT UNSIGNED, \
+                    i INT, iu INT UNSIGNED, \
+                    b BIGINT, bu BIGINT UNSIGNED)",
+            )
+            .unwrap();
+
+            let min = vec![
+                Int(i8::MIN.into()),
+                Int(0),
+                Int(i16::MIN.into()),
+                Int(0),
+                Int(-(1 << 23)),
+                Int(0),
+                Int(i32::MIN.into()),
+                Int(0),
+                Int(i64::MIN),
+                Int(0),
+            ];
+            let max = vec![
+                Int(i8::MAX.into()),
+                Int(u8::MAX.into()),
+                Int(i16::MAX.into()),
+                Int(u16::MAX.into()),
+                Int((1 << 23) - 1),
+                Int((1 << 24) - 1),
+                Int(i32::MAX.into()),
+                Int(u32::MAX.into()),
+                Int(i64::MAX),
+                // the only unsigned value, that doesn't fit into `Int`
+                Value::UInt(u64::MAX),
+            ];
+            let insert = format!("INSERT INTO mysql.tbl VALUES ({})", ["?"; 10].join(", "));
+            for row in [&min, &max] {
+                conn.exec_drop(&insert, row.clone()).unwrap();
+            }
+
+            let rows = conn
+                .exec_iter("SELECT * FROM mysql.tbl ORDER BY t", ())
+                .unwrap()
+                .collect_values()
+                .unwrap();
+            assert_eq!(rows, vec![min.clone(), max.clone()]);
+
+            // text protocol agrees
+            let mut result = conn
+                .query_iter("SELECT * FROM mysql.tbl ORDER BY t")
+                .unwrap();
+            assert_eq!(result.next_typed().unwrap().unwrap().unwrap(), min);
+            assert_eq!(result.next_typed().unwrap().unwrap().unwrap(), max);
+        }
+
+        #[test]
+        fn should_read_year_columns() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            conn.query_drop("CREATE TEMPORARY TABLE mysql.tbl (id INT, y YEAR)")
+                .unwrap();
+            conn.query_drop("INSERT INTO mysql.tbl VALUES (1, 2024), (2, '0000')")
+                .unwrap();
+
+            let query = "SELECT y FROM mysql.tbl ORDER BY id";
+            let text: Vec<Value> = conn.query(query).unwrap();
+            let bin: Vec<Value> = conn.exec(query, ()).unwrap();
+            assert_eq!(bin, vec![Int(2024), Int(0)]);
+            for values in [text, bin] {
+                let years = values.iter().map(|x| x.as_year()).collect::<Vec<_>>();
+                assert_eq!(years, vec![Some(2024), Some(0)]);
+            }
+
+            let mut result = conn.query_iter(query).unwrap();
+            assert_eq!(result.next_typed().unwrap().unwrap()[0], Int(2024));
+        }
+
+        #[test]
+        fn should_fold_rows() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            conn.query_drop("CREATE TEMPORARY TABLE mysql.tbl(a INT)")
+                .unwrap();
+            conn.query_drop("INSERT INTO mysql.tbl (a) VALUES (1), (2), (3)")
+                .unwrap();
+
+            let sum = conn
+                .query_fold("SELECT a FROM mysql.tbl", 0_i64, |acc, a: i64| acc + a)
+                .unwrap();
+            assert_eq!(sum, 6);
+
+            let count = conn
+                .query_fold(
+                    "SELECT a FROM mysql.tbl",
+                    0_usize,
+                    |acc, row: crate::Row| acc + row.len(),
+                )
+                .unwrap();
+            assert_eq!(count, 3);
+
+            conn.query_fold("SELECT a FROM nonexistent", 0_i64, |acc, a: i64| acc + a)
+                .unwrap_err();
+            assert!(conn.ping().is_ok());
+        }
+
+        #[test]
+        fn should_expose_column_decimals_and_length() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            let result = conn.query_iter("SELECT CAST(1 AS DECIMAL(10,3))").unwrap();
+            let columns = result.columns();
+            assert_eq!(columns.as_ref()[0].decimals(), 3);
+            assert!(columns.as_ref()[0].column_length() >= 10);
+        }
+
+        #[test]
+        fn should_read_geometry_as_wkb() {
+            use crate::consts::ColumnType;
+
+            let mut conn = Conn::new(get_opts()).unwrap();
+            let mut result = conn
+                .query_iter("SELECT ST_GeomFromText('POINT(1 2)')")
+                .unwrap();
+            assert_eq!(
+                result.columns().as_ref()[0].column_type(),
+                ColumnType::MYSQL_TYPE_GEOMETRY
+            );
+            let value = result.next().unwrap().unwrap().take::<Value, _>(0).unwrap();
+            // byte order (1) + geometry type (4) + X (8) + Y (8)
+            assert_eq!(value.as_wkb().unwrap().len(), 21);
+        }
+
+        #[test]
+        fn should_parse_large_text_result() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            let value: Value = conn
+                .query_first("SELECT REPEAT('A', 20000000)")
+                .unwrap()
+                .unwrap();
+            assert_eq!(value, Bytes(iter::repeat(b'A').take(20_000_000).collect()));
+        }
+
+        #[test]
+        fn should_execute_statements_and_parse_results() {
+            const CREATE_QUERY: &str = r"CREATE TEMPORARY TABLE
+                mysql.tbl (a TEXT, b INT, c INT UNSIGNED, d DATE, e FLOAT)";
+            const INSERT_STMT: &str = r"INSERT
+                INTO mysql.tbl (a, b, c, d, e)
+                VALUES (?, ?, ?, ?, ?)";
+
+            type RowType = (Value, Value, Value, Value, Value);
+
+            let row1 = (
+                Bytes(b"hello".to_vec()),
+                Int(-123_i64),
+                Int(123_i64),
+                Date(2014_u16, 5_u8, 5_u8, 0_u8, 0_u8, 0_u8, 0_u32),
+                Float(123.123_f32),
+            );
+            let row2 = (Bytes(b"".to_vec()), NULL, NULL, NULL, Float(321.321_f32));
+
+            let mut conn = Conn::new(get_opts()).unwrap();
+            conn.query_drop(CREATE_QUERY).unwrap();
+
+            let insert_stmt = conn.prep(INSERT_STMT).unwrap();
+            assert_eq!(insert_stmt.connection_id(), conn.connection_id());
+            conn.exec_drop(
+                &insert_stmt,
+                (
+                    from_value::<String>(row1.0.clone()),
+                    from_value::<i32>(row1.1.clone()),
+                    from_value::<u32>(row1.2.clone()),
+                    from_value::<PrimitiveDateTime>(row1.3.clone()),
+                    from_value::<f32>(row1.4.clone()),
+                ),
+            )
+            .unwrap();
+            conn.exec_drop(
+                &insert_stmt,
+                (
+                    from_value::<String>(row2.0.clone()),
+                    row2.1.clone(),
+                    row2.2.clone(),
+                    row2.3.clone(),
+                    from_value::<f32>(row2.4.clone()),
+                ),
+            )
+            .unwrap();
+
+            let select_stmt = conn.prep("SELECT * from mysql.tbl").unwrap();
+            let rows: Vec<RowType> = conn.exec(&select_stmt, ()).unwrap();
+
+            assert_eq!(rows, vec![row1, row2]);
+        }
+
+        #[test]
+        fn should_exec_first_and_exec_map() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            let stmt = conn
+                .prep("SELECT ? UNION ALL SELECT ? UNION ALL SELECT ?")
+                .unwrap();
+
+            let first: Option<u8> = conn.exec_first(&stmt, (1, 2, 3)).unwrap();
+            assert_eq!(first, Some(1));
+            let mapped = conn.exec_map(&stmt, (1, 2, 3), |x: u8| x * 2).unwrap();
+            assert_eq!(mapped, vec![2, 4, 6]);
+
+            // statement without a result set
+            let stmt = conn.prep("DO ?").unwrap();
+            let first: Option<u8> = conn.exec_first(&stmt, (1,)).unwrap();
+            assert_eq!(first, None);
+            let mapped = conn.exec_map(&stmt, (1,), |x: u8| x).unwrap();
+            assert!(mapped.is_empty());
+            assert!(conn.ping().is_ok());
+        }
+
+        #[test]
+        fn should_parse_large_binary_result() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            let stmt = conn.prep("SELECT REPEAT('A', 20000000)").unwrap();
+            let value: Value = conn.exec_first(&stmt, ()).unwrap().unwrap();
+            assert_eq!(value, Bytes(iter::repeat(b'A').take(20_000_000).collect()));
+        }
+
+        #[test]
+        fn should_handle_err_packet_mid_binary_result_set() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            conn.query_drop("CREATE TEMPORARY TABLE mysql.tbl(id INT NOT NULL PRIMARY KEY)")
+                .unwrap();
+            conn.query_drop("INSERT INTO mysql.tbl (id) VALUES (1), (2)")
+                .unwrap();
+
+            // The subquery returns more than one row for the second row only,
+            // so the server sends an ERR packet right after the first binary row.
+            let stmt = conn
+                .prep(
+                    "SELECT id, (SELECT 1 UNION ALL SELECT 2 FROM DUAL WHERE t.id > ?) \
+                     FROM mysql.tbl t",
+                )
+                .unwrap();
+            let mut result = conn.exec_iter(&stmt, (1,)).unwrap();
+            let first = result.next().unwrap().unwrap();
+            assert_eq!(first.unwrap(), vec![Int(1), Int(1)]);
+            match result.next() {
+                Some(Err(crate::Error::MySqlError(err))) => assert_eq!(err.code, 1242),
+                other => panic!("expected MySqlError, got {:?}", other),
+            }
+            assert!(result.next().is_none());
+            assert!(result.iter().is_none());
+            drop(result);
+
+            assert!(conn.ping().is_ok());
+        }
+
+        #[test]
+        fn manually_closed_stmt() {
+            let opts = get_opts().stmt_cache_size(1);
+            let mut conn = Conn::new(opts).unwrap();
+            let stmt = conn.prep("SELECT 1").unwrap();
+            conn.exec_drop(&stmt, ()).unwrap();
+            conn.close(stmt).unwrap();
+            let stmt = conn.prep("SELECT 1").unwrap();
+            conn.exec_drop(&stmt, ()).unwrap();
+            conn.close(stmt).unwrap();
+            let stmt = conn.prep("SELECT 2").unwrap();
+            conn.exec_drop(&stmt, ()).unwrap();
+        }
+
+        #[test]
+        fn should_start_commit_and_rollback_transactions() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            conn.query_drop(
+                "CREATE TEMPORARY TABLE mysql.tbl(id INT NOT NULL PRIMARY KEY AUTO_INCREMENT, a INT)",
+            )
+            .unwrap();
+            conn.start_transaction(TxOpts::default())
+                .map(|mut t| {
+                    t.query_drop("INSERT INTO mysql.tbl(a) VALUES(1)").unwrap();
+                    assert_eq!(t.last_insert_id(), Some(1));
+                    assert_eq!(t.affected_rows(), 1);
+                    t.query_drop("INSERT INTO mysql.tbl(a) VALUES(2)").unwrap();
+                    t.commit().unwrap();
+                })
+                .unwrap();
+            assert_eq!(
+                conn.query_iter("SELECT COUNT(a) from mysql.tbl")
+                    .unwrap()
+                    .next()
+                    .unwrap()
+                    .unwrap()
+                    .unwrap(),
+                vec![Bytes(b"2".to_vec())]
+            );
+            conn.start_transaction(TxOpts::default())
+                .map(|mut t| {
+                    t.query_drop("INSERT INTO tbl2(a) VALUES(1)").unwrap_err();
+                    // implicit rollback
+                })
+                .unwrap();
+            assert_eq!(
+                conn.query_iter("SELECT COUNT(a) from mysql.tbl")
+                    .unwrap()
+                    .next()
+                    .unwrap()
+                    .unwrap()
+                    .unwrap(),
+                vec![Bytes(b"2".to_vec())]
+            );
+            conn.start_transaction(TxOpts::default())
+                .map(|mut t| {
+                    t.query_drop("INSERT INTO mysql.tbl(a) VALUES(1)").unwrap();
+                    t.query_drop("INSERT INTO mysql.tbl(a) VALUES(2)").unwrap();
+                    t.rollback().unwrap();
+                })
+                .unwrap();
+            assert_eq!(
+                conn.query_iter("SELECT COUNT(a) from mysql.tbl")
+                    .unwrap()
+                    .next()
+                    .unwrap()
+                    .unwrap()
+                    .unwrap(),
+                vec![Bytes(b"2".to_vec())]
+            );
+            let mut tx = conn.start_transaction(TxOpts::default()).unwrap();
+            tx.exec_drop("INSERT INTO mysql.tbl(a) VALUES(?)", (3,))
+                .unwrap();
+            tx.exec_drop("INSERT INTO mysql.tbl(a) VALUES(?)", (4,))
+                .unwrap();
+            tx.commit().unwrap();
+            assert_eq!(
+                conn.query_iter("SELECT COUNT(a) from mysql.tbl")
+                    .unwrap()
+                    .next()
+                    .unwrap()
+                    .unwrap()
+                    .unwrap(),
+                vec![Bytes(b"4".to_vec())]
+            );
+            let mut tx = conn.start_transaction(TxOpts::default()).unwrap();
+            tx.exec_drop("INSERT INTO mysql.tbl(a) VALUES(?)", (5,))
+                .unwrap();
+            tx.exec_drop("INSERT INTO mysql.tbl(a) VALUES(?)", (6,))
+                .unwrap();
+            drop(tx);
+            assert_eq!(
+                conn.query_first("SELECT COUNT(a) from mysql.tbl").unwrap(),
+                Some(4_usize),
+            );
+        }
+        #[test]
+        fn should_handle_LOCAL_INFILE_with_custom_handler() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            conn.query_drop("CREATE TEMPORARY TABLE mysql.tbl(a TEXT)")
+                .unwrap();
+            conn.set_local_infile_handler(Some(LocalInfileHandler::new(|_, stream| {
+                let mut cell_data = vec![b'Z'; 65535];
+                cell_data.push(b'\n');
+                for _ in 0..1536 {
+                    stream.write_all(&cell_data)?;
+                }
+                Ok(())
+            })));
+            match conn.query_drop("LOAD DATA LOCAL INFILE 'file_name' INTO TABLE mysql.tbl") {
+                Ok(_) => {}
+                Err(ref err) if format!("{}", err).find("not allowed").is_some() => {
+                    return;
+                }
+                Err(err) => panic!("ERROR {}", err),
+            }
+            let count = conn
+                .query_iter("SELECT * FROM mysql.tbl")
+                .unwrap()
+                .map(|row| {
+                    assert_eq!(from_row::<(Vec<u8>,)>(row.unwrap()).0.len(), 65535);
+                    1
+                })
+                .sum::<usize>();
+            assert_eq!(count, 1536);
+        }
+
+        #[test]
+        fn should_handle_LOCAL_INFILE_within_multi_result_query() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            conn.query_drop("CREATE TEMPORARY TABLE mysql.tbl(a TEXT)")
+                .unwrap();
+            conn.set_local_infile_handler(Some(LocalInfileHandler::new(|_, stream| {
+                stream.write_all(b"foo\nbar\n")?;
+                Ok(())
+            })));
+            match conn.query_drop(
+                "SELECT 1; \
+                 LOAD DATA LOCAL INFILE 'file_name' INTO TABLE mysql.tbl; \
+                 SELECT 2;",
+            ) {
+                Ok(_) => {}
+                Err(ref err) if err.to_string().contains("not allowed") => return,
+                Err(err) => panic!("ERROR {}", err),
+            }
+            // connection is usable and in sync after the reply was drained
+            let count: Option<u64> = conn.query_first("SELECT COUNT(*) FROM mysql.tbl").unwrap();
+            assert_eq!(count, Some(2));
+
+            conn.query_drop("LOAD DATA LOCAL INFILE 'file_name' INTO TABLE mysql.tbl")
+                .unwrap();
+            assert_eq!(conn.affected_rows(), 2);
+            let rows: Vec<String> = conn.query("SELECT a FROM mysql.tbl ORDER BY a").unwrap();
+            assert_eq!(rows, vec!["bar", "bar", "foo", "foo"]);
+        }
+
+        #[test]
+        fn should_export_result_set_as_csv() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            conn.query_drop(
+                "CREATE TEMPORARY TABLE mysql.tbl \
+                 (id INT NOT NULL, name TEXT NULL, created DATE NULL)",
+            )
+            .unwrap();
+            conn.exec_drop(
+                "INSERT INTO mysql.tbl VALUES (?, ?, ?), (?, ?, ?), (?, ?, ?)",
+                (
+                    1,
+                    "plain",
+                    Date(2020, 1, 2, 0, 0, 0, 0),
+                    2,
+                    "a, \"quoted\"\nvalue",
+                    NULL,
+                    3,
+                    NULL,
+                    NULL,
+                ),
+            )
+            .unwrap();
+
+            let expected: &[u8] = b"id,name,created\n\
+                1,plain,2020-01-02\n\
+                2,\"a, \"\"quoted\"\"\nvalue\",\n\
+                3,,\n";
+
+            let mut csv = Vec::new();
+            conn.query_iter("SELECT * FROM mysql.tbl ORDER BY id")
+                .unwrap()
+                .to_csv(&mut csv)
+                .unwrap();
+            assert_eq!(csv, expected);
+
+            let mut csv = Vec::new();
+            conn.exec_iter("SELECT * FROM mysql.tbl ORDER BY id", ())
+                .unwrap()
+                .to_csv(&mut csv)
+                .unwrap();
+            assert_eq!(csv, expected);
+        }
+
+        #[test]
+        fn should_load_data_from_bytes() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            conn.query_drop("CREATE TEMPORARY TABLE mysql.tbl (id INT, name TEXT)")
+                .unwrap();
+            let data = b"1,foo\n2,\"bar, baz\"\n3,\"qux\"\n";
+            match conn.load_data_from_bytes("mysql.tbl", data) {
+                Ok(_) => {}
+                Err(ref err) if err.to_string().contains("not allowed") => return,
+                Err(err) => panic!("ERROR {}", err),
+            }
+            assert_eq!(conn.affected_rows(), 3);
+            assert!(conn.0.local_infile_handler.is_none());
+
+            let rows: Vec<(u32, String)> = conn
+                .query("SELECT id, name FROM mysql.tbl ORDER BY id")
+                .unwrap();
+            assert_eq!(
+                rows,
+                vec![(1, "foo".into()), (2, "bar, baz".into()), (3, "qux".into())]
+            );
+        }
+
+        #[test]
+        fn should_reset_connection() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            conn.query_drop(
+                "CREATE TEMPORARY TABLE `mysql`.`test` \
+                 (`test` VARCHAR(255) NULL);",
+            )
+            .unwrap();
+            conn.query_drop("INSERT INTO `mysql`.`test` (`test`) VALUES ('foo');")
+                .unwrap();
+            assert_eq!(conn.affected_rows(), 1);
+            conn.reset().unwrap();
+            assert_eq!(conn.affected_rows(), 0);
+            conn.query_drop("SELECT * FROM `mysql`.`test`;")
+                .unwrap_err();
+        }
+
+        #[test]
+        fn should_change_user() -> crate::Result<()> {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            assert_eq!(
+                conn.query_first::<Value, _>("SELECT @foo")
+                    .unwrap()
+                    .unwrap(),
+                Value::NULL
+            );
+
+            conn.query_drop("SET @foo = 'foo'").unwrap();
+
+            assert_eq!(
+                conn.query_first::<String, _>("SELECT @foo")
+                    .unwrap()
+                    .unwrap(),
+                "foo",
+            );
+
+            conn.change_user(Default::default()).unwrap();
+            assert_eq!(
+                conn.query_first::<Value, _>("SELECT @foo")
+                    .unwrap()
+                    .unwrap(),
+                Value::NULL
+            );
+
+            let plugins: &[&str] =
+                if conn.0.mariadb_server_version.is_none() && conn.server_version() >= (5, 8, 0) {
+                    &["mysql_native_password", "caching_sha2_password"]
+                } else {
+                    &["mysql_native_password"]
+                };
+
+            for plugin in plugins {
+                let mut rng = rand::thread_rng();
+                let mut pass = [0u8; 10];
+                pass.try_fill(&mut rng).unwrap();
+                let pass: String = IntoIterator::into_iter(pass)
+                    .map(|x| ((x % (123 - 97)) + 97) as char)
+                    .collect();
+
+                conn.query_drop("DELETE FROM mysql.user WHERE user = '__mats'")
+                    .unwrap();
+                conn.query_drop("FLUSH PRIVILEGES").unwrap();
+
+                if conn.0.mariadb_server_version.is_some() || conn.server_version() < (5, 7, 0) {
+                    if matches!(conn.server_version(), (5, 6, _)) {
+                        conn.query_drop(
+                            "CREATE USER '__mats'@'%' IDENTIFIED WITH mysql_old_password",
+                        )
+                        .unwrap();
+                        conn.query_drop(format!(
+                            "SET PASSWORD FOR '__mats'@'%' = OLD_PASSWORD({})",
+                            Value::from(pass.clone()).as_sql(false)
+                        ))
+                        .unwrap();
+                    } else {
+                        conn.query_drop("CREATE USER '__mats'@'%'").unwrap();
+                        conn.query_drop(format!(
+                            "SET PASSWORD FOR '__mats'@'%' = PASSWORD({})",
+                            Value::from(pass.clone()).as_sql(false)
+                        ))
+                        .unwrap();
+                    }
+                } else {
+                    conn.query_drop(format!(
+                        "CREATE USER '__mats'@'%' IDENTIFIED WITH {} BY {}",
+                        plugin,
+                        Value::from(pass.clone()).as_sql(false)
+                    ))
+                    .unwrap();
+                };
+
+                conn.query_drop("DELETE FROM mysql.user WHERE user = ''")
+                    .unwrap();
+                let _ = conn.query_drop("SET GLOBAL secure_auth = 0");
+                conn.query_drop("FLUSH PRIVILEGES").unwrap();
+
+                let mut conn2 = Conn::new(get_opts().secure_auth(false)).unwrap();
+                conn2
+                    .change_user(
+                        crate::ChangeUserOpts::default()
+                            .with_db_name(None)
+                            .with_user(Some("__mats".into()))
+                            .with_pass(Some(pass)),
+                    )
+                    .unwrap();
+                let (db, user) = conn2
+                    .query_first::<(Option<String>, String), _>("SELECT DATABASE(), USER();")
+                    .unwrap()
+                    .unwrap();
+                assert_eq!(db, None);
+                assert!(user.starts_with("__mats"));
+            }
+
+            Ok(())
+        }
+
+        #[test]
+        fn prep_exec() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+
+            let stmt1 = conn.prep("SELECT :foo").unwrap();
+            let stmt2 = conn.prep("SELECT :bar").unwrap();
+            assert_eq!(
+                conn.exec::<String, _, _>(&stmt1, params! { "foo" => "foo" })
+                    .unwrap(),
+                vec![String::from("foo")],
+            );
+            assert_eq!(
+                conn.exec::<String, _, _>(&stmt2, params! { "bar" => "bar" })
+                    .unwrap(),
+                vec![String::from("bar")],
+            );
+        }
+
+        #[test]
+        fn should_connect_via_socket_for_127_0_0_1() {
+            let opts = OptsBuilder::from_opts(get_opts());
+            let conn = Conn::new(opts).unwrap();
+            if conn.is_insecure() {
+                assert!(conn.is_socket());
+            }
+        }
+
+        #[test]
+        fn should_connect_via_socket_localhost() {
+            let opts = OptsBuilder::from_opts(get_opts()).ip_or_hostname(Some("localhost"));
+            let conn = Conn::new(opts).unwrap();
+            if conn.is_insecure() {
+                assert!(conn.is_socket());
+            }
+        }
+
+        #[test]
+        fn should_reprobe_socket_on_reconnect() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            conn.reconnect().unwrap();
+            assert!(conn.ping().is_ok());
+
+            if conn.0.socket_probed {
+                let socket = conn.0.opts.get_socket().map(String::from);
+                // pretend that the socket path has changed on the server side
+                conn.0.opts = OptsBuilder::from_opts(conn.0.opts.clone())
+                    .socket(Some("/foo/bar/baz"))
+                    .into();
+                conn.reconnect().unwrap();
+                assert!(conn.is_socket());
+                assert_eq!(conn.0.opts.get_socket().map(String::from), socket);
+            }
+        }
+
+        #[test]
+        fn should_connect_via_explicit_socket_path() {
+            let mut conn = Conn::new(get_opts().prefer_socket(false)).unwrap();
+            let socket = conn
+                .query_first::<Option<String>, _>("SELECT @@socket")
+                .unwrap()
+                .flatten();
+            if let Some(socket) = socket.filter(|s| std::path::Path::new(s).exists()) {
+                let mut conn = Conn::new(get_opts().socket(Some(socket))).unwrap();
+                assert!(conn.is_socket());
+                assert!(!conn.is_tcp());
+                assert!(conn.ping().is_ok());
+            }
+        }
+
+        /// QueryResult::drop hangs on connectivity errors (see [blackbeam/rust-mysql-simple#306][1]).
+        ///
+        /// [1]: https://github.com/blackbeam/rust-mysql-simple/issues/306
+        #[test]
+        fn issue_306() {
+            let (tx, rx) = channel::<()>();
+            let handle = spawn(move || {
+                let mut c1 = Conn::new(get_opts()).unwrap();
+                let c1_id = c1.connection_id();
+                let mut c2 = Conn::new(get_opts()).unwrap();
+                let query_result = c1.query_iter("DO 1; SELECT SLEEP(1); DO 2;").unwrap();
+                c2.query_drop(format!("KILL {c1_id}")).unwrap();
+                drop(c2);
+                drop(query_result);
+                tx.send(()).unwrap();
+            });
+            std::thread::sleep(Duration::from_secs(2));
+            assert!(rx.try_recv().is_ok());
+            handle.join().unwrap();
+        }
+
+        #[test]
+        fn reset_does_work() {
+            let mut c = Conn::new(get_opts()).unwrap();
+            let cid = c.connection_id();
+            c.query_drop("SET @foo = 'foo'").unwrap();
+            assert_eq!(
+                c.query_first::<String, _>("SELECT @foo").unwrap().unwrap(),
                 "foo",
             );
+            c.reset().unwrap();
+            assert_eq!(cid, c.connection_id());
+            assert_eq!(
+                c.query_first::<Value, _>("SELECT @foo").unwrap().unwrap(),
+                Value::NULL
+            );
+        }
+
+        #[test]
+        fn should_drop_multi_result_set() {
+            let opts = OptsBuilder::from_opts(get_opts()).db_name(Some("mysql"));
+            let mut conn = Conn::new(opts).unwrap();
+            conn.query_drop("CREATE TEMPORARY TABLE TEST_TABLE ( name varchar(255) )")
+                .unwrap();
+            conn.exec_drop("SELECT * FROM TEST_TABLE", ()).unwrap();
+            conn.query_drop(
+                r"
+                INSERT INTO TEST_TABLE (name) VALUES ('one');
+                INSERT INTO TEST_TABLE (name) VALUES ('two');
+                INSERT INTO TEST_TABLE (name) VALUES ('three');",
+            )
+            .unwrap();
+            conn.exec_drop("SELECT * FROM TEST_TABLE", ()).unwrap();
+
+            let mut query_result = conn
+                .query_iter(
+                    r"
+                SELECT * FROM TEST_TABLE;
+                INSERT INTO TEST_TABLE (name) VALUES ('one');
+                DO 0;",
+                )
+                .unwrap();
+
+            while let Some(result) = query_result.iter() {
+                result.affected_rows();
+            }
+        }
+
+        #[test]
+        fn should_run_script() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            conn.run_script(
+                r"CREATE TEMPORARY TABLE mysql.tbl (id INT, name TEXT);
+                  INSERT INTO mysql.tbl (id, name) VALUES (1, 'foo'), (2, 'bar');
+                  SELECT * FROM mysql.tbl",
+            )
+            .unwrap();
+            let rows: Vec<(u8, String)> =
+                conn.query("SELECT * FROM mysql.tbl ORDER BY id").unwrap();
+            assert_eq!(rows, vec![(1, "foo".into()), (2, "bar".into())]);
+
+            let result = conn.run_script(
+                "INSERT INTO mysql.tbl (id) VALUES (3); SELECT * FROM missing_table; DO 1",
+            );
+            match result {
+                Err(crate::Error::MySqlError(err)) => {
+                    assert!(err.message.starts_with("statement #1: "), "{}", err.message);
+                }
+                _ => panic!("MySqlError expected"),
+            }
+            // the last statement is not executed
+            let count: Option<u8> = conn.query_first("SELECT COUNT(*) FROM mysql.tbl").unwrap();
+            assert_eq!(count, Some(3));
+            assert!(conn.ping().is_ok());
+        }
+
+        #[test]
+        fn should_handle_multi_result_set() {
+            let opts = OptsBuilder::from_opts(get_opts())
+                .prefer_socket(false)
+                .db_name(Some("mysql"));
+            let mut conn = Conn::new(opts).unwrap();
+            conn.query_drop("DROP PROCEDURE IF EXISTS multi").unwrap();
+            conn.query_drop(
+                r#"CREATE PROCEDURE multi() BEGIN
+                        SELECT 1 UNION ALL SELECT 2;
+                        DO 1;
+                        SELECT 3 UNION ALL SELECT 4;
+                        DO 1;
+                        DO 1;
+                        SELECT REPEAT('A', 17000000);
+                        SELECT REPEAT('A', 17000000);
+                    END"#,
+            )
+            .unwrap();
+            {
+                let mut query_result = conn.query_iter("CALL multi()").unwrap();
+                let result_set = query_result
+                    .by_ref()
+                    .map(|row| row.unwrap().unwrap().pop().unwrap())
+                    .collect::<Vec<crate::Value>>();
+                assert_eq!(result_set, vec![Bytes(b"1".to_vec()), Bytes(b"2".to_vec())]);
+                let result_set = query_result
+                    .by_ref()
+                    .map(|row| row.unwrap().unwrap().pop().unwrap())
+                    .collect::<Vec<crate::Value>>();
+                assert_eq!(result_set, vec![Bytes(b"3".to_vec()), Bytes(b"4".to_vec())]);
+            }
+            let mut result = conn.query_iter("SELECT 1; SELECT 2; SELECT 3;").unwrap();
+            let mut i = 0;
+            while let Some(result_set) = result.iter() {
+                i += 1;
+                for row in result_set {
+                    match i {
+                        1 => assert_eq!(row.unwrap().unwrap(), vec![Bytes(b"1".to_vec())]),
+                        2 => assert_eq!(row.unwrap().unwrap(), vec![Bytes(b"2".to_vec())]),
+                        3 => assert_eq!(row.unwrap().unwrap(), vec![Bytes(b"3".to_vec())]),
+                        _ => unreachable!(),
+                    }
+                }
+            }
+            assert_eq!(i, 3);
+        }
+
+        #[test]
+        fn issue_273() {
+            let opts = OptsBuilder::from_opts(get_opts()).prefer_socket(false);
+            let mut conn = Conn::new(opts).unwrap();
+
+            "DROP FUNCTION IF EXISTS f1".run(&mut conn).unwrap();
+            r"CREATE DEFINER=`root`@`localhost` FUNCTION `f1`(p_arg INT, p_arg2 INT) RETURNS int
+            DETERMINISTIC
+            BEGIN
+                RETURN p_arg + p_arg2;
+            END"
+            .run(&mut conn)
+            .unwrap();
+
+            "SELECT f1(?, ?)"
+                .with((100u8, 100u8))
+                .run(&mut conn)
+                .unwrap();
+        }
+
+        #[test]
+        fn issue_285() {
+            let (tx, rx) = sync_channel::<()>(0);
+
+            let handle = std::thread::spawn(move || {
+                let mut conn = Conn::new(get_opts()).unwrap();
+                const INVALID_SQL: &str = r#"
+                CREATE TEMPORARY TABLE IF NOT EXISTS `user_details` (
+                    `user_id` int(11) NOT NULL AUTO_INCREMENT,
+                    `username` varchar(255) DEFAULT NULL,
+                    `first_name` varchar(50) DEFAULT NULL,
+                    `last_name` varchar(50) DEFAULT NULL,
+                    PRIMARY KEY (`user_id`)
+                );
+
+                INSERT INTO `user_details` (`user_id`, `username`, `first_name`, `last_name`)
+                VALUES (1, 'rogers63', 'david')
+                "#;
+
+                conn.query_iter(INVALID_SQL).unwrap();
+                tx.send(()).unwrap();
+            });
+
+            match rx.recv_timeout(Duration::from_secs(100_000)) {
+                Ok(_) => handle.join().unwrap(),
+                Err(_) => panic!("test failed"),
+            }
+        }
+
+        #[test]
+        fn should_work_with_named_params() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            {
+                let stmt = conn.prep("SELECT :a, :b, :a, :c").unwrap();
+                let result = conn
+                    .exec_first(&stmt, params! {"a" => 1, "b" => 2, "c" => 3})
+                    .unwrap()
+                    .unwrap();
+                assert_eq!((1_u8, 2_u8, 1_u8, 3_u8), result);
+            }
+
+            let result = conn
+                .exec_first(
+                    "SELECT :a, :b, :a + :b, :c",
+                    params! {
+                        "a" => 1,
+                        "b" => 2,
+                        "c" => 3,
+                    },
+                )
+                .unwrap()
+                .unwrap();
+            assert_eq!((1_u8, 2_u8, 3_u8, 3_u8), result);
+        }
+
+        #[test]
+        fn should_execute_with_each_params_variant() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+
+            let stmt = conn.prep("SELECT 42").unwrap();
+            let result: Option<u8> = conn.exec_first(&stmt, Params::Empty).unwrap();
+            assert_eq!(result, Some(42));
+            let result: Option<u8> = conn.exec_first(&stmt, ()).unwrap();
+            assert_eq!(result, Some(42));
+
+            let stmt = conn.prep("SELECT ?, ?").unwrap();
+            let params = Params::Positional(vec![Int(1), Bytes(b"foo".to_vec())]);
+            let result: Option<(u8, String)> = conn.exec_first(&stmt, params).unwrap();
+            assert_eq!(result, Some((1, "foo".into())));
+            let result: Option<(u8, String)> = conn.exec_first(&stmt, (1, "foo")).unwrap();
+            assert_eq!(result, Some((1, "foo".into())));
+
+            let stmt = conn.prep("SELECT :a, :b, :a").unwrap();
+            let mut named = HashMap::new();
+            named.insert(b"a".to_vec(), Int(1));
+            named.insert(b"b".to_vec(), Int(2));
+            let result: Option<(u8, u8, u8)> =
+                conn.exec_first(&stmt, Params::Named(named)).unwrap();
+            assert_eq!(result, Some((1, 2, 1)));
+        }
+
+        #[test]
+        fn should_bind_optional_params() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            let stmt = conn.prep("SELECT ?, ?").unwrap();
+            let (some_id, no_id): (Option<i64>, Option<i64>) = (Some(5), None);
+            let row: (Value, Value) = conn.exec_first(&stmt, (some_id, no_id)).unwrap().unwrap();
+            assert_eq!(row, (Int(5), NULL));
+
+            let row: (Value, Value) = conn
+                .exec_first(&stmt, vec![Value::from(no_id), Value::from(some_id)])
+                .unwrap()
+                .unwrap();
+            assert_eq!(row, (NULL, Int(5)));
+        }
+
+        #[test]
+        fn should_return_error_on_missing_named_parameter() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            let stmt = conn.prep("SELECT :a, :b, :a, :c, :d").unwrap();
+            let result =
+                conn.exec_first::<crate::Row, _, _>(&stmt, params! {"a" => 1, "b" => 2, "c" => 3,});
+            match result {
+                Err(DriverError(MissingNamedParameter(ref x))) if x == "d" => (),
+                _ => panic!("MissingNamedParameter error expected"),
+            }
+        }
+
+        #[test]
+        fn should_return_error_on_named_params_for_positional_statement() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            let stmt = conn.prep("SELECT ?, ?, ?, ?, ?").unwrap();
+            let result = conn.exec_drop(&stmt, params! {"a" => 1, "b" => 2, "c" => 3,});
+            match result {
+                Err(DriverError(NamedParamsForPositionalQuery)) => (),
+                _ => panic!("NamedParamsForPositionalQuery error expected"),
+            }
+        }
+
+        #[test]
+        fn should_handle_tcp_connect_timeout() {
+            use crate::error::{DriverError::ConnectTimeout, Error::DriverError};
+
+            let opts = OptsBuilder::from_opts(get_opts())
+                .prefer_socket(false)
+                .tcp_connect_timeout(Some(::std::time::Duration::from_millis(1000)));
+            assert!(Conn::new(opts).unwrap().ping().is_ok());
+
+            let opts = OptsBuilder::from_opts(get_opts())
+                .prefer_socket(false)
+                .tcp_connect_timeout(Some(::std::time::Duration::from_millis(1000)))
+                .ip_or_hostname(Some("192.168.255.255"));
+            match Conn::new(opts).unwrap_err() {
+                DriverError(ConnectTimeout) => {}
+                err => panic!("Unexpected error: {}", err),
+            }
+        }
+
+        #[test]
+        fn should_set_additional_capabilities() {
+            use crate::consts::CapabilityFlags;
+
+            let opts = OptsBuilder::from_opts(get_opts())
+                .additional_capabilities(CapabilityFlags::CLIENT_FOUND_ROWS);
+
+            let mut conn = Conn::new(opts).unwrap();
+            conn.query_drop("CREATE TEMPORARY TABLE mysql.tbl (a INT, b TEXT)")
+                .unwrap();
+            conn.query_drop("INSERT INTO mysql.tbl (a, b) VALUES (1, 'foo')")
+                .unwrap();
+            let result = conn
+                .query_iter("UPDATE mysql.tbl SET b = 'foo' WHERE a = 1")
+                .unwrap();
+            assert_eq!(result.affected_rows(), 1);
+        }
+
+        #[test]
+        fn should_report_found_rows() {
+            fn affected_rows(found_rows: bool) -> u64 {
+                let opts = OptsBuilder::from_opts(get_opts()).found_rows(found_rows);
+                let mut conn = Conn::new(opts).unwrap();
+                conn.query_drop("CREATE TEMPORARY TABLE mysql.tbl (a INT, b TEXT)")
+                    .unwrap();
+                conn.query_drop("INSERT INTO mysql.tbl (a, b) VALUES (1, 'foo'), (1, 'foo')")
+                    .unwrap();
+                let result = conn
+                    .query_iter("UPDATE mysql.tbl SET b = 'foo' WHERE a = 1")
+                    .unwrap();
+                result.affected_rows()
+            }
+
+            assert_eq!(affected_rows(true), 2);
+            assert_eq!(affected_rows(false), 0);
+        }
+
+        #[test]
+        fn should_stay_on_tcp_if_prefer_socket_is_false() {
+            let opts = OptsBuilder::from_opts(get_opts())
+                .socket(None::<String>)
+                .prefer_socket(false);
+            let mut conn = Conn::new(opts).unwrap();
+            assert!(!conn.is_socket());
+            assert!(conn.is_tcp());
+            assert!(!conn.0.socket_probed);
+            assert!(conn.0.opts.get_socket().is_none());
+
+            conn.reconnect().unwrap();
+            assert!(!conn.is_socket());
+            assert!(conn.0.opts.get_socket().is_none());
+        }
+
+        #[test]
+        fn should_bind_before_connect() {
+            let port = 28000 + (rand::random::<u16>() % 2000);
+            let opts = OptsBuilder::from_opts(get_opts())
+                .prefer_socket(false)
+                .ip_or_hostname(Some("localhost"))
+                .bind_address(Some(([127, 0, 0, 1], port)));
+            let conn = Conn::new(opts).unwrap();
+            let debug_format: String = format!("{:?}", conn);
+            let expected_1 = format!("addr: V4(127.0.0.1:{})", port);
+            let expected_2 = format!("addr: 127.0.0.1:{}", port);
+            assert!(
+                debug_format.contains(&expected_1) || debug_format.contains(&expected_2),
+                "debug_format: {}",
+                debug_format
+            );
+        }
+
+        #[test]
+        fn should_bind_before_connect_with_timeout() {
+            let port = 30000 + (rand::random::<u16>() % 2000);
+            let opts = OptsBuilder::from_opts(get_opts())
+                .prefer_socket(false)
+                .ip_or_hostname(Some("localhost"))
+                .bind_address(Some(([127, 0, 0, 1], port)))
+                .tcp_connect_timeout(Some(::std::time::Duration::from_millis(1000)));
+            let mut conn = Conn::new(opts).unwrap();
+            assert!(conn.ping().is_ok());
+            let debug_format: String = format!("{:?}", conn);
+            let expected_1 = format!("addr: V4(127.0.0.1:{})", port);
+            let expected_2 = format!("addr: 127.0.0.1:{}", port);
+            assert!(
+                debug_format.contains(&expected_1) || debug_format.contains(&expected_2),
+                "debug_format: {}",
+                debug_format
+            );
+        }
+
+        #[test]
+        fn should_not_cache_statements_if_stmt_cache_size_is_zero() {
+            let opts = OptsBuilder::from_opts(get_opts()).stmt_cache_size(0);
+            let mut conn = Conn::new(opts).unwrap();
+
+            let stmt1 = conn.prep("DO 1").unwrap();
+            let stmt2 = conn.prep("DO 2").unwrap();
+            let stmt3 = conn.prep("DO 3").unwrap();
+
+            conn.close(stmt1).unwrap();
+            conn.close(stmt2).unwrap();
+            conn.close(stmt3).unwrap();
+
+            let status: (Value, u8) = conn
+                .query_first("SHOW SESSION STATUS LIKE 'Com_stmt_close';")
+                .unwrap()
+                .unwrap();
+            assert_eq!(status.1, 3);
+        }
+
+        #[test]
+        fn should_hold_stmt_cache_size_bounds() {
+            let opts = OptsBuilder::from_opts(get_opts()).stmt_cache_size(3);
+            let mut conn = Conn::new(opts).unwrap();
+
+            conn.prep("DO 1").unwrap();
+            conn.prep("DO 2").unwrap();
+            conn.prep("DO 3").unwrap();
+            conn.prep("DO 1").unwrap();
+            conn.prep("DO 4").unwrap();
+            conn.prep("DO 3").unwrap();
+            conn.prep("DO 5").unwrap();
+            conn.prep("DO 6").unwrap();
+
+            let status: (String, usize) = conn
+                .query_first("SHOW SESSION STATUS LIKE 'Com_stmt_close'")
+                .unwrap()
+                .unwrap();
+
+            assert_eq!(status.1, 3);
+
+            let mut order = conn
+                .0
+                .stmt_cache
+                .iter()
+                .map(|(_, entry)| &**entry.query.0.as_ref())
+                .collect::<Vec<&[u8]>>();
+            order.sort();
+            assert_eq!(order, &[b"DO 3", b"DO 5", b"DO 6"]);
+        }
+
+        #[test]
+        fn should_share_cached_statements() {
+            let opts = OptsBuilder::from_opts(get_opts()).stmt_cache_size(1);
+            let mut conn = Conn::new(opts).unwrap();
+
+            let stmt1 = conn.prep("SELECT ?").unwrap();
+            let stmt2 = conn.prep("SELECT ?").unwrap();
+            assert_eq!(stmt1.id(), stmt2.id());
+
+            // `stmt1` is evicted, but must remain usable while referenced
+            let stmt3 = conn.prep("SELECT ?, ?").unwrap();
+            assert_ne!(stmt1.id(), stmt3.id());
+            let result: Option<u8> = conn.exec_first(&stmt1, (42,)).unwrap();
+            assert_eq!(result, Some(42));
+
+            drop(stmt1);
+            drop(stmt2);
+            drop(stmt3);
+            conn.prep("SELECT ?, ?, ?").unwrap();
+
+            let status: (String, usize) = conn
+                .query_first("SHOW SESSION STATUS LIKE 'Com_stmt_close'")
+                .unwrap()
+                .unwrap();
+            assert_eq!(status.1, 2);
+        }
+
+        #[test]
+        fn should_handle_json_columns() {
+            use crate::{Deserialized, Serialized};
+            use serde_json::Value as Json;
+            use std::str::FromStr;
+
+            #[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
+            pub struct DecTest {
+                foo: String,
+                quux: (u64, String),
+            }
+
+            let decodable = DecTest {
+                foo: "bar".into(),
+                quux: (42, "hello".into()),
+            };
+
+            let mut conn = Conn::new(get_opts()).unwrap();
+            if conn
+                .query_drop("CREATE TEMPORARY TABLE mysql.tbl(a VARCHAR(32), b JSON)")
+                .is_err()
+            {
+                conn.query_drop("CREATE TEMPORARY TABLE mysql.tbl(a VARCHAR(32), b TEXT)")
+                    .unwrap();
+            }
+            conn.exec_drop(
+                r#"INSERT INTO mysql.tbl VALUES ('hello', ?)"#,
+                (Serialized(&decodable),),
+            )
+            .unwrap();
 
-            conn.change_user(Default::default()).unwrap();
+            let (a, b): (String, Json) = conn
+                .query_first("SELECT a, b FROM mysql.tbl")
+                .unwrap()
+                .unwrap();
             assert_eq!(
-                conn.query_first::<Value, _>("SELECT @foo")
-                    .unwrap()
-                    .unwrap(),
-                Value::NULL
+                (a, b),
+                (
+                    "hello".into(),
+                    Json::from_str(r#"{"foo": "bar", "quux": [42, "hello"]}"#).unwrap()
+                )
             );
 
-            let plugins: &[&str] =
-                if conn.0.mariadb_server_version.is_none() && conn.server_version() >= (5, 8, 0) {
-                    &["mysql_native_password", "caching_sha2_password"]
-                } else {
-                    &["mysql_native_password"]
-                };
+            let row = conn
+                .exec_first("SELECT a, b FROM mysql.tbl WHERE a = ?", ("hello",))
+                .unwrap()
+                .unwrap();
+            let (a, Deserialized(b)) = from_row(row);
+            assert_eq!((a, b), (String::from("hello"), decodable));
+        }
 
-            for plugin in plugins {
-                let mut rng = rand::thread_rng();
-                let mut pass = [0u8; 10];
-                pass.try_fill(&mut rng).unwrap();
-                let pass: String = IntoIterator::into_iter(pass)
-                    .map(|x| ((x % (123 - 97)) + 97) as char)
-                    .collect();
+        #[test]
+        fn should_set_connect_attrs() {
+            let opts = OptsBuilder::from_opts(
+                get_opts().connect_attrs::<String, String>(Some(Default::default())),
+            );
+            let mut conn = Conn::new(opts).unwrap();
 
-                conn.query_drop("DELETE FROM mysql.user WHERE user = '__mats'")
-                    .unwrap();
-                conn.query_drop("FLUSH PRIVILEGES").unwrap();
+            let support_connect_attrs = match (conn.0.server_version, conn.0.mariadb_server_version)
+            {
+                (Some(ref version), _) if *version >= (5, 6, 0) => true,
+                (_, Some(ref version)) if *version >= (10, 0, 0) => true,
+                _ => false,
+            };
 
-                if conn.0.mariadb_server_version.is_some() || conn.server_version() < (5, 7, 0) {
-                    if matches!(conn.server_version(), (5, 6, _)) {
-                        conn.query_drop(
-                            "CREATE USER '__mats'@'%' IDENTIFIED WITH mysql_old_password",
-                        )
-                        .unwrap();
-                        conn.query_drop(format!(
-                            "SET PASSWORD FOR '__mats'@'%' = OLD_PASSWORD({})",
-                            Value::from(pass.clone()).as_sql(false)
-                        ))
-                        .unwrap();
-                    } else {
-                        conn.query_drop("CREATE USER '__mats'@'%'").unwrap();
-                        conn.query_drop(format!(
-                            "SET PASSWORD FOR '__mats'@'%' = PASSWORD({})",
-                            Value::from(pass.clone()).as_sql(false)
-                        ))
-                        .unwrap();
+            if support_connect_attrs {
+                // MySQL >= 5.6 or MariaDB >= 10.0
+
+                if get_system_variable::<String>(&mut conn, "performance_schema") != "ON" {
+                    panic!("The system variable `performance_schema` is off. Restart the MySQL server with `--performance_schema=on` to pass the test.");
+                }
+                let attrs_size: i32 =
+                    get_system_variable(&mut conn, "performance_schema_session_connect_attrs_size");
+                if (0..=128).contains(&attrs_size) {
+                    panic!("The system variable `performance_schema_session_connect_attrs_size` is {}. Restart the MySQL server with `--performance_schema_session_connect_attrs_size=-1` to pass the test.", attrs_size);
+                }
+
+                fn assert_connect_attrs(conn: &mut Conn, expected_values: &[(&str, &str)]) {
+                    let mut actual_values = HashMap::new();
+                    for row in conn.query_iter("SELECT attr_name, attr_value FROM performance_schema.session_account_connect_attrs WHERE processlist_id = connection_id()").unwrap() {
+                        let (name, value) = from_row::<(String, String)>(row.unwrap());
+                        actual_values.insert(name, value);
                     }
-                } else {
-                    conn.query_drop(format!(
-                        "CREATE USER '__mats'@'%' IDENTIFIED WITH {} BY {}",
-                        plugin,
-                        Value::from(pass.clone()).as_sql(false)
-                    ))
+
+                    for (name, value) in expected_values {
+                        assert_eq!(
+                            actual_values.get(&name.to_string()),
+                            Some(&value.to_string())
+                        );
+                    }
+                }
+
+                let pid = process::id().to_string();
+                let prog_name = std::env::args_os()
+                    .next()
+                    .unwrap()
+                    .to_string_lossy()
+                    .into_owned();
+                let mut expected_values = vec![
+                    ("_client_name", "rust-mysql-simple"),
+                    ("_client_version", env!("CARGO_PKG_VERSION")),
+                    ("_os", env!("CARGO_CFG_TARGET_OS")),
+                    ("_pid", &pid),
+                    ("_platform", env!("CARGO_CFG_TARGET_ARCH")),
+                    ("program_name", &prog_name),
+                ];
+
+                // No connect attributes are added.
+                assert_connect_attrs(&mut conn, &expected_values);
+
+                // Connect attributes are added.
+                let opts = OptsBuilder::from_opts(get_opts());
+                let mut connect_attrs = HashMap::with_capacity(3);
+                connect_attrs.insert("foo", "foo val");
+                connect_attrs.insert("bar", "bar val");
+                connect_attrs.insert("program_name", "my program name");
+                let mut conn = Conn::new(opts.connect_attrs(Some(connect_attrs))).unwrap();
+                expected_values.pop(); // remove program_name at the last
+                expected_values.push(("foo", "foo val"));
+                expected_values.push(("bar", "bar val"));
+                expected_values.push(("program_name", "my program name"));
+                assert_connect_attrs(&mut conn, &expected_values);
+            }
+        }
+
+        #[test]
+        #[cfg(feature = "binlog")]
+        fn should_read_binlog() -> crate::Result<()> {
+            use std::{
+                collections::HashMap, sync::mpsc::sync_channel, thread::spawn, time::Duration,
+            };
+
+            fn gen_dummy_data() -> crate::Result<()> {
+                let mut conn = Conn::new(get_opts())?;
+
+                "CREATE TABLE IF NOT EXISTS customers (customer_id int not null)".run(&mut conn)?;
+
+                for i in 0_u8..100 {
+                    "INSERT INTO customers(customer_id) VALUES (?)"
+                        .with((i,))
+                        .run(&mut conn)?;
+                }
+
+                "DROP TABLE customers".run(&mut conn)?;
+
+                Ok(())
+            }
+
+            fn get_conn() -> crate::Result<(Conn, Vec<u8>, u64)> {
+                let mut conn = Conn::new(get_opts())?;
+
+                if let Ok(Some(gtid_mode)) =
+                    "SELECT @@GLOBAL.GTID_MODE".first::<String, _>(&mut conn)
+                {
+                    if !gtid_mode.starts_with("ON") {
+                        panic!(
+                            "GTID_MODE is disabled \
+                                (enable using --gtid_mode=ON --enforce_gtid_consistency=ON)"
+                        );
+                    }
+                }
+
+                let row: crate::Row = "SHOW BINARY LOGS".first(&mut conn)?.unwrap();
+                let filename = row.get(0).unwrap();
+                let position = row.get(1).unwrap();
+
+                gen_dummy_data().unwrap();
+                Ok((conn, filename, position))
+            }
+
+            // iterate using COM_BINLOG_DUMP
+            let (conn, filename, pos) = get_conn().unwrap();
+            let is_mariadb = conn.0.mariadb_server_version.is_some();
+
+            let binlog_stream = conn
+                .get_binlog_stream(BinlogRequest::new(12).with_filename(filename).with_pos(pos))
+                .unwrap();
+
+            let mut events_num = 0;
+            let (tx, rx) = sync_channel(0);
+            spawn(move || {
+                for event in binlog_stream {
+                    tx.send(event).unwrap();
+                }
+            });
+            let mut tmes = HashMap::new();
+            while let Ok(event) = rx.recv_timeout(Duration::from_secs(1)) {
+                let event = event.unwrap();
+                events_num += 1;
+
+                // assert that event type is known
+                event.header().event_type().unwrap();
+
+                // iterate over rows of an event
+                match event.read_data()?.unwrap() {
+                    EventData::TableMapEvent(tme) => {
+                        tmes.insert(tme.table_id(), tme.into_owned());
+                    }
+                    EventData::RowsEvent(re) => {
+                        for row in re.rows(&tmes[&re.table_id()]) {
+                            row.unwrap();
+                        }
+                    }
+                    _ => (),
+                }
+            }
+            assert!(events_num > 0);
+
+            if !is_mariadb {
+                // iterate using COM_BINLOG_DUMP_GTID
+                let (conn, filename, pos) = get_conn().unwrap();
+
+                let binlog_stream = conn
+                    .get_binlog_stream(
+                        BinlogRequest::new(13)
+                            .with_use_gtid(true)
+                            .with_filename(filename)
+                            .with_pos(pos),
+                    )
                     .unwrap();
-                };
 
-                conn.query_drop("DELETE FROM mysql.user WHERE user = ''")
-                    .unwrap();
-                let _ = conn.query_drop("SET GLOBAL secure_auth = 0");
-                conn.query_drop("FLUSH PRIVILEGES").unwrap();
+                let mut events_num = 0;
+                let (tx, rx) = sync_channel(0);
+                spawn(move || {
+                    for event in binlog_stream {
+                        tx.send(event).unwrap();
+                    }
+                });
+                let mut tmes = HashMap::new();
+                while let Ok(event) = rx.recv_timeout(Duration::from_secs(1)) {
+                    let event = event.unwrap();
+                    events_num += 1;
+
+                    // assert that event type is known
+                    event.header().event_type().unwrap();
+
+                    // iterate over rows of an event
+                    match event.read_data()?.unwrap() {
+                        EventData::TableMapEvent(tme) => {
+                            tmes.insert(tme.table_id(), tme.into_owned());
+                        }
+                        EventData::RowsEvent(re) => {
+                            for row in re.rows(&tmes[&re.table_id()]) {
+                                row.unwrap();
+                            }
+                        }
+                        _ => (),
+                    }
+                }
+                assert!(events_num > 0);
+            }
+
+            // iterate using COM_BINLOG_DUMP with BINLOG_DUMP_NON_BLOCK flag
+            let (conn, filename, pos) = get_conn().unwrap();
 
-                let mut conn2 = Conn::new(get_opts().secure_auth(false)).unwrap();
-                conn2
-                    .change_user(
-                        crate::ChangeUserOpts::default()
-                            .with_db_name(None)
-                            .with_user(Some("__mats".into()))
-                            .with_pass(Some(pass)),
-                    )
-                    .unwrap();
-                let (db, user) = conn2
-                    .query_first::<(Option<String>, String), _>("SELECT DATABASE(), USER();")
-                    .unwrap()
-                    .unwrap();
-                assert_eq!(db, None);
-                assert!(user.starts_with("__mats"));
+            let binlog_stream = conn
+                .get_binlog_stream(
+                    BinlogRequest::new(14)
+                        .with_filename(filename)
+                        .with_pos(pos)
+                        .with_flags(crate::BinlogDumpFlags::BINLOG_DUMP_NON_BLOCK),
+                )
+                .unwrap();
+
+            events_num = 0;
+            for event in binlog_stream {
+                let event = event.unwrap();
+                events_num += 1;
+                event.header().event_type().unwrap();
+                event.read_data()?;
             }
+            assert!(events_num > 0);
 
             Ok(())
         }
+    }
+
+    mod handshake {
+        use std::{
+            io::{Read, Write},
+            net::TcpListener,
+            thread,
+        };
+
+        use mysql_common::{
+            io::ParseBuf,
+            packets::{AuthPlugin, HandshakePacket},
+        };
+
+        use super::super::{check_handshake, check_handshake_payload, MAX_HANDSHAKE_LEN};
+        use crate::{
+            consts::CapabilityFlags,
+            Conn,
+            DriverError::{NotMySqlServer, Protocol41NotSet, UnsupportedProtocol},
+            Error::DriverError,
+            OptsBuilder,
+        };
+
+        // MySql 8.0.34
+        const MYSQL_HANDSHAKE: &[u8] = b"\x0a8.0.34\x00\x0d\x00\x00\x00\x1do\x13J<.Q\x07\x00\xff\xff\xff\x02\x00\xff\xdf\x15\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00F/[\x19\x0ezl5H\x01w%\x00caching_sha2_password\x00";
+        // MariaDB 10.6.12 (extended capabilities in the last 4 bytes of the reserved part)
+        const MARIADB_HANDSHAKE: &[u8] = b"\x0a5.5.5-10.6.12-MariaDB-1:10.6.12+maria~ubu2004\x00\x07\x00\x00\x00']O;W`*~\x00\xfe\xf7-\x02\x00\xbf\x81\x15\x00\x00\x00\x00\x00\x00\x1d\x00\x00\x00j&GUB5+3`,V^\x00mysql_native_password\x00";
+        // Same as `MYSQL_HANDSHAKE` but with zero `auth_plugin_data_len`.
+        const ZERO_AUTH_DATA_LEN_HANDSHAKE: &[u8] = b"\x0a8.0.34\x00\x0d\x00\x00\x00\x1do\x13J<.Q\x07\x00\xff\xff\xff\x02\x00\xff\xdf\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00F/[\x19\x0ezl5H\x01w%\x00caching_sha2_password\x00";
+        const HTTP_RESPONSE: &[u8] = b"HTTP/1.1 400 Bad Request\r\n\r\n";
+
+        fn parse(payload: &[u8]) -> HandshakePacket<'_> {
+            ParseBuf(payload).parse::<HandshakePacket>(()).unwrap()
+        }
 
         #[test]
-        fn prep_exec() {
-            let mut conn = Conn::new(get_opts()).unwrap();
+        fn should_reconstruct_mysql_nonce() {
+            let handshake = parse(MYSQL_HANDSHAKE);
+            assert!(handshake
+                .capabilities()
+                .contains(CapabilityFlags::CLIENT_PLUGIN_AUTH));
+            assert_eq!(handshake.connection_id(), 13);
+            assert_eq!(handshake.nonce(), b"\x1do\x13J<.Q\x07F/[\x19\x0ezl5H\x01w%");
+            assert_eq!(
+                handshake.auth_plugin(),
+                Some(AuthPlugin::CachingSha2Password)
+            );
+        }
 
-            let stmt1 = conn.prep("SELECT :foo").unwrap();
-            let stmt2 = conn.prep("SELECT :bar").unwrap();
+        #[test]
+        fn should_reconstruct_mariadb_nonce() {
+            let handshake = parse(MARIADB_HANDSHAKE);
             assert_eq!(
-                conn.exec::<String, _, _>(&stmt1, params! { "foo" => "foo" })
-                    .unwrap(),
-                vec![String::from("foo")],
+                handshake.maria_db_server_version_parsed(),
+                Some((10, 6, 12))
             );
+            assert_eq!(handshake.nonce(), b"']O;W`*~j&GUB5+3`,V^");
             assert_eq!(
-                conn.exec::<String, _, _>(&stmt2, params! { "bar" => "bar" })
-                    .unwrap(),
-                vec![String::from("bar")],
+                handshake.auth_plugin(),
+                Some(AuthPlugin::MysqlNativePassword)
             );
         }
 
         #[test]
-        fn should_connect_via_socket_for_127_0_0_1() {
-            let opts = OptsBuilder::from_opts(get_opts());
-            let conn = Conn::new(opts).unwrap();
-            if conn.is_insecure() {
-                assert!(conn.is_socket());
+        fn should_report_server_version_if_protocol_41_is_not_set() {
+            // `MYSQL_HANDSHAKE` with `CLIENT_PROTOCOL_41` cleared
+            let payload = b"\x0a8.0.34\x00\x0d\x00\x00\x00\x1do\x13J<.Q\x07\x00\xff\xfd\xff\x02\x00\xff\xdf\x15\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00F/[\x19\x0ezl5H\x01w%\x00caching_sha2_password\x00";
+            assert!(check_handshake(&parse(MYSQL_HANDSHAKE)).is_ok());
+
+            match check_handshake(&parse(payload)) {
+                Err(DriverError(Protocol41NotSet {
+                    server_version,
+                    capabilities,
+                })) => {
+                    assert_eq!(server_version, "8.0.34");
+                    assert_eq!(capabilities, 0xdfff_fdff);
+                }
+                other => panic!("expected Protocol41NotSet, got {:?}", other),
             }
+
+            let err = check_handshake(&parse(payload)).unwrap_err();
+            assert!(err.to_string().contains("8.0.34"));
+            assert!(err.to_string().contains("0xdffffdff"));
         }
 
         #[test]
-        fn should_connect_via_socket_localhost() {
-            let opts = OptsBuilder::from_opts(get_opts()).ip_or_hostname(Some("localhost"));
-            let conn = Conn::new(opts).unwrap();
-            if conn.is_insecure() {
-                assert!(conn.is_socket());
+        fn should_reconstruct_nonce_if_auth_data_len_is_zero() {
+            let handshake = parse(ZERO_AUTH_DATA_LEN_HANDSHAKE);
+            assert_eq!(handshake.nonce(), parse(MYSQL_HANDSHAKE).nonce());
+            assert_eq!(
+                handshake.auth_plugin(),
+                Some(AuthPlugin::CachingSha2Password)
+            );
+        }
+
+        #[test]
+        fn should_reject_non_mysql_server() {
+            let err = check_handshake_payload(HTTP_RESPONSE).unwrap_err();
+            match err {
+                DriverError(NotMySqlServer(ref bytes)) => assert_eq!(bytes, &HTTP_RESPONSE[..16]),
+                ref other => panic!("NotMySqlServer expected, got {:?}", other),
+            }
+            match err {
+                DriverError(ref err) => assert_eq!(
+                    err.to_string(),
+                    "Not a MySQL server or unsupported protocol \
+                     (first bytes: 48 54 54 50 2f 31 2e 31 20 34 30 30 20 42 61 64)"
+                ),
+                _ => unreachable!(),
             }
         }
 
-        /// QueryResult::drop hangs on connectivity errors (see [blackbeam/rust-mysql-simple#306][1]).
-        ///
-        /// [1]: https://github.com/blackbeam/rust-mysql-simple/issues/306
         #[test]
-        fn issue_306() {
-            let (tx, rx) = channel::<()>();
-            let handle = spawn(move || {
-                let mut c1 = Conn::new(get_opts()).unwrap();
-                let c1_id = c1.connection_id();
-                let mut c2 = Conn::new(get_opts()).unwrap();
-                let query_result = c1.query_iter("DO 1; SELECT SLEEP(1); DO 2;").unwrap();
-                c2.query_drop(format!("KILL {c1_id}")).unwrap();
-                drop(c2);
-                drop(query_result);
-                tx.send(()).unwrap();
+        fn should_fail_fast_on_http_server() {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let port = listener.local_addr().unwrap().port();
+            let server = thread::spawn(move || {
+                let (mut stream, _) = listener.accept().unwrap();
+                stream.write_all(HTTP_RESPONSE).unwrap();
+                // keep the connection open, so that the client won't see EOF
+                let _ = stream.read(&mut [0_u8; 1]);
             });
-            std::thread::sleep(Duration::from_secs(2));
-            assert!(rx.try_recv().is_ok());
-            handle.join().unwrap();
+
+            let opts = OptsBuilder::new()
+                .ip_or_hostname(Some("127.0.0.1"))
+                .tcp_port(port)
+                .prefer_socket(false);
+            match Conn::new(opts) {
+                Err(DriverError(NotMySqlServer(bytes))) => {
+                    assert_eq!(bytes, &HTTP_RESPONSE[..16])
+                }
+                other => panic!("NotMySqlServer expected, got {:?}", other.map(|_| ())),
+            }
+            server.join().unwrap();
         }
 
         #[test]
-        fn reset_does_work() {
-            let mut c = Conn::new(get_opts()).unwrap();
-            let cid = c.connection_id();
-            c.query_drop("SET @foo = 'foo'").unwrap();
-            assert_eq!(
-                c.query_first::<String, _>("SELECT @foo").unwrap().unwrap(),
-                "foo",
-            );
-            c.reset().unwrap();
-            assert_eq!(cid, c.connection_id());
-            assert_eq!(
-                c.query_first::<Value, _>("SELECT @foo").unwrap().unwrap(),
-                Value::NULL
-            );
+        fn should_check_handshake_payload() {
+            check_handshake_payload(MYSQL_HANDSHAKE).unwrap();
+            check_handshake_payload(MARIADB_HANDSHAKE).unwrap();
+
+            let mut payload = MYSQL_HANDSHAKE.to_vec();
+            payload.resize(MAX_HANDSHAKE_LEN + 1, 0);
+            match check_handshake_payload(&payload) {
+                Err(DriverError(NotMySqlServer(bytes))) => assert_eq!(bytes.len(), 16),
+                other => panic!("NotMySqlServer expected, got {:?}", other),
+            }
+
+            match check_handshake_payload(&[9, b'3', 0]) {
+                Err(DriverError(UnsupportedProtocol(9))) => (),
+                other => panic!("UnsupportedProtocol expected, got {:?}", other),
+            }
+            match check_handshake_payload(&[]) {
+                Err(DriverError(NotMySqlServer(bytes))) => assert!(bytes.is_empty()),
+                other => panic!("NotMySqlServer expected, got {:?}", other),
+            }
+        }
+    }
+
+    mod prefer_socket {
+        use std::net::SocketAddr;
+
+        use super::super::should_probe_socket;
+        use crate::{Opts, OptsBuilder};
+
+        fn probes(opts: OptsBuilder, peer_addr: &str) -> bool {
+            let peer_addr = peer_addr.parse::<SocketAddr>().ok();
+            should_probe_socket(&Opts::from(opts), peer_addr)
         }
 
         #[test]
-        fn should_drop_multi_result_set() {
-            let opts = OptsBuilder::from_opts(get_opts()).db_name(Some("mysql"));
-            let mut conn = Conn::new(opts).unwrap();
-            conn.query_drop("CREATE TEMPORARY TABLE TEST_TABLE ( name varchar(255) )")
-                .unwrap();
-            conn.exec_drop("SELECT * FROM TEST_TABLE", ()).unwrap();
-            conn.query_drop(
-                r"
-                INSERT INTO TEST_TABLE (name) VALUES ('one');
-                INSERT INTO TEST_TABLE (name) VALUES ('two');
-                INSERT INTO TEST_TABLE (name) VALUES ('three');",
-            )
-            .unwrap();
-            conn.exec_drop("SELECT * FROM TEST_TABLE", ()).unwrap();
+        fn should_probe_socket_only_for_loopback_peer() {
+            let opts = OptsBuilder::new().ip_or_hostname(Some("localhost"));
+            assert!(probes(opts.clone(), "127.0.0.1:3306"));
+            assert!(probes(opts.clone(), "[::1]:3306"));
+            assert!(!probes(opts.clone(), "10.0.0.1:3306"));
+            // socket connection
+            assert!(!probes(opts, ""));
+
+            // host name is not loopback, but it resolves to the loopback address
+            let opts = OptsBuilder::new().ip_or_hostname(Some("db.example.com"));
+            assert!(probes(opts, "127.0.0.1:3306"));
+        }
 
-            let mut query_result = conn
-                .query_iter(
-                    r"
-                SELECT * FROM TEST_TABLE;
-                INSERT INTO TEST_TABLE (name) VALUES ('one');
-                DO 0;",
-                )
-                .unwrap();
+        #[test]
+        fn should_not_probe_socket_if_opted_out() {
+            let opts = OptsBuilder::new().ip_or_hostname(Some("127.0.0.1"));
+            assert!(!probes(opts.clone().prefer_socket(false), "127.0.0.1:3306"));
+            assert!(!probes(
+                opts.clone().skip_setup_queries(true),
+                "127.0.0.1:3306"
+            ));
+            assert!(!probes(
+                opts.socket(Some("/tmp/mysql.sock")),
+                "127.0.0.1:3306"
+            ));
+        }
+    }
 
-            while let Some(result) = query_result.iter() {
-                result.affected_rows();
-            }
+    mod extra_auth_data {
+        use super::super::extra_auth_data;
+        use crate::{DriverError::UnexpectedPacket, Error::DriverError};
+
+        #[test]
+        fn should_extract_extra_auth_data() {
+            // fast auth result
+            assert_eq!(extra_auth_data(&[0x01, 0x03]).unwrap(), &[0x03]);
+            assert_eq!(extra_auth_data(&[0x01, 0x04]).unwrap(), &[0x04]);
+
+            // public key
+            let key = b"-----BEGIN PUBLIC KEY-----\n...\n-----END PUBLIC KEY-----\n";
+            let packet = [&[0x01][..], &key[..]].concat();
+            assert_eq!(extra_auth_data(&packet).unwrap(), &key[..]);
         }
 
         #[test]
-        fn should_handle_multi_result_set() {
-            let opts = OptsBuilder::from_opts(get_opts())
-                .prefer_socket(false)
-                .db_name(Some("mysql"));
-            let mut conn = Conn::new(opts).unwrap();
-            conn.query_drop("DROP PROCEDURE IF EXISTS multi").unwrap();
-            conn.query_drop(
-                r#"CREATE PROCEDURE multi() BEGIN
-                        SELECT 1 UNION ALL SELECT 2;
-                        DO 1;
-                        SELECT 3 UNION ALL SELECT 4;
-                        DO 1;
-                        DO 1;
-                        SELECT REPEAT('A', 17000000);
-                        SELECT REPEAT('A', 17000000);
-                    END"#,
-            )
-            .unwrap();
-            {
-                let mut query_result = conn.query_iter("CALL multi()").unwrap();
-                let result_set = query_result
-                    .by_ref()
-                    .map(|row| row.unwrap().unwrap().pop().unwrap())
-                    .collect::<Vec<crate::Value>>();
-                assert_eq!(result_set, vec![Bytes(b"1".to_vec()), Bytes(b"2".to_vec())]);
-                let result_set = query_result
-                    .by_ref()
-                    .map(|row| row.unwrap().unwrap().pop().unwrap())
-                    .collect::<Vec<crate::Value>>();
-                assert_eq!(result_set, vec![Bytes(b"3".to_vec()), Bytes(b"4".to_vec())]);
-            }
-            let mut result = conn.query_iter("SELECT 1; SELECT 2; SELECT 3;").unwrap();
-            let mut i = 0;
-            while let Some(result_set) = result.iter() {
-                i += 1;
-                for row in result_set {
-                    match i {
-                        1 => assert_eq!(row.unwrap().unwrap(), vec![Bytes(b"1".to_vec())]),
-                        2 => assert_eq!(row.unwrap().unwrap(), vec![Bytes(b"2".to_vec())]),
-                        3 => assert_eq!(row.unwrap().unwrap(), vec![Bytes(b"3".to_vec())]),
-                        _ => unreachable!(),
-                    }
+        fn should_reject_malformed_extra_auth_data() {
+            for packet in [&[][..], &[0x01], &[0x00, 0x03], &[0xfe, 0x01]] {
+                match extra_auth_data(packet) {
+                    Err(DriverError(UnexpectedPacket)) => (),
+                    other => panic!("UnexpectedPacket expected, got {:?}", other),
                 }
             }
-            assert_eq!(i, 3);
         }
+    }
 
-        #[test]
-        fn issue_273() {
-            let opts = OptsBuilder::from_opts(get_opts()).prefer_socket(false);
-            let mut conn = Conn::new(opts).unwrap();
+    mod packets {
+        use mysql_common::proto::sync_framed::MySyncFramed;
 
-            "DROP FUNCTION IF EXISTS f1".run(&mut conn).unwrap();
-            r"CREATE DEFINER=`root`@`localhost` FUNCTION `f1`(p_arg INT, p_arg2 INT) RETURNS int
-            DETERMINISTIC
-            BEGIN
-                RETURN p_arg + p_arg2;
-            END"
-            .run(&mut conn)
-            .unwrap();
+        use std::collections::VecDeque;
 
-            "SELECT f1(?, ?)"
-                .with((100u8, 100u8))
-                .run(&mut conn)
-                .unwrap();
+        use crate::consts::MAX_PAYLOAD_LEN;
+
+        /// Writes `payload` and reads it back. Returns the wire representation.
+        fn roundtrip(payload: &[u8]) -> Vec<u8> {
+            let mut framed = MySyncFramed::new(VecDeque::<u8>::new());
+            framed.codec_mut().max_allowed_packet = 3 * MAX_PAYLOAD_LEN;
+
+            framed.send(&mut &*payload).unwrap();
+            let wire = framed.get_ref().iter().copied().collect::<Vec<u8>>();
+
+            framed.codec_mut().reset_seq_id();
+            let mut buffer = Vec::new();
+            assert!(framed.next_packet(&mut buffer).unwrap());
+            assert!(
+                buffer == payload,
+                "payload of {} bytes mismatch",
+                payload.len()
+            );
+            assert!(framed.get_ref().is_empty());
+
+            wire
+        }
+
+        /// Returns `(payload_len, seq_id)` of every packet on the wire.
+        fn headers(mut wire: &[u8]) -> Vec<(usize, u8)> {
+            let mut headers = Vec::new();
+            while !wire.is_empty() {
+                let len = wire[0] as usize | (wire[1] as usize) << 8 | (wire[2] as usize) << 16;
+                headers.push((len, wire[3]));
+                wire = &wire[4 + len..];
+            }
+            headers
         }
 
         #[test]
-        fn issue_285() {
-            let (tx, rx) = sync_channel::<()>(0);
+        fn should_roundtrip_sub_max_payload() {
+            let wire = roundtrip(&vec![b'a'; MAX_PAYLOAD_LEN - 1]);
+            assert_eq!(headers(&wire), vec![(MAX_PAYLOAD_LEN - 1, 0)]);
+        }
 
-            let handle = std::thread::spawn(move || {
-                let mut conn = Conn::new(get_opts()).unwrap();
-                const INVALID_SQL: &str = r#"
-                CREATE TEMPORARY TABLE IF NOT EXISTS `user_details` (
-                    `user_id` int(11) NOT NULL AUTO_INCREMENT,
-                    `username` varchar(255) DEFAULT NULL,
-                    `first_name` varchar(50) DEFAULT NULL,
-                    `last_name` varchar(50) DEFAULT NULL,
-                    PRIMARY KEY (`user_id`)
-                );
+        #[test]
+        fn should_terminate_max_payload_with_empty_packet() {
+            let wire = roundtrip(&vec![b'a'; MAX_PAYLOAD_LEN]);
+            assert_eq!(headers(&wire), vec![(MAX_PAYLOAD_LEN, 0), (0, 1)]);
+        }
+
+        #[test]
+        fn should_terminate_multiple_of_max_payload_with_empty_packet() {
+            let wire = roundtrip(&vec![b'a'; 2 * MAX_PAYLOAD_LEN]);
+            assert_eq!(
+                headers(&wire),
+                vec![(MAX_PAYLOAD_LEN, 0), (MAX_PAYLOAD_LEN, 1), (0, 2)]
+            );
+
+            let wire = roundtrip(&vec![b'a'; 2 * MAX_PAYLOAD_LEN + 1]);
+            assert_eq!(
+                headers(&wire),
+                vec![(MAX_PAYLOAD_LEN, 0), (MAX_PAYLOAD_LEN, 1), (1, 2)]
+            );
+        }
+    }
 
-                INSERT INTO `user_details` (`user_id`, `username`, `first_name`, `last_name`)
-                VALUES (1, 'rogers63', 'david')
-                "#;
+    mod prepare {
+        use std::collections::VecDeque;
 
-                conn.query_iter(INVALID_SQL).unwrap();
-                tx.send(()).unwrap();
-            });
+        use super::super::read_stmt_definitions;
+        use crate::{
+            DriverError::{TruncatedPrepareResponse, UnexpectedPacket},
+            Error::DriverError,
+            Result,
+        };
 
-            match rx.recv_timeout(Duration::from_secs(100_000)) {
-                Ok(_) => handle.join().unwrap(),
-                Err(_) => panic!("test failed"),
+        const EOF: &[u8] = &[0xfe, 0x00, 0x00, 0x02, 0x00];
+
+        /// Column definition of an `INT` column named `name`.
+        fn column_def(name: &str) -> Vec<u8> {
+            let mut pld = Vec::new();
+            for part in ["def", "", "", "", name, ""] {
+                pld.push(part.len() as u8);
+                pld.extend(part.as_bytes());
             }
+            pld.push(0x0c);
+            pld.extend(63_u16.to_le_bytes());
+            pld.extend(11_u32.to_le_bytes());
+            pld.push(0x03);
+            pld.extend(0_u16.to_le_bytes());
+            pld.extend([0x00, 0x00, 0x00]);
+            pld
+        }
+
+        fn read(packets: Vec<Vec<u8>>, count: u16, deprecate_eof: bool) -> Result<Vec<String>> {
+            let mut packets = packets.into_iter().collect::<VecDeque<_>>();
+            let columns = read_stmt_definitions("column", count, deprecate_eof, || {
+                Ok(packets.pop_front().expect("read past the prepare response"))
+            })?;
+            assert!(packets.is_empty(), "prepare response is not consumed");
+            Ok(columns.iter().map(|c| c.name_str().into_owned()).collect())
         }
 
         #[test]
-        fn should_work_with_named_params() {
-            let mut conn = Conn::new(get_opts()).unwrap();
-            {
-                let stmt = conn.prep("SELECT :a, :b, :a, :c").unwrap();
-                let result = conn
-                    .exec_first(&stmt, params! {"a" => 1, "b" => 2, "c" => 3})
-                    .unwrap()
-                    .unwrap();
-                assert_eq!((1_u8, 2_u8, 1_u8, 3_u8), result);
-            }
+        fn should_read_stmt_definitions() {
+            let packets = vec![column_def("a"), column_def("b"), EOF.to_vec()];
+            assert_eq!(read(packets, 2, false).unwrap(), vec!["a", "b"]);
 
-            let result = conn
-                .exec_first(
-                    "SELECT :a, :b, :a + :b, :c",
-                    params! {
-                        "a" => 1,
-                        "b" => 2,
-                        "c" => 3,
-                    },
-                )
-                .unwrap()
-                .unwrap();
-            assert_eq!((1_u8, 2_u8, 3_u8, 3_u8), result);
+            let packets = vec![column_def("a"), column_def("b")];
+            assert_eq!(read(packets, 2, true).unwrap(), vec!["a", "b"]);
         }
 
         #[test]
-        fn should_return_error_on_missing_named_parameter() {
-            let mut conn = Conn::new(get_opts()).unwrap();
-            let stmt = conn.prep("SELECT :a, :b, :a, :c, :d").unwrap();
-            let result =
-                conn.exec_first::<crate::Row, _, _>(&stmt, params! {"a" => 1, "b" => 2, "c" => 3,});
-            match result {
-                Err(DriverError(MissingNamedParameter(ref x))) if x == "d" => (),
-                _ => panic!("MissingNamedParameter error expected"),
+        fn should_reject_truncated_prepare_response() {
+            let packets = vec![column_def("a"), EOF.to_vec()];
+            let err = read(packets, 2, false).unwrap_err();
+            match err {
+                DriverError(TruncatedPrepareResponse {
+                    kind: "column",
+                    expected: 2,
+                    got: 1,
+                }) => (),
+                ref other => panic!("TruncatedPrepareResponse expected, got {:?}", other),
+            }
+            match err {
+                DriverError(ref err) => assert_eq!(
+                    err.to_string(),
+                    "Server sent 1 of 2 column definitions in reply to COM_STMT_PREPARE"
+                ),
+                _ => unreachable!(),
             }
         }
 
         #[test]
-        fn should_return_error_on_named_params_for_positional_statement() {
-            let mut conn = Conn::new(get_opts()).unwrap();
-            let stmt = conn.prep("SELECT ?, ?, ?, ?, ?").unwrap();
-            let result = conn.exec_drop(&stmt, params! {"a" => 1, "b" => 2, "c" => 3,});
-            match result {
-                Err(DriverError(NamedParamsForPositionalQuery)) => (),
-                _ => panic!("NamedParamsForPositionalQuery error expected"),
+        fn should_reject_excess_stmt_definitions() {
+            let packets = vec![column_def("a"), column_def("b")];
+            match read(packets, 1, false) {
+                Err(DriverError(UnexpectedPacket)) => (),
+                other => panic!("UnexpectedPacket expected, got {:?}", other),
             }
         }
+    }
 
-        #[test]
-        fn should_handle_tcp_connect_timeout() {
-            use crate::error::{DriverError::ConnectTimeout, Error::DriverError};
+    /// Scripted server side of a connection.
+    mod fake_server {
+        use std::{
+            io::{Read, Write},
+            net::{TcpListener, TcpStream},
+        };
 
-            let opts = OptsBuilder::from_opts(get_opts())
-                .prefer_socket(false)
-                .tcp_connect_timeout(Some(::std::time::Duration::from_millis(1000)));
-            assert!(Conn::new(opts).unwrap().ping().is_ok());
+        use crate::OptsBuilder;
 
-            let opts = OptsBuilder::from_opts(get_opts())
+        // MySql 8.0.34, but the auth plugin is `mysql_native_password`
+        const HANDSHAKE: &[u8] = b"\x0a8.0.34\x00\x0d\x00\x00\x00\x1do\x13J<.Q\x07\x00\xff\xff\xff\x02\x00\xff\xdf\x15\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00F/[\x19\x0ezl5H\x01w%\x00mysql_native_password\x00";
+        pub const OK: &[u8] = b"\x00\x00\x00\x02\x00\x00\x00";
+
+        pub fn write_packet(stream: &mut TcpStream, seq_id: u8, payload: &[u8]) {
+            let len = (payload.len() as u32).to_le_bytes();
+            stream.write_all(&[len[0], len[1], len[2], seq_id]).unwrap();
+            stream.write_all(payload).unwrap();
+        }
+
+        /// Returns `None` if the client has closed the connection.
+        pub fn read_packet(stream: &mut TcpStream) -> Option<Vec<u8>> {
+            let mut header = [0_u8; 4];
+            stream.read_exact(&mut header).ok()?;
+            let mut payload =
+                vec![0_u8; u32::from_le_bytes([header[0], header[1], header[2], 0]) as usize];
+            stream.read_exact(&mut payload).ok()?;
+            Some(payload)
+        }
+
+        /// Accepts a client and lets it in.
+        pub fn accept(listener: &TcpListener) -> TcpStream {
+            let (mut stream, _) = listener.accept().unwrap();
+            write_packet(&mut stream, 0, HANDSHAKE);
+            read_packet(&mut stream).unwrap();
+            write_packet(&mut stream, 2, OK);
+            stream
+        }
+
+        /// Options to connect to the `listener` without setup queries.
+        pub fn opts(listener: &TcpListener) -> OptsBuilder {
+            OptsBuilder::new()
+                .ip_or_hostname(Some("127.0.0.1"))
+                .tcp_port(listener.local_addr().unwrap().port())
                 .prefer_socket(false)
-                .tcp_connect_timeout(Some(::std::time::Duration::from_millis(1000)))
-                .ip_or_hostname(Some("192.168.255.255"));
-            match Conn::new(opts).unwrap_err() {
-                DriverError(ConnectTimeout) => {}
-                err => panic!("Unexpected error: {}", err),
+                .skip_setup_queries(true)
+        }
+    }
+
+    mod list_fields {
+        use std::{net::TcpListener, thread};
+
+        use super::fake_server::{accept, opts, read_packet, write_packet};
+        use crate::{Conn, DriverError, Error};
+
+        #[test]
+        fn should_fail_on_truncated_default_value() {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let opts = opts(&listener);
+            let server = thread::spawn(move || {
+                let mut stream = accept(&listener);
+                read_packet(&mut stream).unwrap();
+                // column definition of `tbl`.`a` INT
+                let mut column = Vec::new();
+                for x in ["def", "mysql", "tbl", "tbl", "a", "a"] {
+                    column.push(x.len() as u8);
+                    column.extend_from_slice(x.as_bytes());
+                }
+                column.extend_from_slice(b"\x0c\x3f\x00\x0b\x00\x00\x00\x03\x00\x00\x00\x00\x00");
+                // default value of 5 bytes, but only 2 bytes are there
+                column.extend_from_slice(b"\x0542");
+                write_packet(&mut stream, 1, &column);
+                while read_packet(&mut stream).is_some() {}
+            });
+
+            let mut conn = Conn::new(opts).unwrap();
+            match conn.list_fields("tbl", "") {
+                Err(Error::DriverError(DriverError::UnexpectedPacket)) => (),
+                other => panic!("expected UnexpectedPacket, got {:?}", other),
             }
+            drop(conn);
+            server.join().unwrap();
         }
+    }
+
+    mod ping {
+        use std::{net::TcpListener, thread, time::Duration};
+
+        use super::fake_server::{accept, opts, read_packet};
+        use crate::{consts::Command, Conn};
 
         #[test]
-        fn should_set_additional_capabilities() {
-            use crate::consts::CapabilityFlags;
+        fn should_mark_connection_broken_if_ping_times_out() {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let opts = opts(&listener);
+            let server = thread::spawn(move || {
+                let mut stream = accept(&listener);
+                // never replies
+                let mut commands = Vec::new();
+                while let Some(payload) = read_packet(&mut stream) {
+                    commands.push(payload[0]);
+                }
+                commands
+            });
 
-            let opts = OptsBuilder::from_opts(get_opts())
-                .additional_capabilities(CapabilityFlags::CLIENT_FOUND_ROWS);
+            let mut conn = Conn::new(opts).unwrap();
+            assert!(conn.ping_with_timeout(Duration::from_millis(50)).is_err());
+            // nothing is sent over the broken connection
+            assert!(conn.ping().is_err());
+            drop(conn);
+            assert_eq!(server.join().unwrap(), vec![Command::COM_PING as u8]);
+        }
+    }
+
+    mod query_timeout {
+        use std::{net::TcpListener, thread};
+
+        use super::fake_server::{accept, opts, read_packet, write_packet, OK};
+        use crate::{consts::Command, Conn};
+
+        /// Returns queries, that `query_with_timeout` sends to a server of the given versions.
+        fn sent_queries(
+            server_version: Option<(u16, u16, u16)>,
+            mariadb_server_version: Option<(u16, u16, u16)>,
+            queries: &[&str],
+        ) -> Vec<String> {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let opts = opts(&listener);
+            let server = thread::spawn(move || {
+                let mut stream = accept(&listener);
+                let mut queries = Vec::new();
+                while let Some(payload) = read_packet(&mut stream) {
+                    if payload[0] == Command::COM_QUERY as u8 {
+                        queries.push(String::from_utf8(payload[1..].to_vec()).unwrap());
+                        write_packet(&mut stream, 1, OK);
+                    }
+                }
+                queries
+            });
 
             let mut conn = Conn::new(opts).unwrap();
-            conn.query_drop("CREATE TEMPORARY TABLE mysql.tbl (a INT, b TEXT)")
-                .unwrap();
-            conn.query_drop("INSERT INTO mysql.tbl (a, b) VALUES (1, 'foo')")
-                .unwrap();
-            let result = conn
-                .query_iter("UPDATE mysql.tbl SET b = 'foo' WHERE a = 1")
-                .unwrap();
-            assert_eq!(result.affected_rows(), 1);
+            conn.0.server_version = server_version;
+            conn.0.mariadb_server_version = mariadb_server_version;
+            for query in queries {
+                let rows: Vec<u8> = conn.query_with_timeout(query, 1500).unwrap();
+                assert!(rows.is_empty());
+            }
+            drop(conn);
+            server.join().unwrap()
         }
 
         #[test]
-        fn should_bind_before_connect() {
-            let port = 28000 + (rand::random::<u16>() % 2000);
-            let opts = OptsBuilder::from_opts(get_opts())
-                .prefer_socket(false)
-                .ip_or_hostname(Some("localhost"))
-                .bind_address(Some(([127, 0, 0, 1], port)));
-            let conn = Conn::new(opts).unwrap();
-            let debug_format: String = format!("{:?}", conn);
-            let expected_1 = format!("addr: V4(127.0.0.1:{})", port);
-            let expected_2 = format!("addr: 127.0.0.1:{}", port);
-            assert!(
-                debug_format.contains(&expected_1) || debug_format.contains(&expected_2),
-                "debug_format: {}",
-                debug_format
+        fn should_bound_only_selects_on_mysql() {
+            let queries = sent_queries(Some((8, 0, 34)), None, &[" select 1", "DO 1", "SELECTx"]);
+            assert_eq!(
+                queries,
+                vec![
+                    "select /*+ MAX_EXECUTION_TIME(1500) */ 1",
+                    "DO 1",
+                    "SELECTx"
+                ]
             );
         }
 
         #[test]
-        fn should_bind_before_connect_with_timeout() {
-            let port = 30000 + (rand::random::<u16>() % 2000);
-            let opts = OptsBuilder::from_opts(get_opts())
-                .prefer_socket(false)
-                .ip_or_hostname(Some("localhost"))
-                .bind_address(Some(([127, 0, 0, 1], port)))
-                .tcp_connect_timeout(Some(::std::time::Duration::from_millis(1000)));
-            let mut conn = Conn::new(opts).unwrap();
-            assert!(conn.ping().is_ok());
-            let debug_format: String = format!("{:?}", conn);
-            let expected_1 = format!("addr: V4(127.0.0.1:{})", port);
-            let expected_2 = format!("addr: 127.0.0.1:{}", port);
-            assert!(
-                debug_format.contains(&expected_1) || debug_format.contains(&expected_2),
-                "debug_format: {}",
-                debug_format
-            );
+        fn should_not_bound_queries_on_old_mysql() {
+            let queries = sent_queries(Some((5, 7, 7)), None, &["SELECT 1"]);
+            assert_eq!(queries, vec!["SELECT 1"]);
         }
 
         #[test]
-        fn should_not_cache_statements_if_stmt_cache_size_is_zero() {
-            let opts = OptsBuilder::from_opts(get_opts()).stmt_cache_size(0);
-            let mut conn = Conn::new(opts).unwrap();
+        fn should_bound_any_statement_on_mariadb() {
+            let queries = sent_queries(None, Some((10, 6, 0)), &["SELECT 1", "DO 1"]);
+            assert_eq!(
+                queries,
+                vec![
+                    "SET STATEMENT max_statement_time = 1.500 FOR SELECT 1",
+                    "SET STATEMENT max_statement_time = 1.500 FOR DO 1",
+                ]
+            );
+            let queries = sent_queries(None, Some((10, 1, 1)), &["SELECT 1"]);
+            assert_eq!(queries, vec!["SELECT 1"]);
+        }
+    }
 
-            let stmt1 = conn.prep("DO 1").unwrap();
-            let stmt2 = conn.prep("DO 2").unwrap();
-            let stmt3 = conn.prep("DO 3").unwrap();
+    mod quit {
+        use std::{net::TcpListener, thread};
 
-            conn.close(stmt1).unwrap();
-            conn.close(stmt2).unwrap();
-            conn.close(stmt3).unwrap();
+        use super::fake_server::{accept, opts, read_packet};
+        use crate::{consts::Command, Conn};
 
-            let status: (Value, u8) = conn
-                .query_first("SHOW SESSION STATUS LIKE 'Com_stmt_close';")
-                .unwrap()
-                .unwrap();
-            assert_eq!(status.1, 3);
+        #[test]
+        fn should_quit_gracefully_on_drop() {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let opts = opts(&listener);
+            let server = thread::spawn(move || {
+                let mut stream = accept(&listener);
+                let mut commands = Vec::new();
+                while let Some(payload) = read_packet(&mut stream) {
+                    commands.push(payload[0]);
+                }
+                commands
+            });
+
+            drop(Conn::new(opts).unwrap());
+            // the server won't count the connection as aborted
+            assert_eq!(server.join().unwrap(), vec![Command::COM_QUIT as u8]);
         }
+    }
 
-        #[test]
-        fn should_hold_stmt_cache_size_bounds() {
-            let opts = OptsBuilder::from_opts(get_opts()).stmt_cache_size(3);
-            let mut conn = Conn::new(opts).unwrap();
+    mod reprepare {
+        use std::{net::TcpListener, thread};
 
-            conn.prep("DO 1").unwrap();
-            conn.prep("DO 2").unwrap();
-            conn.prep("DO 3").unwrap();
-            conn.prep("DO 1").unwrap();
-            conn.prep("DO 4").unwrap();
-            conn.prep("DO 3").unwrap();
-            conn.prep("DO 5").unwrap();
-            conn.prep("DO 6").unwrap();
+        use super::fake_server::{accept, opts, read_packet, write_packet, OK};
+        use crate::{consts::Command, prelude::*, Conn};
 
-            let status: (String, usize) = conn
-                .query_first("SHOW SESSION STATUS LIKE 'Com_stmt_close'")
-                .unwrap()
-                .unwrap();
+        // ER_NEED_REPREPARE
+        const NEED_REPREPARE: &[u8] =
+            b"\xff\x4f\x06#HY000Prepared statement needs to be re-prepared";
 
-            assert_eq!(status.1, 3);
+        const PREPARE: u8 = Command::COM_STMT_PREPARE as u8;
+        const EXECUTE: u8 = Command::COM_STMT_EXECUTE as u8;
+        const CLOSE: u8 = Command::COM_STMT_CLOSE as u8;
+        const QUIT: u8 = Command::COM_QUIT as u8;
 
-            let mut order = conn
-                .0
-                .stmt_cache
-                .iter()
-                .map(|(_, entry)| &**entry.query.0.as_ref())
-                .collect::<Vec<&[u8]>>();
-            order.sort();
-            assert_eq!(order, &[b"DO 3", b"DO 5", b"DO 6"]);
+        /// Reply to `COM_STMT_PREPARE` for a statement without params and columns.
+        fn prepare_ok(stmt_id: u32) -> Vec<u8> {
+            let mut payload = vec![0x00];
+            payload.extend_from_slice(&stmt_id.to_le_bytes());
+            payload.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0]);
+            payload
         }
 
-        #[test]
-        fn should_handle_json_columns() {
-            use crate::{Deserialized, Serialized};
-            use serde_json::Value as Json;
-            use std::str::FromStr;
-
-            #[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
-            pub struct DecTest {
-                foo: String,
-                quux: (u64, String),
+        /// Serves a single connection, that fails the first execution with `ER_NEED_REPREPARE`.
+        /// Returns commands sent by the client (command byte and statement id, if any).
+        fn serve(listener: TcpListener) -> Vec<(u8, Option<u32>)> {
+            let mut stream = accept(&listener);
+
+            let mut commands = Vec::new();
+            let mut next_stmt_id = 1;
+            while let Some(payload) = read_packet(&mut stream) {
+                let stmt_id =
+                    || u32::from_le_bytes([payload[1], payload[2], payload[3], payload[4]]);
+                let command = match payload[0] {
+                    PREPARE => {
+                        write_packet(&mut stream, 1, &prepare_ok(next_stmt_id));
+                        next_stmt_id += 1;
+                        (PREPARE, None)
+                    }
+                    EXECUTE => {
+                        let reply = if stmt_id() == 1 { NEED_REPREPARE } else { OK };
+                        write_packet(&mut stream, 1, reply);
+                        (EXECUTE, Some(stmt_id()))
+                    }
+                    CLOSE => (CLOSE, Some(stmt_id())),
+                    other => (other, None),
+                };
+                commands.push(command);
             }
+            commands
+        }
 
-            let decodable = DecTest {
-                foo: "bar".into(),
-                quux: (42, "hello".into()),
-            };
+        fn execute_and_quit(stmt_cache_size: usize) -> Vec<(u8, Option<u32>)> {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let opts = opts(&listener).stmt_cache_size(stmt_cache_size);
+            let server = thread::spawn(move || serve(listener));
 
-            let mut conn = Conn::new(get_opts()).unwrap();
-            if conn
-                .query_drop("CREATE TEMPORARY TABLE mysql.tbl(a VARCHAR(32), b JSON)")
-                .is_err()
-            {
-                conn.query_drop("CREATE TEMPORARY TABLE mysql.tbl(a VARCHAR(32), b TEXT)")
-                    .unwrap();
-            }
-            conn.exec_drop(
-                r#"INSERT INTO mysql.tbl VALUES ('hello', ?)"#,
-                (Serialized(&decodable),),
-            )
-            .unwrap();
+            let mut conn = Conn::new(opts).unwrap();
+            let stmt = conn.prep("DO 1").unwrap();
+            conn.exec_drop(&stmt, ()).unwrap();
+            conn.close(stmt).unwrap();
+            drop(conn);
 
-            let (a, b): (String, Json) = conn
-                .query_first("SELECT a, b FROM mysql.tbl")
-                .unwrap()
-                .unwrap();
+            server.join().unwrap()
+        }
+
+        #[test]
+        fn should_reprepare_statement_on_need_reprepare_error() {
             assert_eq!(
-                (a, b),
-                (
-                    "hello".into(),
-                    Json::from_str(r#"{"foo": "bar", "quux": [42, "hello"]}"#).unwrap()
-                )
+                execute_and_quit(10),
+                vec![
+                    (PREPARE, None),
+                    (EXECUTE, Some(1)),
+                    (PREPARE, None),
+                    (EXECUTE, Some(2)),
+                    (CLOSE, Some(1)),
+                    (CLOSE, Some(2)),
+                    (QUIT, None),
+                ]
             );
+        }
 
-            let row = conn
-                .exec_first("SELECT a, b FROM mysql.tbl WHERE a = ?", ("hello",))
-                .unwrap()
-                .unwrap();
-            let (a, Deserialized(b)) = from_row(row);
-            assert_eq!((a, b), (String::from("hello"), decodable));
+        #[test]
+        fn should_close_reprepared_statement_if_stmt_cache_is_disabled() {
+            assert_eq!(
+                execute_and_quit(0),
+                vec![
+                    (PREPARE, None),
+                    (EXECUTE, Some(1)),
+                    (PREPARE, None),
+                    (EXECUTE, Some(2)),
+                    (CLOSE, Some(1)),
+                    (CLOSE, Some(2)),
+                    (QUIT, None),
+                ]
+            );
         }
+    }
 
-        #[test]
-        fn should_set_connect_attrs() {
-            let opts = OptsBuilder::from_opts(
-                get_opts().connect_attrs::<String, String>(Some(Default::default())),
-            );
-            let mut conn = Conn::new(opts).unwrap();
+    mod session_state {
+        use std::io::Write;
 
-            let support_connect_attrs = match (conn.0.server_version, conn.0.mariadb_server_version)
-            {
-                (Some(ref version), _) if *version >= (5, 6, 0) => true,
-                (_, Some(ref version)) if *version >= (10, 0, 0) => true,
-                _ => false,
-            };
+        use mysql_common::packets::CommonOkPacket;
 
-            if support_connect_attrs {
-                // MySQL >= 5.6 or MariaDB >= 10.0
+        use crate::{
+            buffer_pool::get_buffer,
+            conn::{Conn, ConnInner},
+            consts::CapabilityFlags,
+            OptsBuilder,
+        };
 
-                if get_system_variable::<String>(&mut conn, "performance_schema") != "ON" {
-                    panic!("The system variable `performance_schema` is off. Restart the MySQL server with `--performance_schema=on` to pass the test.");
-                }
-                let attrs_size: i32 =
-                    get_system_variable(&mut conn, "performance_schema_session_connect_attrs_size");
-                if (0..=128).contains(&attrs_size) {
-                    panic!("The system variable `performance_schema_session_connect_attrs_size` is {}. Restart the MySQL server with `--performance_schema_session_connect_attrs_size=-1` to pass the test.", attrs_size);
-                }
+        #[test]
+        fn should_skip_malformed_session_state_info() {
+            let mut conn = Conn(Box::new(ConnInner::empty(OptsBuilder::new().into())));
+            conn.0.capability_flags =
+                CapabilityFlags::CLIENT_PROTOCOL_41 | CapabilityFlags::CLIENT_SESSION_TRACK;
 
-                fn assert_connect_attrs(conn: &mut Conn, expected_values: &[(&str, &str)]) {
-                    let mut actual_values = HashMap::new();
-                    for row in conn.query_iter("SELECT attr_name, attr_value FROM performance_schema.session_account_connect_attrs WHERE processlist_id = connection_id()").unwrap() {
-                        let (name, value) = from_row::<(String, String)>(row.unwrap());
-                        actual_values.insert(name, value);
-                    }
+            let mut buffer = get_buffer();
+            buffer
+                .as_mut()
+                // header, affected rows, last insert id
+                .write_all(b"\x00\x00\x00")
+                .unwrap();
+            // SERVER_STATUS_AUTOCOMMIT | SERVER_SESSION_STATE_CHANGED, no warnings, empty info
+            buffer.as_mut().write_all(b"\x02\x40\x00\x00\x00").unwrap();
+            // schema change with a truncated schema name, then a valid one
+            buffer
+                .as_mut()
+                .write_all(b"\x0a\x01\x02\x05a\x01\x04\x03foo")
+                .unwrap();
 
-                    for (name, value) in expected_values {
-                        assert_eq!(
-                            actual_values.get(&name.to_string()),
-                            Some(&value.to_string())
-                        );
-                    }
-                }
+            conn.handle_ok::<CommonOkPacket>(&buffer).unwrap();
+            assert_eq!(conn.current_db(), Some("foo"));
+        }
+    }
 
-                let pid = process::id().to_string();
-                let prog_name = std::env::args_os()
-                    .next()
-                    .unwrap()
-                    .to_string_lossy()
-                    .into_owned();
-                let mut expected_values = vec![
-                    ("_client_name", "rust-mysql-simple"),
-                    ("_client_version", env!("CARGO_PKG_VERSION")),
-                    ("_os", env!("CARGO_CFG_TARGET_OS")),
-                    ("_pid", &pid),
-                    ("_platform", env!("CARGO_CFG_TARGET_ARCH")),
-                    ("program_name", &prog_name),
-                ];
+    mod long_data {
+        use super::super::{long_data_chunk_len, LONG_DATA_HEADER_LEN};
+        use crate::consts::MAX_PAYLOAD_LEN;
 
-                // No connect attributes are added.
-                assert_connect_attrs(&mut conn, &expected_values);
+        #[test]
+        fn should_fit_long_data_chunk_into_packet() {
+            assert_eq!(long_data_chunk_len(1024), 1024 - LONG_DATA_HEADER_LEN);
+            assert_eq!(
+                long_data_chunk_len(1024 * 1024 * 1024),
+                MAX_PAYLOAD_LEN - LONG_DATA_HEADER_LEN
+            );
+            // degenerate limits don't underflow
+            assert_eq!(long_data_chunk_len(LONG_DATA_HEADER_LEN), 1);
+            assert_eq!(long_data_chunk_len(0), 1);
+        }
+    }
 
-                // Connect attributes are added.
-                let opts = OptsBuilder::from_opts(get_opts());
-                let mut connect_attrs = HashMap::with_capacity(3);
-                connect_attrs.insert("foo", "foo val");
-                connect_attrs.insert("bar", "bar val");
-                connect_attrs.insert("program_name", "my program name");
-                let mut conn = Conn::new(opts.connect_attrs(Some(connect_attrs))).unwrap();
-                expected_values.pop(); // remove program_name at the last
-                expected_values.push(("foo", "foo val"));
-                expected_values.push(("bar", "bar val"));
-                expected_values.push(("program_name", "my program name"));
-                assert_connect_attrs(&mut conn, &expected_values);
-            }
+    mod typed_execute {
+        use mysql_common::{packets::ComStmtExecuteRequestBuilder, proto::MySerialize};
+
+        use super::super::TypedExecuteRequest;
+        use crate::{consts::ColumnType, Value};
+
+        fn serialize<T: MySerialize>(request: &T) -> Vec<u8> {
+            let mut buf = Vec::new();
+            request.serialize(&mut buf);
+            buf
         }
 
         #[test]
-        #[cfg(feature = "binlog")]
-        fn should_read_binlog() -> crate::Result<()> {
-            use std::{
-                collections::HashMap, sync::mpsc::sync_channel, thread::spawn, time::Duration,
+        fn should_only_override_param_types() {
+            let params = vec![
+                Value::NULL,
+                Value::Bytes(b"{}".to_vec()),
+                Value::UInt(42),
+                Value::Double(0.5),
+            ];
+            let default_types = [
+                ColumnType::MYSQL_TYPE_NULL,
+                ColumnType::MYSQL_TYPE_VAR_STRING,
+                ColumnType::MYSQL_TYPE_LONGLONG,
+                ColumnType::MYSQL_TYPE_DOUBLE,
+            ];
+            let (body, _) = ComStmtExecuteRequestBuilder::new(7).build(&params);
+            let expected = serialize(&body);
+            let typed = TypedExecuteRequest {
+                body,
+                types: &default_types,
             };
+            assert_eq!(serialize(&typed), expected);
+
+            let (body, _) = ComStmtExecuteRequestBuilder::new(7).build(&params);
+            let typed = TypedExecuteRequest {
+                body,
+                types: &[
+                    ColumnType::MYSQL_TYPE_NULL,
+                    ColumnType::MYSQL_TYPE_JSON,
+                    ColumnType::MYSQL_TYPE_LONGLONG,
+                    ColumnType::MYSQL_TYPE_DOUBLE,
+                ],
+            };
+            let actual = serialize(&typed);
+            let diff = actual
+                .iter()
+                .zip(&expected)
+                .enumerate()
+                .filter(|(_, (a, e))| a != e)
+                .map(|(i, (a, _))| (i, *a))
+                .collect::<Vec<_>>();
+            // command, statement id, flags, iteration count, NULL-bitmap, new-params-bound flag,
+            // then the type of the second parameter
+            assert_eq!(diff, vec![(14, ColumnType::MYSQL_TYPE_JSON as u8)]);
+            assert_eq!(actual.len(), expected.len());
+        }
+    }
 
-            fn gen_dummy_data() -> crate::Result<()> {
-                let mut conn = Conn::new(get_opts())?;
-
-                "CREATE TABLE IF NOT EXISTS customers (customer_id int not null)".run(&mut conn)?;
-
-                for i in 0_u8..100 {
-                    "INSERT INTO customers(customer_id) VALUES (?)"
-                        .with((i,))
-                        .run(&mut conn)?;
-                }
+    mod handshake_response {
+        use mysql_common::{io::ReadMysqlExt, packets::AuthPlugin};
 
-                "DROP TABLE customers".run(&mut conn)?;
+        use std::collections::HashMap;
 
-                Ok(())
-            }
+        use crate::{
+            conn::{Conn, ConnInner},
+            consts::CapabilityFlags,
+            OptsBuilder,
+        };
 
-            fn get_conn() -> crate::Result<(Conn, Vec<u8>, u64)> {
-                let mut conn = Conn::new(get_opts())?;
+        const NONCE: [u8; 20] = *b"0123456789abcdefghij";
+        // 4 bytes of capabilities, 4 bytes of max packet size,
+        // 1 byte of collation, 23 bytes of filler and the NUL-terminated user name.
+        const AUTH_RESPONSE_OFFSET: usize = 32 + b"root\0".len();
 
-                if let Ok(Some(gtid_mode)) =
-                    "SELECT @@GLOBAL.GTID_MODE".first::<String, _>(&mut conn)
-                {
-                    if !gtid_mode.starts_with("ON") {
-                        panic!(
-                            "GTID_MODE is disabled \
-                                (enable using --gtid_mode=ON --enforce_gtid_consistency=ON)"
-                        );
-                    }
-                }
+        fn handshake_response(capability_flags: CapabilityFlags) -> (CapabilityFlags, Vec<u8>) {
+            handshake_response_with_opts(OptsBuilder::new(), capability_flags)
+        }
 
-                let row: crate::Row = "SHOW BINARY LOGS".first(&mut conn)?.unwrap();
-                let filename = row.get(0).unwrap();
-                let position = row.get(1).unwrap();
+        fn handshake_response_with_opts(
+            opts: OptsBuilder,
+            capability_flags: CapabilityFlags,
+        ) -> (CapabilityFlags, Vec<u8>) {
+            let opts = opts.user(Some("root")).pass(Some("password"));
+            let mut conn = Conn(Box::new(ConnInner::empty(opts.into())));
+            conn.0.capability_flags = CapabilityFlags::CLIENT_PROTOCOL_41 | capability_flags;
+            conn.0.nonce = NONCE.to_vec();
+
+            let payload = conn.handshake_response().to_vec();
+            let mut flags = [0_u8; 4];
+            flags.copy_from_slice(&payload[..4]);
+            let flags = CapabilityFlags::from_bits_truncate(u32::from_le_bytes(flags));
+            assert_eq!(&payload[32..AUTH_RESPONSE_OFFSET], b"root\0");
+            (flags, payload[AUTH_RESPONSE_OFFSET..].to_vec())
+        }
 
-                gen_dummy_data().unwrap();
-                Ok((conn, filename, position))
+        /// Reads the connection attributes block that follows the auth response.
+        fn read_connect_attrs(auth_response: &[u8]) -> HashMap<String, String> {
+            let attrs_offset = 1 + 20 + b"mysql_native_password\0".len();
+            let mut attrs_block = &auth_response[attrs_offset..];
+            let len = attrs_block.read_lenenc_int().unwrap();
+            assert_eq!(len as usize, attrs_block.len());
+
+            let mut attrs = HashMap::new();
+            while !attrs_block.is_empty() {
+                let name = attrs_block.read_lenenc_str().unwrap();
+                let value = attrs_block.read_lenenc_str().unwrap();
+                attrs.insert(
+                    String::from_utf8(name).unwrap(),
+                    String::from_utf8(value).unwrap(),
+                );
             }
+            attrs
+        }
 
-            // iterate using COM_BINLOG_DUMP
-            let (conn, filename, pos) = get_conn().unwrap();
-            let is_mariadb = conn.0.mariadb_server_version.is_some();
+        fn auth_data() -> Vec<u8> {
+            AuthPlugin::MysqlNativePassword
+                .gen_data(Some("password"), &NONCE)
+                .unwrap()
+                .to_vec()
+        }
 
-            let binlog_stream = conn
-                .get_binlog_stream(BinlogRequest::new(12).with_filename(filename).with_pos(pos))
-                .unwrap();
+        #[test]
+        fn should_write_lenenc_auth_response_with_plugin_name() {
+            let (flags, auth_response) = handshake_response(
+                CapabilityFlags::CLIENT_SECURE_CONNECTION
+                    | CapabilityFlags::CLIENT_PLUGIN_AUTH
+                    | CapabilityFlags::CLIENT_PLUGIN_AUTH_LENENC_CLIENT_DATA,
+            );
+            assert!(flags.contains(CapabilityFlags::CLIENT_PLUGIN_AUTH));
 
-            let mut events_num = 0;
-            let (tx, rx) = sync_channel(0);
-            spawn(move || {
-                for event in binlog_stream {
-                    tx.send(event).unwrap();
-                }
-            });
-            let mut tmes = HashMap::new();
-            while let Ok(event) = rx.recv_timeout(Duration::from_secs(1)) {
-                let event = event.unwrap();
-                events_num += 1;
+            let mut expected = vec![20];
+            expected.extend(auth_data());
+            expected.extend_from_slice(b"mysql_native_password\0");
+            assert_eq!(auth_response, expected);
+        }
 
-                // assert that event type is known
-                event.header().event_type().unwrap();
+        #[test]
+        fn should_write_length_prefixed_auth_response_with_plugin_name() {
+            let (flags, auth_response) = handshake_response(
+                CapabilityFlags::CLIENT_SECURE_CONNECTION | CapabilityFlags::CLIENT_PLUGIN_AUTH,
+            );
+            assert!(flags.contains(CapabilityFlags::CLIENT_PLUGIN_AUTH));
 
-                // iterate over rows of an event
-                match event.read_data()?.unwrap() {
-                    EventData::TableMapEvent(tme) => {
-                        tmes.insert(tme.table_id(), tme.into_owned());
-                    }
-                    EventData::RowsEvent(re) => {
-                        for row in re.rows(&tmes[&re.table_id()]) {
-                            row.unwrap();
-                        }
-                    }
-                    _ => (),
-                }
-            }
-            assert!(events_num > 0);
+            let mut expected = vec![20];
+            expected.extend(auth_data());
+            expected.extend_from_slice(b"mysql_native_password\0");
+            assert_eq!(auth_response, expected);
+        }
 
-            if !is_mariadb {
-                // iterate using COM_BINLOG_DUMP_GTID
-                let (conn, filename, pos) = get_conn().unwrap();
+        #[test]
+        fn should_write_length_prefixed_auth_response_without_plugin_auth() {
+            let (flags, auth_response) =
+                handshake_response(CapabilityFlags::CLIENT_SECURE_CONNECTION);
+            assert!(!flags.contains(CapabilityFlags::CLIENT_PLUGIN_AUTH));
+
+            let mut expected = vec![20];
+            expected.extend(auth_data());
+            assert_eq!(auth_response, expected);
+        }
 
-                let binlog_stream = conn
-                    .get_binlog_stream(
-                        BinlogRequest::new(13)
-                            .with_use_gtid(true)
-                            .with_filename(filename)
-                            .with_pos(pos),
-                    )
-                    .unwrap();
+        #[test]
+        fn should_write_nul_terminated_auth_response_without_secure_connection() {
+            let (flags, auth_response) = handshake_response(CapabilityFlags::empty());
+            assert!(!flags.contains(CapabilityFlags::CLIENT_SECURE_CONNECTION));
+            assert!(!flags.contains(CapabilityFlags::CLIENT_PLUGIN_AUTH));
+
+            let mut expected = auth_data();
+            expected.push(0);
+            assert_eq!(auth_response, expected);
+        }
 
-                let mut events_num = 0;
-                let (tx, rx) = sync_channel(0);
-                spawn(move || {
-                    for event in binlog_stream {
-                        tx.send(event).unwrap();
-                    }
-                });
-                let mut tmes = HashMap::new();
-                while let Ok(event) = rx.recv_timeout(Duration::from_secs(1)) {
-                    let event = event.unwrap();
-                    events_num += 1;
+        #[test]
+        fn should_write_length_prefixed_connect_attrs() {
+            let mut attrs = HashMap::new();
+            attrs.insert("foo", "foo val");
+            attrs.insert("program_name", "my program name");
+            let (flags, auth_response) = handshake_response_with_opts(
+                OptsBuilder::new().connect_attrs(Some(attrs)),
+                CapabilityFlags::CLIENT_SECURE_CONNECTION
+                    | CapabilityFlags::CLIENT_PLUGIN_AUTH
+                    | CapabilityFlags::CLIENT_CONNECT_ATTRS,
+            );
+            assert!(flags.contains(CapabilityFlags::CLIENT_CONNECT_ATTRS));
 
-                    // assert that event type is known
-                    event.header().event_type().unwrap();
+            let attrs = read_connect_attrs(&auth_response);
+            assert_eq!(attrs["_client_name"], "rust-mysql-simple");
+            assert_eq!(attrs["_client_version"], env!("CARGO_PKG_VERSION"));
+            assert_eq!(attrs["foo"], "foo val");
+            assert_eq!(attrs["program_name"], "my program name");
+        }
 
-                    // iterate over rows of an event
-                    match event.read_data()?.unwrap() {
-                        EventData::TableMapEvent(tme) => {
-                            tmes.insert(tme.table_id(), tme.into_owned());
-                        }
-                        EventData::RowsEvent(re) => {
-                            for row in re.rows(&tmes[&re.table_id()]) {
-                                row.unwrap();
-                            }
-                        }
-                        _ => (),
-                    }
-                }
-                assert!(events_num > 0);
-            }
+        #[test]
+        fn should_write_program_name_connect_attr() {
+            let mut attrs = HashMap::new();
+            attrs.insert("program_name", "overridden");
+            let (flags, auth_response) = handshake_response_with_opts(
+                OptsBuilder::new()
+                    .connect_attrs(Some(attrs))
+                    .program_name(Some("billing")),
+                CapabilityFlags::CLIENT_SECURE_CONNECTION
+                    | CapabilityFlags::CLIENT_PLUGIN_AUTH
+                    | CapabilityFlags::CLIENT_CONNECT_ATTRS,
+            );
+            assert!(flags.contains(CapabilityFlags::CLIENT_CONNECT_ATTRS));
+            assert_eq!(
+                read_connect_attrs(&auth_response)["program_name"],
+                "billing"
+            );
 
-            // iterate using COM_BINLOG_DUMP with BINLOG_DUMP_NON_BLOCK flag
-            let (conn, filename, pos) = get_conn().unwrap();
+            // standalone (connection attributes are disabled)
+            let (flags, auth_response) = handshake_response_with_opts(
+                OptsBuilder::new()
+                    .connect_attrs::<String, String>(None)
+                    .program_name(Some("billing")),
+                CapabilityFlags::CLIENT_SECURE_CONNECTION
+                    | CapabilityFlags::CLIENT_PLUGIN_AUTH
+                    | CapabilityFlags::CLIENT_CONNECT_ATTRS,
+            );
+            assert!(flags.contains(CapabilityFlags::CLIENT_CONNECT_ATTRS));
+            let attrs = read_connect_attrs(&auth_response);
+            assert_eq!(attrs["program_name"], "billing");
+            assert_eq!(attrs["_client_name"], "rust-mysql-simple");
+        }
 
-            let binlog_stream = conn
-                .get_binlog_stream(
-                    BinlogRequest::new(14)
-                        .with_filename(filename)
-                        .with_pos(pos)
-                        .with_flags(crate::BinlogDumpFlags::BINLOG_DUMP_NON_BLOCK),
-                )
-                .unwrap();
+        #[test]
+        fn should_ignore_program_name_if_connect_attrs_not_supported() {
+            let (flags, auth_response) = handshake_response_with_opts(
+                OptsBuilder::new()
+                    .connect_attrs::<String, String>(None)
+                    .program_name(Some("billing")),
+                CapabilityFlags::CLIENT_SECURE_CONNECTION | CapabilityFlags::CLIENT_PLUGIN_AUTH,
+            );
+            assert!(!flags.contains(CapabilityFlags::CLIENT_CONNECT_ATTRS));
 
-            events_num = 0;
-            for event in binlog_stream {
-                let event = event.unwrap();
-                events_num += 1;
-                event.header().event_type().unwrap();
-                event.read_data()?;
-            }
-            assert!(events_num > 0);
+            let mut expected = vec![20];
+            expected.extend(auth_data());
+            expected.extend_from_slice(b"mysql_native_password\0");
+            assert_eq!(auth_response, expected);
+        }
 
-            Ok(())
+        #[test]
+        fn should_not_write_connect_attrs_if_not_supported() {
+            let attrs = HashMap::<String, String>::new();
+            let (flags, auth_response) = handshake_response_with_opts(
+                OptsBuilder::new().connect_attrs(Some(attrs)),
+                CapabilityFlags::CLIENT_SECURE_CONNECTION | CapabilityFlags::CLIENT_PLUGIN_AUTH,
+            );
+            assert!(!flags.contains(CapabilityFlags::CLIENT_CONNECT_ATTRS));
+
+            let mut expected = vec![20];
+            expected.extend(auth_data());
+            expected.extend_from_slice(b"mysql_native_password\0");
+            assert_eq!(auth_response, expected);
         }
     }
 
@@ -2618,6 +6238,22 @@ mod test {
             })
         }
 
+        #[bench]
+        fn simple_query_first(bencher: &mut test::Bencher) {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            bencher.iter(|| {
+                let _: Option<crate::Value> = conn.query_first("SELECT 1").unwrap();
+            })
+        }
+
+        #[bench]
+        fn simple_query_scalar(bencher: &mut test::Bencher) {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            bencher.iter(|| {
+                let _ = conn.query_scalar("SELECT 1").unwrap();
+            })
+        }
+
         #[bench]
         fn simple_prepared_query_row(bencher: &mut test::Bencher) {
             let mut conn = Conn::new(get_opts()).unwrap();
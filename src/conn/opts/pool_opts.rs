@@ -6,6 +6,8 @@
 // option. All files in the project carrying such notice may not be copied,
 // modified, or distributed except according to those terms.
 
+use std::time::Duration;
+
 macro_rules! const_assert {
     ($name:ident, $($xs:expr),+ $(,)*) => {
         #[allow(unknown_lints, clippy::eq_op)]
@@ -27,6 +29,9 @@ pub struct PoolOpts {
     constraints: PoolConstraints,
     reset_connection: bool,
     check_health: bool,
+    max_idle_before_ping: Duration,
+    ping_timeout: Option<Duration>,
+    idle_heartbeat: Option<Duration>,
 }
 
 impl PoolOpts {
@@ -114,6 +119,95 @@ impl PoolOpts {
     pub fn check_health(&self) -> bool {
         self.check_health
     }
+
+    /// Sets the idle time after which a pooled connection is pinged upon retrieving it
+    /// from a pool (defaults to zero, i.e. always ping). Only applies if
+    /// [`PoolOpts::check_health`] is `true`.
+    ///
+    /// A connection, that was used recently, is likely alive, so it's given out
+    /// without the additional roundtrip. A dead connection will surface as an error
+    /// of the next command in this case.
+    ///
+    /// # Connection URL
+    ///
+    /// Use `max_idle_before_ping_ms` URL parameter to set this value. E.g.
+    ///
+    /// ```
+    /// # use mysql::*;
+    /// # use std::time::Duration;
+    /// # fn main() -> Result<()> {
+    /// let opts = Opts::from_url("mysql://localhost/db?max_idle_before_ping_ms=5000")?;
+    /// assert_eq!(opts.get_pool_opts().max_idle_before_ping(), Duration::from_secs(5));
+    /// # Ok(()) }
+    /// ```
+    pub fn with_max_idle_before_ping(mut self, max_idle_before_ping: Duration) -> Self {
+        self.max_idle_before_ping = max_idle_before_ping;
+        self
+    }
+
+    /// Returns the `max_idle_before_ping` value (see [`PoolOpts::with_max_idle_before_ping`]).
+    pub fn max_idle_before_ping(&self) -> Duration {
+        self.max_idle_before_ping
+    }
+
+    /// Sets the timeout of the health check ping (defaults to `None`, i.e. the read timeout
+    /// of the connection applies). Only applies if [`PoolOpts::check_health`] is `true`.
+    ///
+    /// If `Some(_)`, then [`crate::Conn::ping_with_timeout`] is used, so that a half-dead
+    /// connection won't stall the retrieval. Zero timeout is treated as `None`. It is rejected
+    /// if given in the connection URL.
+    ///
+    /// # Connection URL
+    ///
+    /// Use `ping_timeout_ms` URL parameter to set this value. E.g.
+    ///
+    /// ```
+    /// # use mysql::*;
+    /// # use std::time::Duration;
+    /// # fn main() -> Result<()> {
+    /// let opts = Opts::from_url("mysql://localhost/db?ping_timeout_ms=500")?;
+    /// assert_eq!(opts.get_pool_opts().ping_timeout(), Some(Duration::from_millis(500)));
+    /// # Ok(()) }
+    /// ```
+    pub fn with_ping_timeout(mut self, ping_timeout: Option<Duration>) -> Self {
+        self.ping_timeout = ping_timeout.filter(|timeout| !timeout.is_zero());
+        self
+    }
+
+    /// Returns the `ping_timeout` value (see [`PoolOpts::with_ping_timeout`]).
+    pub fn ping_timeout(&self) -> Option<Duration> {
+        self.ping_timeout
+    }
+
+    /// Sets the interval of the pool heartbeat (defaults to `None`, i.e. no heartbeat).
+    ///
+    /// If `Some(_)`, then a background thread will [`crate::Conn::touch`] every connection
+    /// that stays in the pool for at least the given interval, so that the server won't close
+    /// it due to `wait_timeout`. Dead connections are removed from the pool.
+    ///
+    /// Zero interval is treated as `None`. It is rejected if given in the connection URL.
+    ///
+    /// # Connection URL
+    ///
+    /// Use `idle_heartbeat_ms` URL parameter to set this value. E.g.
+    ///
+    /// ```
+    /// # use mysql::*;
+    /// # use std::time::Duration;
+    /// # fn main() -> Result<()> {
+    /// let opts = Opts::from_url("mysql://localhost/db?idle_heartbeat_ms=60000")?;
+    /// assert_eq!(opts.get_pool_opts().idle_heartbeat(), Some(Duration::from_secs(60)));
+    /// # Ok(()) }
+    /// ```
+    pub fn with_idle_heartbeat(mut self, idle_heartbeat: Option<Duration>) -> Self {
+        self.idle_heartbeat = idle_heartbeat.filter(|interval| !interval.is_zero());
+        self
+    }
+
+    /// Returns the `idle_heartbeat` value (see [`PoolOpts::with_idle_heartbeat`]).
+    pub fn idle_heartbeat(&self) -> Option<Duration> {
+        self.idle_heartbeat
+    }
 }
 
 impl Default for PoolOpts {
@@ -122,6 +216,9 @@ impl Default for PoolOpts {
             constraints: PoolConstraints::DEFAULT,
             reset_connection: true,
             check_health: true,
+            max_idle_before_ping: Duration::ZERO,
+            ping_timeout: None,
+            idle_heartbeat: None,
         }
     }
 }
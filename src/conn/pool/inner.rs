@@ -4,6 +4,7 @@ use std::{
         atomic::{AtomicUsize, Ordering},
         Condvar, Mutex,
     },
+    time::Duration,
 };
 
 use crate::{Conn, Opts, PoolOpts};
@@ -58,6 +59,20 @@ impl Protected {
     pub fn push_back(&mut self, conn: Conn) {
         self.connections.push_back(conn)
     }
+
+    /// Takes out connections that stay in the pool for at least `idle`.
+    pub fn take_idle(&mut self, idle: Duration) -> Vec<Conn> {
+        let mut idle_conns = Vec::new();
+        let mut i = 0;
+        while i < self.connections.len() {
+            if self.connections[i].idle_time() >= idle {
+                idle_conns.extend(self.connections.remove(i));
+            } else {
+                i += 1;
+            }
+        }
+        idle_conns
+    }
 }
 
 pub struct Inner {
@@ -99,6 +114,27 @@ impl Inner {
         &self.protected
     }
 
+    /// Touches connections that stay in the pool for at least `idle`
+    /// and removes the dead ones.
+    pub fn heartbeat(&self, idle: Duration) {
+        let (protected, condvar) = self.protected();
+        let idle_conns = match protected.lock() {
+            Ok(mut protected) => protected.take_idle(idle),
+            Err(_) => return,
+        };
+
+        for mut conn in idle_conns {
+            if conn.touch().is_ok() {
+                if let Ok(mut protected) = protected.lock() {
+                    protected.push_back(conn);
+                }
+            } else {
+                self.decrease();
+            }
+            condvar.notify_one();
+        }
+    }
+
     pub fn new(opts: Opts) -> crate::Result<Self> {
         Ok(Self {
             count: AtomicUsize::new(opts.get_pool_opts().constraints().min()),
@@ -9,7 +9,8 @@
 use std::{
     fmt,
     ops::Deref,
-    sync::Arc,
+    sync::{Arc, Weak},
+    thread,
     time::{Duration, Instant},
 };
 
@@ -115,7 +116,15 @@ impl Pool {
             }
         };
 
-        if call_ping && self.inner.opts().check_health() && conn.ping().is_err() {
+        let pool_opts = self.inner.opts();
+        if call_ping
+            && pool_opts.check_health()
+            && conn.idle_time() >= pool_opts.max_idle_before_ping()
+            && match pool_opts.ping_timeout() {
+                Some(timeout) => conn.ping_with_timeout(timeout).is_err(),
+                None => conn.ping().is_err(),
+            }
+        {
             // existing connection seem to be dead, retrying..
             self.inner.decrease();
             return self._get_conn(stmt, timeout, call_ping);
@@ -133,9 +142,25 @@ impl Pool {
         Opts: TryFrom<T, Error = E>,
         crate::Error: From<E>,
     {
-        Ok(Pool {
-            inner: Arc::new(inner::Inner::new(Opts::try_from(opts)?)?),
-        })
+        let inner = Arc::new(inner::Inner::new(Opts::try_from(opts)?)?);
+
+        if let Some(interval) = inner.opts().idle_heartbeat() {
+            let inner = Arc::downgrade(&inner);
+            thread::spawn(move || Self::heartbeat(inner, interval));
+        }
+
+        Ok(Pool { inner })
+    }
+
+    /// Heartbeat loop. Stops as soon as the pool is dropped.
+    fn heartbeat(inner: Weak<inner::Inner>, interval: Duration) {
+        loop {
+            thread::sleep(interval);
+            match inner.upgrade() {
+                Some(inner) => inner.heartbeat(interval),
+                None => break,
+            }
+        }
     }
 
     /// Gives you a [`PooledConn`](struct.PooledConn.html).
@@ -347,8 +372,8 @@ mod test {
         use std::{thread, time::Duration};
 
         use crate::{
-            from_value, prelude::*, test_misc::get_opts, DriverError, Error, OptsBuilder, Pool,
-            PoolConstraints, PoolOpts, TxOpts, Value,
+            from_value, prelude::*, test_misc::get_opts, Conn, DriverError, Error, OptsBuilder,
+            Pool, PoolConstraints, PoolOpts, TxOpts, Value,
         };
 
         #[test]
@@ -400,6 +425,53 @@ mod test {
             }
         }
 
+        #[test]
+        fn should_not_ping_recently_used_connection() {
+            fn killed_conn_id(pool_opts: PoolOpts) -> (u32, u32) {
+                let pool_opts = pool_opts
+                    .with_constraints(PoolConstraints::new_const::<1, 1>())
+                    .with_reset_connection(false);
+                let pool = Pool::new(get_opts().pool_opts(pool_opts)).unwrap();
+                let id = pool.get_conn().unwrap().connection_id();
+
+                let mut conn = Conn::new(get_opts()).unwrap();
+                conn.query_drop(format!("KILL {}", id)).unwrap();
+                thread::sleep(Duration::from_millis(250));
+
+                (id, pool.get_conn().unwrap().connection_id())
+            }
+
+            // recently used connection is given out as is
+            let (id, new_id) = killed_conn_id(
+                PoolOpts::default().with_max_idle_before_ping(Duration::from_secs(3600)),
+            );
+            assert_eq!(id, new_id);
+
+            // dead connection is replaced if pinged
+            let (id, new_id) = killed_conn_id(PoolOpts::default());
+            assert_ne!(id, new_id);
+        }
+
+        #[test]
+        fn should_remove_dead_connections_on_heartbeat() {
+            let pool_opts = PoolOpts::default()
+                .with_constraints(PoolConstraints::new_const::<1, 1>())
+                .with_reset_connection(false)
+                .with_max_idle_before_ping(Duration::from_secs(3600))
+                .with_idle_heartbeat(Some(Duration::from_millis(100)));
+            let pool = Pool::new(get_opts().pool_opts(pool_opts)).unwrap();
+            let id = pool.get_conn().unwrap().connection_id();
+
+            let mut conn = Conn::new(get_opts()).unwrap();
+            conn.query_drop(format!("KILL {}", id)).unwrap();
+            thread::sleep(Duration::from_millis(500));
+
+            // the dead connection was removed by the heartbeat, so no ping is needed
+            let mut conn = pool.get_conn().unwrap();
+            assert_ne!(id, conn.connection_id());
+            assert_eq!(conn.query_first::<u8, _>("SELECT 1").unwrap(), Some(1));
+        }
+
         #[test]
         fn should_fix_connectivity_errors_on_prepare() {
             let pool = Pool::new(get_opts().pool_opts(
@@ -11,8 +11,11 @@ use mysql_common::row::convert::FromRowError;
 use std::{convert::TryInto, result::Result as StdResult};
 
 use crate::{
-    conn::{queryable::AsStatement, ConnMut},
-    from_row, from_row_opt,
+    conn::{
+        queryable::{from_row, AsStatement},
+        ConnMut,
+    },
+    from_row_opt,
     prelude::FromRow,
     Binary, Error, Params, QueryResult, Result, Text,
 };
@@ -334,7 +337,7 @@ where
     {
         let mut conn = conn.try_into()?;
         let statement = self.query.as_statement(&mut *conn)?;
-        let meta = conn._execute(&statement, self.params.into())?;
+        let meta = conn._execute(&statement, self.params.into(), None)?;
         Ok(QueryResult::new(conn, meta))
     }
 }
@@ -382,7 +385,7 @@ where
 
         for params in self.params {
             let params = params.into();
-            let meta = conn._execute(&statement, params)?;
+            let meta = conn._execute(&statement, params, None)?;
             let mut query_result = QueryResult::<Binary>::new((&mut *conn).into(), meta);
             while let Some(result_set) = query_result.iter() {
                 for row in result_set {
@@ -8,11 +8,25 @@
 
 pub use mysql_common::proto::{Binary, Text};
 
-use mysql_common::{io::ParseBuf, packets::OkPacket, row::RowDeserializer, value::ServerSide};
-
-use std::{borrow::Cow, marker::PhantomData, sync::Arc};
-
-use crate::{conn::ConnMut, Column, Conn, Error, Result, Row};
+use mysql_common::{
+    io::ParseBuf,
+    packets::OkPacket,
+    row::{new_row_raw, RowDeserializer},
+    value::ServerSide,
+};
+
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    io,
+    marker::PhantomData,
+    sync::{Arc, OnceLock},
+};
+
+use crate::{
+    conn::ConnMut, consts::StatusFlags, value::to_typed, Column, Conn, DriverError, Error, Result,
+    Row, Value,
+};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Or<A, B> {
@@ -22,10 +36,15 @@ pub enum Or<A, B> {
 
 /// Result set kind.
 pub trait Protocol: 'static + Send + Sync {
+    /// `true` for the binary protocol.
+    const BINARY: bool;
+
     fn next(conn: &mut Conn, columns: Arc<[Column]>) -> Result<Option<Row>>;
 }
 
 impl Protocol for Text {
+    const BINARY: bool = false;
+
     fn next(conn: &mut Conn, columns: Arc<[Column]>) -> Result<Option<Row>> {
         match conn.next_row_packet()? {
             Some(pld) => {
@@ -38,6 +57,8 @@ impl Protocol for Text {
 }
 
 impl Protocol for Binary {
+    const BINARY: bool = true;
+
     fn next(conn: &mut Conn, columns: Arc<[Column]>) -> Result<Option<Row>> {
         match conn.next_row_packet()? {
             Some(pld) => {
@@ -52,8 +73,9 @@ impl Protocol for Binary {
 /// State of a result set iterator.
 #[derive(Debug)]
 enum SetIteratorState {
-    /// Iterator is in a non-empty set.
-    InSet(Arc<[Column]>),
+    /// Iterator is in a non-empty set (columns and the column names index,
+    /// that is built on the first lookup).
+    InSet(Arc<[Column]>, OnceLock<HashMap<Vec<u8>, usize>>),
     /// Iterator is in an empty set.
     InEmptySet(OkPacket<'static>),
     /// Iterator is in an errored result set.
@@ -74,17 +96,36 @@ impl SetIteratorState {
     }
 
     fn columns(&self) -> Option<&Arc<[Column]>> {
-        if let Self::InSet(ref cols) = self {
+        if let Self::InSet(ref cols, _) = self {
             Some(cols)
         } else {
             None
         }
     }
+
+    fn column_names_index(&self) -> Option<&OnceLock<HashMap<Vec<u8>, usize>>> {
+        if let Self::InSet(_, ref index) = self {
+            Some(index)
+        } else {
+            None
+        }
+    }
+}
+
+/// Maps column names to column indexes.
+///
+/// The first column wins if a name is duplicated (same as a linear scan).
+fn column_names_index(columns: &[Column]) -> HashMap<Vec<u8>, usize> {
+    let mut index = HashMap::with_capacity(columns.len());
+    for (i, column) in columns.iter().enumerate() {
+        index.entry(column.name_ref().to_vec()).or_insert(i);
+    }
+    index
 }
 
 impl From<Vec<Column>> for SetIteratorState {
     fn from(columns: Vec<Column>) -> Self {
-        Self::InSet(columns.into())
+        Self::InSet(columns.into(), OnceLock::new())
     }
 }
 
@@ -114,11 +155,23 @@ impl From<Or<Vec<Column>, OkPacket<'static>>> for SetIteratorState {
 /// It is an iterator:
 /// *   over result sets (via `Self::current_set`)
 /// *   over rows of a current result set (via `Iterator` impl)
+///
+/// # Text vs binary protocol
+///
+/// Results of a text query (`Queryable::query*`) come over the text protocol, so every
+/// non-`NULL` value is `Value::Bytes` regardless of the column type. Results of a statement
+/// execution (`Queryable::exec*`) come over the binary protocol, so values are typed
+/// (`Value::Int`, `Value::Date`, etc.).
+///
+/// Use prepared statements if you need typed values, or see [`QueryResult::next_typed`]
+/// and [`QueryResult::is_binary`].
 #[derive(Debug)]
 pub struct QueryResult<'c, 't, 'tc, T: crate::prelude::Protocol> {
     conn: ConnMut<'c, 't, 'tc>,
     state: SetIteratorState,
     set_index: usize,
+    /// Number of rows yielded from the current result set.
+    rows: u64,
     protocol: PhantomData<T>,
 }
 
@@ -131,6 +184,7 @@ impl<'c, 't, 'tc, T: crate::prelude::Protocol> QueryResult<'c, 't, 'tc, T> {
             conn,
             state,
             set_index: 0,
+            rows: 0,
             protocol: PhantomData,
         }
     }
@@ -142,6 +196,20 @@ impl<'c, 't, 'tc, T: crate::prelude::Protocol> QueryResult<'c, 't, 'tc, T> {
         Self::from_state(conn, meta.into())
     }
 
+    /// Returns `true` if this result came over the binary protocol (i.e. it is a result
+    /// of a statement execution), and `false` for the text protocol.
+    pub fn is_binary(&self) -> bool {
+        T::BINARY
+    }
+
+    /// Returns `true` if the current result set reached [`crate::Opts::get_max_result_rows`].
+    fn rows_limit_reached(&self) -> bool {
+        match self.conn.0.opts.get_max_result_rows() {
+            Some(max) => self.rows >= max,
+            None => false,
+        }
+    }
+
     /// Updates state with the next result set, if any.
     ///
     /// Returns `false` if there is no next result set.
@@ -159,6 +227,7 @@ impl<'c, 't, 'tc, T: crate::prelude::Protocol> QueryResult<'c, 't, 'tc, T> {
                 Err(err) => self.state = err.into(),
             }
             self.set_index += 1;
+            self.rows = 0;
         } else {
             self.state = SetIteratorState::Done;
         }
@@ -260,12 +329,30 @@ impl<'c, 't, 'tc, T: crate::prelude::Protocol> QueryResult<'c, 't, 'tc, T> {
             .unwrap_or_default()
     }
 
+    /// Returns OK packet of the current result set.
+    ///
+    /// For the last result set it'll be the terminating packet, once the set is consumed.
+    fn ok_packet(&self) -> Option<&OkPacket<'_>> {
+        match self.state {
+            SetIteratorState::Done => self.conn.0.ok_packet.as_ref(),
+            ref state => state.ok_packet(),
+        }
+    }
+
     /// Returns the warnings count for the current result set.
+    ///
+    /// Warnings of the last result set are available after it is consumed.
     pub fn warnings(&self) -> u16 {
-        self.state
-            .ok_packet()
-            .map(|ok| ok.warnings())
-            .unwrap_or_default()
+        self.ok_packet().map(|ok| ok.warnings()).unwrap_or_default()
+    }
+
+    /// Returns status flags for the current result set.
+    ///
+    /// Status flags of the last result set are available after it is consumed.
+    pub fn status(&self) -> StatusFlags {
+        self.ok_packet()
+            .map(|ok| ok.status_flags())
+            .unwrap_or_else(StatusFlags::empty)
     }
 
     /// [Info] for the current result set.
@@ -292,12 +379,135 @@ impl<'c, 't, 'tc, T: crate::prelude::Protocol> QueryResult<'c, 't, 'tc, T> {
             .unwrap_or_else(|| "".into())
     }
 
+    /// Returns the number of columns of the current result set without reading its rows
+    /// (zero if the current result set has no columns, e.g. it is an OK packet).
+    ///
+    /// Use [`crate::Statement::num_columns`] to get the number before the execution.
+    pub fn num_columns(&self) -> usize {
+        self.state.columns().map_or(0, |columns| columns.len())
+    }
+
     /// Returns columns of the current result rest.
     pub fn columns(&self) -> SetColumns {
         SetColumns {
             inner: self.state.columns().map(Into::into),
+            index: self.state.column_names_index(),
+        }
+    }
+
+    /// Same as `Iterator::next`, but text protocol values get converted according
+    /// to the column types, so that text and binary protocols produce the same rows
+    /// (e.g. `INT` column value will be `Value::Int` rather than `Value::Bytes`).
+    pub fn next_typed(&mut self) -> Option<Result<Row>> {
+        self.next().map(|row| {
+            row.map(|row| {
+                let columns = row.columns();
+                let values = row
+                    .unwrap_raw()
+                    .into_iter()
+                    .zip(columns.iter())
+                    .map(|(value, column)| value.map(|value| to_typed(value, column)))
+                    .collect();
+                new_row_raw(values, columns)
+            })
+        })
+    }
+
+    /// Collects rows of the current result set as raw values.
+    ///
+    /// Returns the first row error, if any. Remaining result sets are dropped.
+    ///
+    /// Since `QueryResult` borrows the connection, this is the way to release the borrow early
+    /// if rows are processed by code that also needs the connection:
+    ///
+    /// ```rust
+    /// # mysql::doctest_wrapper!(__result, {
+    /// # use mysql::*;
+    /// # use mysql::prelude::*;
+    /// fn process(conn: &mut Conn, rows: Vec<Vec<Value>>) -> Result<()> {
+    ///     for row in rows {
+    ///         conn.exec_drop("DO ?", row)?;
+    ///     }
+    ///     Ok(())
+    /// }
+    ///
+    /// # let mut conn = Conn::new(get_opts())?;
+    /// let rows = conn.query_iter("SELECT 1 UNION ALL SELECT 2")?.collect_values()?;
+    /// process(&mut conn, rows)?;
+    /// # });
+    /// ```
+    #[doc(alias = "into_rows")]
+    pub fn collect_values(mut self) -> Result<Vec<Vec<Value>>> {
+        self.by_ref().map(|row| row.map(Row::unwrap)).collect()
+    }
+
+    /// Applies `f` to raw values of the first row of the current result set.
+    ///
+    /// Returns `Ok(None)` if the current result set is empty. Remaining rows and result sets
+    /// are dropped.
+    pub fn map_first<U, F>(mut self, f: F) -> Result<Option<U>>
+    where
+        F: FnOnce(Vec<Value>) -> U,
+    {
+        self.next()
+            .map(|row| row.map(|row| f(row.unwrap())))
+            .transpose()
+    }
+
+    /// Writes the current result set to `out` as CSV without collecting it.
+    ///
+    /// The first line is a header with column names, then goes one line per row.
+    /// `NULL` is written as an empty field, other values are written in their text form
+    /// (as the text protocol would send them). Fields containing `,`, `"` or line breaks
+    /// are enclosed in `"`. Remaining result sets are dropped.
+    pub fn to_csv<W: io::Write>(mut self, out: &mut W) -> Result<()> {
+        for (i, column) in self.columns().as_ref().iter().enumerate() {
+            if i > 0 {
+                out.write_all(b",")?;
+            }
+            write_csv_field(out, column.name_ref())?;
+        }
+        out.write_all(b"\n")?;
+
+        for row in self.by_ref() {
+            for (i, value) in row?.unwrap().iter().enumerate() {
+                if i > 0 {
+                    out.write_all(b",")?;
+                }
+                match value {
+                    Value::NULL => (),
+                    Value::Bytes(bytes) => write_csv_field(out, bytes)?,
+                    // temporal values are quoted by `Value::as_sql`
+                    Value::Date(..) | Value::Time(..) => {
+                        write_csv_field(out, value.as_sql(false).trim_matches('\'').as_bytes())?
+                    }
+                    value => write_csv_field(out, value.as_sql(false).as_bytes())?,
+                }
+            }
+            out.write_all(b"\n")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Writes a CSV field, enclosing it in `"` if necessary.
+fn write_csv_field<W: io::Write>(out: &mut W, field: &[u8]) -> io::Result<()> {
+    if !field
+        .iter()
+        .any(|x| matches!(x, b',' | b'"' | b'\n' | b'\r'))
+    {
+        return out.write_all(field);
+    }
+
+    out.write_all(b"\"")?;
+    for chunk in field.split_inclusive(|x| *x == b'"') {
+        out.write_all(chunk)?;
+        if chunk.ends_with(b"\"") {
+            out.write_all(b"\"")?;
         }
     }
+    out.write_all(b"\"")
 }
 
 impl<'c, 't, 'tc, T: crate::prelude::Protocol> Drop for QueryResult<'c, 't, 'tc, T> {
@@ -341,9 +551,17 @@ impl<T: crate::prelude::Protocol> Iterator for QueryResult<'_, '_, '_, T> {
         let state = std::mem::replace(&mut self.state, OnBoundary);
 
         match state {
-            InSet(cols) => match T::next(&mut self.conn, cols.clone()) {
+            InSet(cols, index) => match T::next(&mut self.conn, cols.clone()) {
+                Ok(Some(_)) if self.rows_limit_reached() => {
+                    // The rest of the result set is still on the wire.
+                    self.conn.mark_broken();
+                    self.state = Done;
+                    let max = self.rows;
+                    Some(Err(Error::DriverError(DriverError::TooManyRows(max))))
+                }
                 Ok(Some(row)) => {
-                    self.state = InSet(cols);
+                    self.rows += 1;
+                    self.state = InSet(cols, index);
                     Some(Ok(row))
                 }
                 Ok(None) => {
@@ -378,18 +596,29 @@ impl<T: crate::prelude::Protocol> Drop for ResultSet<'_, '_, '_, '_, T> {
     }
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone)]
 pub struct SetColumns<'a> {
     inner: Option<&'a Arc<[Column]>>,
+    index: Option<&'a OnceLock<HashMap<Vec<u8>, usize>>>,
+}
+
+impl PartialEq for SetColumns<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        // the index is derived from columns and may be not yet built
+        self.inner == other.inner
+    }
 }
 
 impl<'a> SetColumns<'a> {
     /// Returns an index of a column by its name.
+    ///
+    /// Returns the first matching column if the name is duplicated.
     pub fn column_index<U: AsRef<str>>(&self, name: U) -> Option<usize> {
         let name = name.as_ref().as_bytes();
-        self.inner
-            .as_ref()
-            .and_then(|cols| cols.iter().position(|col| col.name_ref() == name))
+        let index = self
+            .index?
+            .get_or_init(|| column_names_index(self.as_ref()));
+        index.get(name).copied()
     }
 }
 
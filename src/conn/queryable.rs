@@ -8,15 +8,104 @@
 
 use mysql_common::row::convert::FromRowError;
 
-use std::{borrow::Cow, result::Result as StdResult};
+use std::{borrow::Cow, collections::HashMap, hash::Hash, result::Result as StdResult};
 
 use crate::{
     conn::query_result::{Binary, Text},
-    from_row, from_row_opt,
+    error::RowColumns,
+    from_row_opt,
     prelude::FromRow,
-    Params, QueryResult, Result, Statement,
+    Column, DriverError, Error, Params, QueryResult, Result, Row, Statement, Value,
 };
 
+/// Same as [`crate::from_row`] but panics with a message that names
+/// the MySql types of the row columns along with the requested Rust type.
+pub(crate) fn from_row<T: FromRow>(row: Row) -> T {
+    match T::from_row_opt(row) {
+        Ok(x) => x,
+        Err(FromRowError(row)) => panic!(
+            "Couldn't convert row ({}) to `{}`",
+            RowColumns(&row),
+            std::any::type_name::<T>()
+        ),
+    }
+}
+
+/// Adds the index of the failed statement to the server error (see [`Queryable::run_script`]).
+fn script_error(index: usize, err: Error) -> Error {
+    match err {
+        Error::MySqlError(mut err) => {
+            err.message = format!("statement #{}: {}", index, err.message);
+            Error::MySqlError(err)
+        }
+        err => err,
+    }
+}
+
+/// Replaces the only `?` placeholder of the `query` with `len` comma-separated placeholders
+/// (or with `NULL` if `len` is zero, see [`Queryable::exec_in`]).
+///
+/// Placeholders inside quoted strings, quoted identifiers and comments are ignored.
+fn expand_in_list(query: &str, len: usize) -> Result<String> {
+    let bytes = query.as_bytes();
+    let mut placeholders = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            quote @ (b'\'' | b'"' | b'`') => {
+                i += 1;
+                while i < bytes.len() && bytes[i] != quote {
+                    if bytes[i] == b'\\' && quote != b'`' {
+                        i += 1;
+                    }
+                    i += 1;
+                }
+            }
+            b'#' => {
+                while i < bytes.len() && bytes[i] != b'\n' {
+                    i += 1;
+                }
+            }
+            b'-' if bytes[i..].starts_with(b"-- ") => {
+                while i < bytes.len() && bytes[i] != b'\n' {
+                    i += 1;
+                }
+            }
+            b'/' if bytes[i..].starts_with(b"/*") => {
+                i += 2;
+                while i < bytes.len() && !bytes[i..].starts_with(b"*/") {
+                    i += 1;
+                }
+                i += 1;
+            }
+            b'?' => placeholders.push(i),
+            _ => (),
+        }
+        i += 1;
+    }
+
+    match placeholders[..] {
+        [pos] => {
+            let list = if len == 0 {
+                "NULL".to_owned()
+            } else {
+                vec!["?"; len].join(", ")
+            };
+            Ok(format!("{}{}{}", &query[..pos], list, &query[pos + 1..]))
+        }
+        _ => Err(Error::DriverError(DriverError::MismatchedStmtParams(
+            placeholders.len() as u16,
+            1,
+        ))),
+    }
+}
+
+/// Same as [`crate::from_row_opt`] but the error names the requested Rust type.
+pub(crate) fn try_from_row<T: FromRow>(row: Row) -> Result<T> {
+    T::from_row_opt(row)
+        .map_err(|FromRowError(row)| Error::FromRowError(row, Some(std::any::type_name::<T>())))
+}
+
 /// Something, that eventually is a `Statement` in the context of a `T: Queryable`.
 pub trait AsStatement {
     /// Make a statement out of `Self`.
@@ -28,6 +117,21 @@ pub trait Queryable {
     /// Performs text query.
     fn query_iter<Q: AsRef<str>>(&mut self, query: Q) -> Result<QueryResult<'_, '_, '_, Text>>;
 
+    /// Performs text query and returns column metadata of the first result set
+    /// along with the result itself.
+    ///
+    /// Columns are available even if the result set has no rows (e.g. `... WHERE 1 = 0`),
+    /// and are empty if the query doesn't produce a result set.
+    /// See also [`QueryResult::columns`].
+    fn query_meta<Q: AsRef<str>>(
+        &mut self,
+        query: Q,
+    ) -> Result<(Vec<Column>, QueryResult<'_, '_, '_, Text>)> {
+        let result = self.query_iter(query)?;
+        let columns = result.columns().as_ref().to_vec();
+        Ok((columns, result))
+    }
+
     /// Performs text query and collects the first result set.
     fn query<T, Q>(&mut self, query: Q) -> Result<Vec<T>>
     where
@@ -37,6 +141,21 @@ pub trait Queryable {
         self.query_map(query, from_row)
     }
 
+    /// Performs text query and converts every row of the first result set into `T`.
+    ///
+    /// Unlike [`Queryable::query`], this won't panic if a row couldn't be converted.
+    /// Returns the first conversion error as [`Error::FromRowError`] (that names
+    /// the row columns and `T`) instead, the rest of the result is dropped.
+    fn select<T, Q>(&mut self, query: Q) -> Result<Vec<T>>
+    where
+        Q: AsRef<str>,
+        T: FromRow,
+    {
+        self.query_iter(query)?
+            .map(|row| try_from_row(row?))
+            .collect()
+    }
+
     /// Same as [`Queryable::query`] but useful when you not sure what your schema is.
     fn query_opt<T, Q>(&mut self, query: Q) -> Result<Vec<StdResult<T, FromRowError>>>
     where
@@ -108,6 +227,24 @@ pub trait Queryable {
             .try_fold(init, |acc, row: Result<T>| row.map(|row| f(acc, row)))
     }
 
+    /// Performs text query and collects the first result set into a map.
+    ///
+    /// Every row is mapped into a key-value pair using `f`. If several rows produce
+    /// the same key, then the later row overwrites the earlier one.
+    fn query_to_map<T, F, Q, K, V>(&mut self, query: Q, mut f: F) -> Result<HashMap<K, V>>
+    where
+        Q: AsRef<str>,
+        T: FromRow,
+        F: FnMut(T) -> (K, V),
+        K: Eq + Hash,
+    {
+        self.query_fold(query, HashMap::new(), |mut acc, row| {
+            let (key, value) = f(row);
+            acc.insert(key, value);
+            acc
+        })
+    }
+
     /// Same as [`Queryable::query_fold`] but useful when you not sure what your schema is.
     fn query_fold_opt<T, F, Q, U>(&mut self, query: Q, init: U, mut f: F) -> Result<U>
     where
@@ -122,6 +259,14 @@ pub trait Queryable {
             })
     }
 
+    /// Performs text query and collects raw values of the first result set.
+    fn query_collect<Q>(&mut self, query: Q) -> Result<Vec<Vec<Value>>>
+    where
+        Q: AsRef<str>,
+    {
+        self.query_iter(query)?.collect_values()
+    }
+
     /// Performs text query and drops the query result.
     fn query_drop<Q>(&mut self, query: Q) -> Result<()>
     where
@@ -130,6 +275,29 @@ pub trait Queryable {
         self.query_iter(query).map(drop)
     }
 
+    /// Performs a multi-statement text query (e.g. a migration script) and drops the results.
+    ///
+    /// Unlike [`Queryable::query_drop`], this will check every result set, so the error
+    /// of any statement is returned. Server error message gets prefixed with the zero-based
+    /// index of the failed statement, e.g. `statement #2: ...` (statements that produce
+    /// several result sets, such as `CALL`, will shift the index).
+    fn run_script<Q>(&mut self, script: Q) -> Result<()>
+    where
+        Q: AsRef<str>,
+    {
+        let mut result = self
+            .query_iter(script)
+            .map_err(|err| script_error(0, err))?;
+        let mut index = 0;
+        while let Some(set) = result.iter() {
+            for row in set {
+                row.map_err(|err| script_error(index, err))?;
+            }
+            index += 1;
+        }
+        Ok(())
+    }
+
     /// Prepares the given `query` as a prepared statement.
     fn prep<Q: AsRef<str>>(&mut self, query: Q) -> Result<crate::Statement>;
 
@@ -159,6 +327,24 @@ pub trait Queryable {
     }
 
     /// Executes the given `stmt` and collects the first result set.
+    ///
+    /// `stmt` might be a query text, in this case it is prepared first (prepared statements
+    /// are cached, see [`crate::Opts::get_stmt_cache_size`]). `params` are either positional,
+    /// named or empty (i.e. `()`).
+    ///
+    /// ```rust
+    /// # mysql::doctest_wrapper!(__result, {
+    /// # use mysql::*;
+    /// # use mysql::prelude::*;
+    /// # let mut conn = Conn::new(get_opts())?;
+    /// let rows: Vec<i64> = conn.exec("SELECT ?", (42_i64,))?;
+    /// assert_eq!(rows, vec![42]);
+    /// let rows: Vec<i64> = conn.exec("SELECT :foo", params! { "foo" => 42_i64 })?;
+    /// assert_eq!(rows, vec![42]);
+    /// let rows: Vec<i64> = conn.exec("SELECT 42", ())?;
+    /// assert_eq!(rows, vec![42]);
+    /// # });
+    /// ```
     fn exec<T, S, P>(&mut self, stmt: S, params: P) -> Result<Vec<T>>
     where
         S: AsStatement,
@@ -168,6 +354,52 @@ pub trait Queryable {
         self.exec_map(stmt, params, from_row)
     }
 
+    /// Executes the `query` binding every element of the `list` to the query's only `?`
+    /// placeholder, that is expanded into `?, ?, ...` (one per element).
+    ///
+    /// Handy for `IN (...)` clauses. The expanded query is prepared and cached as usual,
+    /// so every distinct list length gives a distinct statement.
+    ///
+    /// Note that an empty list expands to `NULL`, so `x IN (?)` becomes `x IN (NULL)` which
+    /// matches no rows, whereas `x NOT IN (?)` becomes `x NOT IN (NULL)` which also matches
+    /// no rows (not every row).
+    ///
+    /// Returns [`DriverError::MismatchedStmtParams`] if the `query` hasn't exactly one
+    /// placeholder.
+    ///
+    /// ```rust
+    /// # mysql::doctest_wrapper!(__result, {
+    /// # use mysql::*;
+    /// # use mysql::prelude::*;
+    /// # let mut conn = Conn::new(get_opts())?;
+    /// let rows: Vec<u8> = conn.exec_in("SELECT 1 FROM DUAL WHERE 2 IN (?)", vec![1, 2, 3])?;
+    /// assert_eq!(rows, vec![1]);
+    /// # });
+    /// ```
+    fn exec_in<T, V>(&mut self, query: &str, list: Vec<V>) -> Result<Vec<T>>
+    where
+        T: FromRow,
+        V: Into<Value>,
+    {
+        let query = expand_in_list(query, list.len())?;
+        let params = list.into_iter().map(Into::into).collect::<Vec<Value>>();
+        self.exec(query, params)
+    }
+
+    /// Executes the given `stmt` and converts every row of the first result set into `T`.
+    ///
+    /// Same as [`Queryable::select`] but for prepared statements.
+    fn exec_select<T, S, P>(&mut self, stmt: S, params: P) -> Result<Vec<T>>
+    where
+        S: AsStatement,
+        P: Into<Params>,
+        T: FromRow,
+    {
+        self.exec_iter(stmt, params)?
+            .map(|row| try_from_row(row?))
+            .collect()
+    }
+
     /// Same as [`Queryable::exec`] but useful when you not sure what your schema is.
     fn exec_opt<T, S, P>(&mut self, stmt: S, params: P) -> Result<Vec<StdResult<T, FromRowError>>>
     where
@@ -187,7 +419,7 @@ pub trait Queryable {
     {
         self.exec_iter(stmt, params)?
             .next()
-            .map(|row| row.map(crate::from_row))
+            .map(|row| row.map(from_row))
             .transpose()
     }
 
@@ -269,3 +501,41 @@ pub trait Queryable {
         self.exec_iter(stmt, params).map(drop)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::expand_in_list;
+    use crate::{DriverError::MismatchedStmtParams, Error::DriverError};
+
+    #[test]
+    fn should_expand_in_list() {
+        assert_eq!(
+            expand_in_list("SELECT * FROM t WHERE id IN (?)", 3).unwrap(),
+            "SELECT * FROM t WHERE id IN (?, ?, ?)"
+        );
+        assert_eq!(
+            expand_in_list("SELECT * FROM t WHERE id IN (?)", 0).unwrap(),
+            "SELECT * FROM t WHERE id IN (NULL)"
+        );
+        assert_eq!(
+            expand_in_list(
+                "SELECT '?', `?`, \"\\\"?\" /* ? */ FROM t WHERE x IN (?) -- ?",
+                2
+            )
+            .unwrap(),
+            "SELECT '?', `?`, \"\\\"?\" /* ? */ FROM t WHERE x IN (?, ?) -- ?"
+        );
+    }
+
+    #[test]
+    fn should_require_single_placeholder() {
+        match expand_in_list("SELECT 1", 1) {
+            Err(DriverError(MismatchedStmtParams(0, 1))) => (),
+            other => panic!("MismatchedStmtParams expected, got {:?}", other),
+        }
+        match expand_in_list("SELECT ? FROM t WHERE id IN (?)", 1) {
+            Err(DriverError(MismatchedStmtParams(2, 1))) => (),
+            other => panic!("MismatchedStmtParams expected, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,60 @@
+// Copyright (c) 2020 rust-mysql-simple contributors
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+/// Connection counters (see [`crate::Conn::stats`]).
+///
+/// Counters are kept for the whole lifetime of a `Conn` (including
+/// [`crate::Conn::reconnect`]) and include queries issued by the driver itself
+/// (e.g. [`crate::Opts::get_init`] queries).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct ConnStats {
+    pub(crate) queries: u64,
+    pub(crate) rows: u64,
+    pub(crate) packets_sent: u64,
+    pub(crate) packets_received: u64,
+    pub(crate) bytes_sent: u64,
+    pub(crate) bytes_received: u64,
+    pub(crate) errors: u64,
+}
+
+impl ConnStats {
+    /// Number of queries (`COM_QUERY`) and statement executions (`COM_STMT_EXECUTE`) sent.
+    pub fn queries(&self) -> u64 {
+        self.queries
+    }
+
+    /// Number of result set rows read.
+    pub fn rows(&self) -> u64 {
+        self.rows
+    }
+
+    /// Number of packets sent (a packet larger than `MAX_PAYLOAD_LEN` counts once).
+    pub fn packets_sent(&self) -> u64 {
+        self.packets_sent
+    }
+
+    /// Number of packets received (a packet larger than `MAX_PAYLOAD_LEN` counts once).
+    pub fn packets_received(&self) -> u64 {
+        self.packets_received
+    }
+
+    /// Number of payload bytes sent (packet headers are not counted).
+    pub fn bytes_sent(&self) -> u64 {
+        self.bytes_sent
+    }
+
+    /// Number of payload bytes received (packet headers are not counted).
+    pub fn bytes_received(&self) -> u64 {
+        self.bytes_received
+    }
+
+    /// Number of server errors and failed reads or writes.
+    pub fn errors(&self) -> u64 {
+        self.errors
+    }
+}
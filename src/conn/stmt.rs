@@ -8,7 +8,7 @@
 
 use mysql_common::{io::ParseBuf, packets::StmtPacket, proto::MyDeserialize};
 
-use std::{borrow::Cow, io, sync::Arc};
+use std::{borrow::Cow, fmt, io, sync::Arc};
 
 use crate::{prelude::*, Column, Result};
 
@@ -18,6 +18,7 @@ pub struct InnerStmt {
     params: Option<Vec<Column>>,
     stmt_packet: StmtPacket,
     connection_id: u32,
+    query: Vec<u8>,
 }
 
 impl<'de> MyDeserialize<'de> for InnerStmt {
@@ -32,6 +33,7 @@ impl<'de> MyDeserialize<'de> for InnerStmt {
             params: None,
             stmt_packet,
             connection_id,
+            query: Vec::new(),
         })
     }
 }
@@ -47,6 +49,11 @@ impl InnerStmt {
         self
     }
 
+    pub fn with_query(mut self, query: &[u8]) -> Self {
+        self.query = query.to_vec();
+        self
+    }
+
     pub fn columns(&self) -> &[Column] {
         self.columns.as_ref().map(AsRef::as_ref).unwrap_or(&[])
     }
@@ -63,6 +70,10 @@ impl InnerStmt {
         self.connection_id
     }
 
+    pub fn query(&self) -> &[u8] {
+        &self.query
+    }
+
     pub fn num_params(&self) -> u16 {
         self.stmt_packet.num_params()
     }
@@ -72,7 +83,7 @@ impl InnerStmt {
     }
 }
 
-#[derive(Debug, Clone, Eq, PartialEq)]
+#[derive(Clone, Eq, PartialEq)]
 pub struct Statement {
     pub(crate) inner: Arc<InnerStmt>,
     pub(crate) named_params: Option<Vec<Vec<u8>>>,
@@ -111,6 +122,16 @@ impl Statement {
     }
 }
 
+impl fmt::Debug for Statement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Statement")
+            .field("id", &self.id())
+            .field("num_params", &self.num_params())
+            .field("num_columns", &self.num_columns())
+            .finish()
+    }
+}
+
 impl AsStatement for Statement {
     fn as_statement<Q: Queryable>(&self, _queryable: &mut Q) -> Result<Cow<'_, Statement>> {
         Ok(Cow::Borrowed(self))
@@ -13,6 +13,7 @@ use std::{
     borrow::Borrow,
     collections::HashMap,
     hash::{BuildHasherDefault, Hash},
+    mem,
     sync::Arc,
 };
 
@@ -43,6 +44,8 @@ pub struct StmtCache {
     cap: usize,
     cache: LruCache<u32, Entry>,
     query_map: HashMap<QueryString, u32, BuildHasherDefault<XxHash>>,
+    /// Statements evicted from the cache while still referenced by a `Statement`.
+    evicted: Vec<Arc<InnerStmt>>,
 }
 
 impl StmtCache {
@@ -51,6 +54,7 @@ impl StmtCache {
             cap,
             cache: LruCache::unbounded(),
             query_map: Default::default(),
+            evicted: Vec::new(),
         }
     }
 
@@ -97,15 +101,42 @@ impl StmtCache {
         None
     }
 
+    /// Moves the statement out of the cache, so that it'll be closed once unreferenced
+    /// (see [`StmtCache::take_unreferenced`]).
+    pub fn evict(&mut self, id: u32) {
+        if let Some(entry) = self.cache.pop(&id) {
+            if self.query_map.get(&**entry.query.0.as_ref()) == Some(&id) {
+                self.query_map.remove(&**entry.query.0.as_ref());
+            }
+            self.evicted.push(entry.stmt);
+        }
+    }
+
+    /// Keeps the evicted statement until there are no `Statement`s referencing it.
+    pub fn retain_evicted(&mut self, stmt: Arc<InnerStmt>) {
+        self.evicted.push(stmt);
+    }
+
+    /// Removes and returns evicted statements that are no longer referenced.
+    pub fn take_unreferenced(&mut self) -> Vec<Arc<InnerStmt>> {
+        let (unreferenced, referenced) = mem::take(&mut self.evicted)
+            .into_iter()
+            .partition(|stmt| Arc::strong_count(stmt) == 1);
+        self.evicted = referenced;
+        unreferenced
+    }
+
     pub fn clear(&mut self) {
         self.query_map.clear();
         self.cache.clear();
+        self.evicted.clear();
     }
 
     pub fn remove(&mut self, id: u32) {
         if let Some(entry) = self.cache.pop(&id) {
             self.query_map.remove::<[u8]>(entry.query.borrow());
         }
+        self.evicted.retain(|stmt| stmt.id() != id);
     }
 
     #[cfg(test)]
@@ -113,7 +144,11 @@ impl StmtCache {
         self.cache.iter()
     }
 
-    pub fn into_iter(mut self) -> impl Iterator<Item = (u32, Entry)> {
+    /// Returns all the statements, including evicted ones.
+    pub fn into_iter(mut self) -> impl Iterator<Item = Arc<InnerStmt>> {
+        let evicted = mem::take(&mut self.evicted);
         std::iter::from_fn(move || self.cache.pop_lru())
+            .map(|(_, entry)| entry.stmt)
+            .chain(evicted)
     }
 }
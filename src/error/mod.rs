@@ -15,7 +15,7 @@ use url::ParseError;
 
 use std::{error, fmt, io, result, sync};
 
-use crate::{Row, Value};
+use crate::{consts::ColumnType, Row, Value};
 
 pub mod tls;
 
@@ -65,8 +65,12 @@ pub enum Error {
     UrlError(UrlError),
     #[cfg(any(feature = "native-tls", feature = "rustls"))]
     TlsError(tls::TlsError),
-    FromValueError(Value),
-    FromRowError(Row),
+    /// Value couldn't be converted. Carries the MySql type of the source column
+    /// and the target Rust type, if known (see [`crate::prelude::RowExt::try_get`]).
+    FromValueError(Value, Option<(ColumnType, &'static str)>),
+    /// Row couldn't be converted. Carries the target Rust type, if known
+    /// (see [`crate::prelude::Queryable::select`]).
+    FromRowError(Row, Option<&'static str>),
 }
 
 impl Error {
@@ -75,11 +79,12 @@ impl Error {
         match self {
             #[cfg(any(feature = "native-tls", feature = "rustls"))]
             Error::TlsError(_) => true,
+            Error::DriverError(DriverError::EmptyQuery) => false,
             Error::IoError(_) | Error::DriverError(_) | Error::CodecError(_) => true,
             Error::MySqlError(_)
             | Error::UrlError(_)
-            | Error::FromValueError(_)
-            | Error::FromRowError(_) => false,
+            | Error::FromValueError(..)
+            | Error::FromRowError(..) => false,
         }
     }
 
@@ -108,13 +113,13 @@ impl error::Error for Error {
 
 impl From<FromValueError> for Error {
     fn from(FromValueError(value): FromValueError) -> Error {
-        Error::FromValueError(value)
+        Error::FromValueError(value, None)
     }
 }
 
 impl From<FromRowError> for Error {
     fn from(FromRowError(row): FromRowError) -> Error {
-        Error::FromRowError(row)
+        Error::FromRowError(row, None)
     }
 }
 
@@ -184,9 +189,39 @@ impl fmt::Display for Error {
             Error::UrlError(ref err) => write!(f, "UrlError {{ {} }}", err),
             #[cfg(any(feature = "native-tls", feature = "rustls"))]
             Error::TlsError(ref err) => write!(f, "TlsError {{ {} }}", err),
-            Error::FromRowError(_) => "from row conversion error".fmt(f),
-            Error::FromValueError(_) => "from value conversion error".fmt(f),
+            Error::FromRowError(ref row, target) => {
+                write!(
+                    f,
+                    "from row conversion error: couldn't convert row ({})",
+                    RowColumns(row)
+                )?;
+                match target {
+                    Some(target) => write!(f, " to `{}`", target),
+                    None => Ok(()),
+                }
+            }
+            Error::FromValueError(_, Some((column_type, target))) => write!(
+                f,
+                "from value conversion error: couldn't convert {:?} value to `{}`",
+                column_type, target
+            ),
+            Error::FromValueError(_, None) => "from value conversion error".fmt(f),
+        }
+    }
+}
+
+/// Displays names and MySql types of row columns, e.g. `` `id`: MYSQL_TYPE_LONGLONG ``.
+pub(crate) struct RowColumns<'a>(pub(crate) &'a Row);
+
+impl fmt::Display for RowColumns<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, column) in self.0.columns_ref().iter().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            write!(f, "`{}`: {:?}", column.name_str(), column.column_type())?;
         }
+        Ok(())
     }
 }
 
@@ -203,9 +238,25 @@ pub enum DriverError {
     CouldNotConnect(Option<(String, String, io::ErrorKind)>),
     UnsupportedProtocol(u8),
     PacketOutOfSync,
-    PacketTooLarge,
-    Protocol41NotSet,
+    /// Outgoing packet of `len` bytes exceeds the client side `max_allowed_packet` (`max`).
+    PacketTooLarge {
+        len: usize,
+        max: usize,
+    },
+    /// Server doesn't support `CLIENT_PROTOCOL_41`
+    /// (`server_version` and `capabilities` are those advertised in the handshake).
+    Protocol41NotSet {
+        server_version: String,
+        capabilities: u32,
+    },
     UnexpectedPacket,
+    /// Server sent `got` of `expected` parameter or column (`kind`) definitions
+    /// in reply to `COM_STMT_PREPARE`.
+    TruncatedPrepareResponse {
+        kind: &'static str,
+        expected: u16,
+        got: u16,
+    },
     MismatchedStmtParams(u16, usize),
     InvalidPoolConstraints,
     SetupError,
@@ -220,6 +271,13 @@ pub enum DriverError {
     UnknownAuthPlugin(String),
     OldMysqlPasswordDisabled,
     CleartextPluginDisabled,
+    NoAddress,
+    TooManyRows(u64),
+    /// Query text is empty or consists of whitespaces only (it is rejected on the client side).
+    EmptyQuery,
+    /// The first packet received from the server doesn't look like a MySql handshake
+    /// (e.g. the address points to a non-MySql server). Holds first bytes of the packet.
+    NotMySqlServer(Vec<u8>),
 }
 
 impl error::Error for DriverError {
@@ -242,9 +300,30 @@ impl fmt::Display for DriverError {
                 write!(f, "Unsupported protocol version {}", proto_version)
             }
             DriverError::PacketOutOfSync => write!(f, "Packet out of sync"),
-            DriverError::PacketTooLarge => write!(f, "Packet too large"),
-            DriverError::Protocol41NotSet => write!(f, "Server must set CLIENT_PROTOCOL_41 flag"),
+            DriverError::PacketTooLarge { len, max } => write!(
+                f,
+                "Packet too large: {} bytes exceeds max_allowed_packet of {} bytes",
+                len, max
+            ),
+            DriverError::Protocol41NotSet {
+                ref server_version,
+                capabilities,
+            } => write!(
+                f,
+                "Server must set CLIENT_PROTOCOL_41 flag \
+                 (server version: {}, capabilities: {:#010x})",
+                server_version, capabilities
+            ),
             DriverError::UnexpectedPacket => write!(f, "Unexpected packet"),
+            DriverError::TruncatedPrepareResponse {
+                kind,
+                expected,
+                got,
+            } => write!(
+                f,
+                "Server sent {} of {} {} definitions in reply to COM_STMT_PREPARE",
+                got, expected, kind
+            ),
             DriverError::MismatchedStmtParams(exp, prov) => write!(
                 f,
                 "Statement takes {} parameters but {} was supplied",
@@ -286,6 +365,23 @@ impl fmt::Display for DriverError {
             DriverError::CleartextPluginDisabled => {
                 write!(f, "mysql_clear_password must be enabled on the client side")
             }
+            DriverError::NoAddress => {
+                write!(f, "Could not connect: neither host nor socket is specified")
+            }
+            DriverError::TooManyRows(max) => {
+                write!(f, "Result set exceeds the limit of {} rows", max)
+            }
+            DriverError::EmptyQuery => write!(f, "Query is empty"),
+            DriverError::NotMySqlServer(ref bytes) => {
+                write!(
+                    f,
+                    "Not a MySQL server or unsupported protocol (first bytes:"
+                )?;
+                for byte in bytes {
+                    write!(f, " {:02x}", byte)?;
+                }
+                write!(f, ")")
+            }
         }
     }
 }
@@ -1096,3 +1192,39 @@ pub enum ServerError {
     ER_UNSUPPORTED_ENGINE = 1726u16,
     ER_BINLOG_UNSAFE_AUTOINC_NOT_FIRST = 1727u16,
 }
+
+#[cfg(test)]
+mod test {
+    use mysql_common::{
+        io::ParseBuf,
+        packets::{ErrPacket, ServerError},
+    };
+
+    use super::MySqlError;
+    use crate::consts::CapabilityFlags;
+
+    fn parse_err(payload: &[u8]) -> ServerError<'_> {
+        match ParseBuf(payload)
+            .parse(CapabilityFlags::CLIENT_PROTOCOL_41)
+            .unwrap()
+        {
+            ErrPacket::Error(server_error) => server_error,
+            ErrPacket::Progress(_) => panic!("server error expected"),
+        }
+    }
+
+    #[test]
+    fn should_format_non_utf8_server_error() {
+        // latin1 encoded message
+        let payload = b"\xff\x15\x04#28000Acc\xe8s refus\xe9 \xff";
+        let err = MySqlError::from(parse_err(payload));
+        assert_eq!(err.code, 1045);
+        assert_eq!(err.state, "28000");
+        assert_eq!(err.message, "Acc\u{fffd}s refus\u{fffd} \u{fffd}");
+        assert_eq!(
+            err.to_string(),
+            "ERROR 1045 (28000): Acc\u{fffd}s refus\u{fffd} \u{fffd}"
+        );
+        assert_eq!(format!("{:?}", err), err.to_string());
+    }
+}
@@ -22,10 +22,13 @@ use std::{
     time::Duration,
 };
 
-use crate::error::{
-    DriverError::{ConnectTimeout, CouldNotConnect},
-    Error::DriverError,
-    Result as MyResult,
+use crate::{
+    error::{
+        DriverError::{ConnectTimeout, CouldNotConnect, NoAddress},
+        Error::DriverError,
+        Result as MyResult,
+    },
+    Opts,
 };
 
 mod tcp;
@@ -41,6 +44,44 @@ pub enum Stream {
 }
 
 impl Stream {
+    /// Connects to the server using the given options.
+    ///
+    /// Socket (or named pipe on windows) is used if defined, TCP otherwise.
+    /// This is the only place where the transport is chosen, so every path
+    /// (`Conn::new`, `Conn::reconnect`, `prefer_socket` upgrade) behaves the same.
+    pub fn dial(opts: &Opts) -> MyResult<Stream> {
+        let read_timeout = opts.get_read_timeout().cloned();
+        let write_timeout = opts.get_write_timeout().cloned();
+        if let Some(socket) = opts.get_socket() {
+            return Stream::connect_socket(socket, read_timeout, write_timeout);
+        }
+
+        let ip_or_hostname = match opts.get_host() {
+            url::Host::Domain(domain) if domain.is_empty() => {
+                return Err(DriverError(NoAddress));
+            }
+            url::Host::Domain(domain) => domain,
+            url::Host::Ipv4(ip) => ip.to_string(),
+            url::Host::Ipv6(ip) => ip.to_string(),
+        };
+        Stream::connect_tcp(
+            &ip_or_hostname,
+            opts.get_tcp_port(),
+            read_timeout,
+            write_timeout,
+            opts.get_tcp_keepalive_time_ms(),
+            #[cfg(any(target_os = "linux", target_os = "macos",))]
+            opts.get_tcp_keepalive_probe_interval_secs(),
+            #[cfg(any(target_os = "linux", target_os = "macos",))]
+            opts.get_tcp_keepalive_probe_count(),
+            #[cfg(target_os = "linux")]
+            opts.get_tcp_user_timeout_ms(),
+            opts.get_tcp_nodelay(),
+            opts.get_tcp_connect_timeout(),
+            opts.bind_address().cloned(),
+        )
+    }
+
     #[cfg(unix)]
     pub fn connect_socket(
         socket: &str,
@@ -55,7 +96,16 @@ impl Stream {
             }
             Err(e) => {
                 let addr = socket.to_string();
-                let desc = e.to_string();
+                let desc = match e.kind() {
+                    io::ErrorKind::NotFound => format!("socket file does not exist ({})", e),
+                    io::ErrorKind::PermissionDenied => {
+                        format!("permission denied to access socket file ({})", e)
+                    }
+                    io::ErrorKind::ConnectionRefused => {
+                        format!("no server is listening on socket file ({})", e)
+                    }
+                    _ => e.to_string(),
+                };
                 Err(DriverError(CouldNotConnect(Some((addr, desc, e.kind())))))
             }
         }
@@ -134,6 +184,20 @@ impl Stream {
             })
     }
 
+    /// Sets the read timeout of the underlying socket.
+    pub fn set_read_timeout(&mut self, read_timeout: Option<Duration>) -> io::Result<()> {
+        match self {
+            #[cfg(unix)]
+            Stream::SocketStream(stream) => stream.get_ref().set_read_timeout(read_timeout),
+            #[cfg(windows)]
+            Stream::SocketStream(stream) => {
+                stream.get_mut().set_read_timeout(read_timeout);
+                Ok(())
+            }
+            Stream::TcpStream(stream) => stream.set_read_timeout(read_timeout),
+        }
+    }
+
     pub fn is_insecure(&self) -> bool {
         matches!(self, Stream::TcpStream(TcpStream::Insecure(_)))
     }
@@ -142,6 +206,19 @@ impl Stream {
         matches!(self, Stream::SocketStream(_))
     }
 
+    pub fn is_tcp(&self) -> bool {
+        matches!(self, Stream::TcpStream(_))
+    }
+
+    /// Returns the address of the server, if connected via TCP.
+    pub fn peer_addr(&self) -> Option<SocketAddr> {
+        match self {
+            Stream::TcpStream(stream) => stream.peer_addr().ok(),
+            #[cfg(any(unix, windows))]
+            Stream::SocketStream(_) => None,
+        }
+    }
+
     #[cfg(all(not(feature = "native-tls"), not(feature = "rustls")))]
     pub fn make_secure(self, _host: url::Host, _ssl_opts: crate::SslOpts) -> MyResult<Stream> {
         panic!(
@@ -170,6 +247,28 @@ pub enum TcpStream {
     Insecure(BufStream<net::TcpStream>),
 }
 
+impl TcpStream {
+    fn set_read_timeout(&self, read_timeout: Option<Duration>) -> io::Result<()> {
+        match self {
+            #[cfg(feature = "native-tls")]
+            TcpStream::Secure(stream) => stream.get_ref().get_ref().set_read_timeout(read_timeout),
+            #[cfg(feature = "rustls")]
+            TcpStream::Secure(stream) => stream.get_ref().get_ref().set_read_timeout(read_timeout),
+            TcpStream::Insecure(stream) => stream.get_ref().set_read_timeout(read_timeout),
+        }
+    }
+
+    fn peer_addr(&self) -> io::Result<SocketAddr> {
+        match self {
+            #[cfg(feature = "native-tls")]
+            TcpStream::Secure(stream) => stream.get_ref().get_ref().peer_addr(),
+            #[cfg(feature = "rustls")]
+            TcpStream::Secure(stream) => stream.get_ref().get_ref().peer_addr(),
+            TcpStream::Insecure(stream) => stream.get_ref().peer_addr(),
+        }
+    }
+}
+
 #[cfg(unix)]
 impl AsRawFd for TcpStream {
     fn as_raw_fd(&self) -> RawFd {
@@ -194,3 +293,67 @@ impl fmt::Debug for TcpStream {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::net::TcpListener;
+
+    use super::Stream;
+    use crate::OptsBuilder;
+
+    #[test]
+    fn should_dial_tcp_without_socket() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let opts = OptsBuilder::new()
+            .ip_or_hostname(Some("127.0.0.1"))
+            .tcp_port(listener.local_addr().unwrap().port());
+        let stream = Stream::dial(&opts.into()).unwrap();
+        assert!(stream.is_insecure());
+        assert!(!stream.is_socket());
+        assert!(stream.is_tcp());
+        assert_eq!(stream.peer_addr(), Some(listener.local_addr().unwrap()));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn should_dial_socket_first() {
+        use std::os::unix::net::UnixListener;
+
+        let path = std::env::temp_dir().join(format!("mysql-dial-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let listener = UnixListener::bind(&path).unwrap();
+
+        // TCP address is ignored if socket is defined
+        let opts = OptsBuilder::new()
+            .ip_or_hostname(Some("127.0.0.1"))
+            .tcp_port(1)
+            .socket(Some(path.to_str().unwrap()));
+        let stream = Stream::dial(&opts.into());
+        drop(listener);
+        std::fs::remove_file(&path).unwrap();
+        let stream = stream.unwrap();
+        assert!(stream.is_socket());
+        assert!(!stream.is_tcp());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn should_name_missing_socket_path() {
+        use crate::{DriverError::CouldNotConnect, Error::DriverError};
+
+        let path = std::env::temp_dir().join(format!("mysql-missing-{}.sock", std::process::id()));
+        let path = path.to_str().unwrap();
+        let opts = OptsBuilder::new().socket(Some(path));
+        match Stream::dial(&opts.into()) {
+            Err(DriverError(CouldNotConnect(Some((addr, desc, kind))))) => {
+                assert_eq!(addr, path);
+                assert!(desc.starts_with("socket file does not exist"), "{}", desc);
+                assert_eq!(kind, std::io::ErrorKind::NotFound);
+            }
+            other => panic!("CouldNotConnect expected, got {:?}", other.map(|_| ())),
+        }
+
+        let err = Stream::dial(&OptsBuilder::new().socket(Some(path)).into()).unwrap_err();
+        assert!(err.to_string().contains(path), "{}", err);
+    }
+}
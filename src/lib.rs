@@ -891,9 +891,14 @@ pub extern crate serde_json;
 extern crate serde_derive;
 
 mod buffer_pool;
+mod column;
 mod conn;
 pub mod error;
 mod io;
+mod ok_packet;
+mod row;
+mod server_status;
+mod value;
 
 #[cfg(feature = "derive")]
 extern crate mysql_common;
@@ -921,6 +926,8 @@ pub use crate::conn::opts::ClientIdentity;
 #[doc(inline)]
 pub use crate::myc::packets::{session_state_change, SessionStateInfo};
 
+#[doc(inline)]
+pub use crate::conn::auth::{AuthPlugin, AuthPluginHandler, AuthStep};
 #[cfg(feature = "binlog")]
 #[doc(inline)]
 pub use crate::conn::binlog_stream::BinlogStream;
@@ -940,6 +947,8 @@ pub use crate::conn::query::QueryWithParams;
 #[doc(inline)]
 pub use crate::conn::query_result::{Binary, QueryResult, ResultSet, SetColumns, Text};
 #[doc(inline)]
+pub use crate::conn::stats::ConnStats;
+#[doc(inline)]
 pub use crate::conn::stmt::Statement;
 #[doc(inline)]
 pub use crate::conn::transaction::{AccessMode, IsolationLevel, Transaction, TxOpts};
@@ -948,7 +957,7 @@ pub use crate::conn::Conn;
 #[doc(inline)]
 pub use crate::error::{DriverError, Error, MySqlError, Result, ServerError, UrlError};
 #[doc(inline)]
-pub use crate::myc::packets::Column;
+pub use crate::myc::packets::{Column, OkPacket};
 #[doc(inline)]
 pub use crate::myc::params::Params;
 #[doc(inline)]
@@ -963,8 +972,16 @@ pub use crate::myc::value::convert::{from_value, from_value_opt, FromValueError}
 pub use crate::myc::value::json::{Deserialized, Serialized};
 #[doc(inline)]
 pub use crate::myc::value::Value;
+#[doc(inline)]
+pub use crate::ok_packet::LoadInfo;
+#[doc(inline)]
+pub use crate::server_status::ServerStatus;
+#[doc(inline)]
+pub use crate::value::{value_from_base64, BytesDisplay, ValueDisplay, ValueKey};
 
 pub mod prelude {
+    #[doc(inline)]
+    pub use crate::column::ColumnExt;
     #[doc(inline)]
     pub use crate::conn::query::{BatchQuery, BinQuery, TextQuery, WithParams};
     #[doc(inline)]
@@ -975,6 +992,12 @@ pub mod prelude {
     pub use crate::myc::prelude::{FromValue, ToValue};
     #[doc(inline)]
     pub use crate::myc::row::ColumnIndex;
+    #[doc(inline)]
+    pub use crate::ok_packet::OkPacketExt;
+    #[doc(inline)]
+    pub use crate::row::RowExt;
+    #[doc(inline)]
+    pub use crate::value::ValueExt;
 
     /// Trait for protocol markers [`crate::Binary`] and [`crate::Text`].
     pub trait Protocol: crate::conn::query_result::Protocol {}
@@ -1065,7 +1088,10 @@ mod test_misc {
     #[allow(dead_code)]
     fn error_should_implement_send_and_sync() {
         fn _dummy<T: Send + Sync>(_: T) {}
-        _dummy(crate::error::Error::FromValueError(crate::Value::NULL));
+        _dummy(crate::error::Error::FromValueError(
+            crate::Value::NULL,
+            None,
+        ));
     }
 
     #[allow(dead_code)]
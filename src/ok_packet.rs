@@ -0,0 +1,130 @@
+// Copyright (c) 2020 rust-mysql-simple contributors
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use crate::OkPacket;
+
+/// Counts reported in the info of an OK packet after a bulk operation, such as
+/// `LOAD DATA`, multi-row `INSERT` or `ALTER TABLE`.
+///
+/// Counts that are not reported by the statement (e.g. `Duplicates` for `LOAD DATA`)
+/// are zero.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct LoadInfo {
+    records: u64,
+    duplicates: u64,
+    deleted: u64,
+    skipped: u64,
+    warnings: u64,
+}
+
+impl LoadInfo {
+    /// Parses an info string, e.g. `Records: 3  Duplicates: 0  Warnings: 1`.
+    ///
+    /// Returns `None` if the string doesn't contain `Records` and `Warnings` counts
+    /// or contains anything else besides the known counts.
+    pub fn parse(info: &[u8]) -> Option<LoadInfo> {
+        let info = std::str::from_utf8(info).ok()?;
+        let mut load_info = LoadInfo::default();
+        let (mut has_records, mut has_warnings) = (false, false);
+
+        let mut tokens = info.split_whitespace();
+        while let Some(name) = tokens.next() {
+            let count = tokens.next()?.parse().ok()?;
+            match name {
+                "Records:" => {
+                    load_info.records = count;
+                    has_records = true;
+                }
+                "Duplicates:" => load_info.duplicates = count,
+                "Deleted:" => load_info.deleted = count,
+                "Skipped:" => load_info.skipped = count,
+                "Warnings:" => {
+                    load_info.warnings = count;
+                    has_warnings = true;
+                }
+                _ => return None,
+            }
+        }
+
+        if has_records && has_warnings {
+            Some(load_info)
+        } else {
+            None
+        }
+    }
+
+    /// Number of processed records.
+    pub fn records(&self) -> u64 {
+        self.records
+    }
+
+    /// Number of records, that duplicate an existing unique key value.
+    pub fn duplicates(&self) -> u64 {
+        self.duplicates
+    }
+
+    /// Number of deleted (replaced) records.
+    pub fn deleted(&self) -> u64 {
+        self.deleted
+    }
+
+    /// Number of skipped records.
+    pub fn skipped(&self) -> u64 {
+        self.skipped
+    }
+
+    /// Number of warnings.
+    pub fn warnings(&self) -> u64 {
+        self.warnings
+    }
+}
+
+/// Helper methods for [`OkPacket`].
+pub trait OkPacketExt {
+    /// Parses the info of this OK packet (see [`LoadInfo::parse`]).
+    fn parse_info(&self) -> Option<LoadInfo>;
+}
+
+impl OkPacketExt for OkPacket<'_> {
+    fn parse_info(&self) -> Option<LoadInfo> {
+        self.info_ref().and_then(LoadInfo::parse)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::LoadInfo;
+
+    #[test]
+    fn should_parse_load_info() {
+        let info = LoadInfo::parse(b"Records: 3  Duplicates: 1  Warnings: 2").unwrap();
+        assert_eq!(info.records(), 3);
+        assert_eq!(info.duplicates(), 1);
+        assert_eq!(info.warnings(), 2);
+        assert_eq!(info.deleted(), 0);
+        assert_eq!(info.skipped(), 0);
+
+        let info = LoadInfo::parse(b"Records: 5  Deleted: 1  Skipped: 2  Warnings: 0").unwrap();
+        assert_eq!(info.records(), 5);
+        assert_eq!(info.deleted(), 1);
+        assert_eq!(info.skipped(), 2);
+        assert_eq!(info.warnings(), 0);
+    }
+
+    #[test]
+    fn should_not_parse_other_info() {
+        assert_eq!(LoadInfo::parse(b""), None);
+        assert_eq!(
+            LoadInfo::parse(b"Rows matched: 2  Changed: 2  Warnings: 0"),
+            None
+        );
+        assert_eq!(LoadInfo::parse(b"Records: 3  Duplicates: 0"), None);
+        assert_eq!(LoadInfo::parse(b"Records: x  Warnings: 0"), None);
+        assert_eq!(LoadInfo::parse(b"Records: 3  Warnings:"), None);
+    }
+}
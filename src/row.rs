@@ -0,0 +1,124 @@
+// Copyright (c) 2020 rust-mysql-simple contributors
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use mysql_common::{row::ColumnIndex, value::convert::FromValueError};
+
+use crate::{
+    prelude::{FromValue, ValueExt},
+    Error, Result, Row,
+};
+
+/// Helper methods for [`Row`].
+pub trait RowExt {
+    /// Converts `self` into a JSON object using column names as keys
+    /// (see [`ValueExt::to_json`] for the conversion of values).
+    ///
+    /// Values, that were taken from the row, are `null`. If several columns share
+    /// the same name, then the last one wins.
+    fn to_json_object(&self) -> serde_json::Value;
+
+    /// Same as [`Row::get_opt`] but the conversion error ([`Error::FromValueError`])
+    /// names the MySql type of the column and `T`.
+    ///
+    /// Returns `None` if there is no such column or the value was taken from the row.
+    fn try_get<T: FromValue, I: ColumnIndex>(&self, index: I) -> Option<Result<T>>;
+}
+
+impl RowExt for Row {
+    fn to_json_object(&self) -> serde_json::Value {
+        let object = self
+            .columns_ref()
+            .iter()
+            .enumerate()
+            .map(|(i, column)| {
+                let value = self
+                    .as_ref(i)
+                    .map_or(serde_json::Value::Null, ValueExt::to_json);
+                (column.name_str().into_owned(), value)
+            })
+            .collect::<serde_json::Map<_, _>>();
+        serde_json::Value::Object(object)
+    }
+
+    fn try_get<T: FromValue, I: ColumnIndex>(&self, index: I) -> Option<Result<T>> {
+        let index = index.idx(self.columns_ref())?;
+        let column_type = self.columns_ref()[index].column_type();
+        let result = self.get_opt::<T, usize>(index)?;
+        Some(result.map_err(|FromValueError(value)| {
+            Error::FromValueError(value, Some((column_type, std::any::type_name::<T>())))
+        }))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use mysql_common::row::new_row;
+
+    use crate::{consts::ColumnType, prelude::*, Column, Value};
+
+    #[test]
+    fn should_convert_row_to_json_object() {
+        let columns = vec![
+            Column::new(ColumnType::MYSQL_TYPE_LONG).with_name(b"id"),
+            Column::new(ColumnType::MYSQL_TYPE_VAR_STRING).with_name(b"name"),
+            Column::new(ColumnType::MYSQL_TYPE_BLOB).with_name(b"data"),
+            Column::new(ColumnType::MYSQL_TYPE_DOUBLE).with_name(b"score"),
+            Column::new(ColumnType::MYSQL_TYPE_DATETIME).with_name(b"created"),
+            Column::new(ColumnType::MYSQL_TYPE_NULL).with_name(b"nothing"),
+        ];
+        let mut row = new_row(
+            vec![
+                Value::Int(1),
+                Value::Bytes(b"foo".to_vec()),
+                Value::Bytes(vec![0xde, 0xad, 0xbe, 0xef]),
+                Value::Double(0.5),
+                Value::Date(2024, 1, 2, 3, 4, 5, 0),
+                Value::NULL,
+            ],
+            columns.into(),
+        );
+
+        assert_eq!(
+            row.to_json_object(),
+            serde_json::json!({
+                "id": 1,
+                "name": "foo",
+                "data": "3q2+7w==",
+                "score": 0.5,
+                "created": "2024-01-02T03:04:05",
+                "nothing": null,
+            })
+        );
+
+        let _: Option<Value> = row.take("name");
+        assert_eq!(row.to_json_object()["name"], serde_json::Value::Null);
+    }
+
+    #[test]
+    fn should_name_column_and_rust_types_in_conversion_errors() {
+        let columns = vec![Column::new(ColumnType::MYSQL_TYPE_DATETIME).with_name(b"dt")];
+        let row = new_row(vec![Value::Date(2020, 1, 1, 0, 0, 0, 0)], columns.into());
+
+        let err = row.try_get::<i64, _>("dt").unwrap().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "from value conversion error: couldn't convert MYSQL_TYPE_DATETIME value to `i64`"
+        );
+        assert!(row.try_get::<i64, _>("foo").is_none());
+        assert_eq!(
+            row.try_get::<Value, _>(0).unwrap().unwrap(),
+            Value::Date(2020, 1, 1, 0, 0, 0, 0)
+        );
+
+        let err = crate::Error::FromRowError(row, Some("i64"));
+        assert_eq!(
+            err.to_string(),
+            "from row conversion error: couldn't convert row (`dt`: MYSQL_TYPE_DATETIME) to `i64`"
+        );
+    }
+}
@@ -0,0 +1,123 @@
+// Copyright (c) 2020 rust-mysql-simple contributors
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use crate::consts::StatusFlags;
+
+/// Snapshot of server status flags, reported by the server in OK and EOF packets.
+///
+/// See [`crate::Conn::server_status`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ServerStatus(StatusFlags);
+
+impl ServerStatus {
+    /// Returns raw status flags.
+    pub fn flags(&self) -> StatusFlags {
+        self.0
+    }
+
+    /// A transaction is active (`SERVER_STATUS_IN_TRANS`).
+    pub fn in_transaction(&self) -> bool {
+        self.0.contains(StatusFlags::SERVER_STATUS_IN_TRANS)
+    }
+
+    /// Autocommit mode is enabled (`SERVER_STATUS_AUTOCOMMIT`).
+    pub fn autocommit(&self) -> bool {
+        self.0.contains(StatusFlags::SERVER_STATUS_AUTOCOMMIT)
+    }
+
+    /// More result sets are following (`SERVER_MORE_RESULTS_EXISTS`).
+    pub fn more_results(&self) -> bool {
+        self.0.contains(StatusFlags::SERVER_MORE_RESULTS_EXISTS)
+    }
+
+    /// The query used a bad index (`SERVER_STATUS_NO_GOOD_INDEX_USED`).
+    pub fn no_good_index_used(&self) -> bool {
+        self.0
+            .contains(StatusFlags::SERVER_STATUS_NO_GOOD_INDEX_USED)
+    }
+
+    /// The query used no index (`SERVER_STATUS_NO_INDEX_USED`).
+    pub fn no_index_used(&self) -> bool {
+        self.0.contains(StatusFlags::SERVER_STATUS_NO_INDEX_USED)
+    }
+
+    /// A read-only cursor is open for the executed statement (`SERVER_STATUS_CURSOR_EXISTS`).
+    pub fn cursor_exists(&self) -> bool {
+        self.0.contains(StatusFlags::SERVER_STATUS_CURSOR_EXISTS)
+    }
+
+    /// The last row of a cursor was sent (`SERVER_STATUS_LAST_ROW_SENT`).
+    pub fn last_row_sent(&self) -> bool {
+        self.0.contains(StatusFlags::SERVER_STATUS_LAST_ROW_SENT)
+    }
+
+    /// The current database was dropped (`SERVER_STATUS_DB_DROPPED`).
+    pub fn db_dropped(&self) -> bool {
+        self.0.contains(StatusFlags::SERVER_STATUS_DB_DROPPED)
+    }
+
+    /// `NO_BACKSLASH_ESCAPES` SQL mode is enabled (`SERVER_STATUS_NO_BACKSLASH_ESCAPES`).
+    pub fn no_backslash_escapes(&self) -> bool {
+        self.0
+            .contains(StatusFlags::SERVER_STATUS_NO_BACKSLASH_ESCAPES)
+    }
+
+    /// Metadata of a prepared statement has changed (`SERVER_STATUS_METADATA_CHANGED`).
+    pub fn metadata_changed(&self) -> bool {
+        self.0.contains(StatusFlags::SERVER_STATUS_METADATA_CHANGED)
+    }
+
+    /// The query was logged as slow (`SERVER_QUERY_WAS_SLOW`).
+    pub fn query_was_slow(&self) -> bool {
+        self.0.contains(StatusFlags::SERVER_QUERY_WAS_SLOW)
+    }
+
+    /// The result set contains output parameters of a procedure (`SERVER_PS_OUT_PARAMS`).
+    pub fn ps_out_params(&self) -> bool {
+        self.0.contains(StatusFlags::SERVER_PS_OUT_PARAMS)
+    }
+
+    /// A read-only transaction is active (`SERVER_STATUS_IN_TRANS_READONLY`).
+    pub fn in_read_only_transaction(&self) -> bool {
+        self.0
+            .contains(StatusFlags::SERVER_STATUS_IN_TRANS_READONLY)
+    }
+
+    /// Session state has changed (`SERVER_SESSION_STATE_CHANGED`).
+    pub fn session_state_changed(&self) -> bool {
+        self.0.contains(StatusFlags::SERVER_SESSION_STATE_CHANGED)
+    }
+}
+
+impl From<StatusFlags> for ServerStatus {
+    fn from(flags: StatusFlags) -> Self {
+        ServerStatus(flags)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::ServerStatus;
+    use crate::consts::StatusFlags;
+
+    #[test]
+    fn should_name_status_flags() {
+        let status = ServerStatus::from(
+            StatusFlags::SERVER_STATUS_IN_TRANS | StatusFlags::SERVER_MORE_RESULTS_EXISTS,
+        );
+        assert!(status.in_transaction());
+        assert!(status.more_results());
+        assert!(!status.autocommit());
+        assert!(!status.db_dropped());
+
+        let status = ServerStatus::from(StatusFlags::SERVER_STATUS_AUTOCOMMIT);
+        assert!(status.autocommit());
+        assert!(!status.in_transaction());
+        assert_eq!(status.flags(), StatusFlags::SERVER_STATUS_AUTOCOMMIT);
+    }
+}
@@ -0,0 +1,1568 @@
+// Copyright (c) 2020 rust-mysql-simple contributors
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use std::{
+    cmp::Ordering,
+    convert::TryFrom,
+    fmt,
+    hash::{Hash, Hasher},
+    mem,
+    str::FromStr,
+    time::Duration,
+};
+
+use crate::{
+    consts::{ColumnFlags, ColumnType},
+    Column, Value,
+};
+
+/// Length of the SRID prefix of MySql internal geometry format.
+const SRID_LEN: usize = 4;
+
+/// Maximum magnitude of a MySql `TIME` value (`838:59:59`) in seconds.
+const TIME_MAX_SECS: u64 = 838 * 60 * 60 + 59 * 60 + 59;
+
+/// Unix timestamp of `0000-01-01 00:00:00`.
+const UNIX_TIMESTAMP_MIN: i64 = -62_167_219_200;
+
+/// Unix timestamp of `9999-12-31 23:59:59`.
+const UNIX_TIMESTAMP_MAX: i64 = 253_402_300_799;
+
+/// Helper methods for [`Value`].
+pub trait ValueExt {
+    /// Returns [WKB] of a `MYSQL_TYPE_GEOMETRY` value.
+    ///
+    /// MySql stores geometry values as a 4-byte SRID followed by the WKB representation,
+    /// so this method strips the 4-byte SRID prefix. `Value` doesn't carry the column type,
+    /// so the caller must ensure that the column is `MYSQL_TYPE_GEOMETRY` (any `Bytes` value
+    /// longer than 4 bytes is accepted). Returns `None` for other values.
+    ///
+    /// [WKB]: https://dev.mysql.com/doc/refman/8.0/en/gis-data-formats.html#gis-wkb-format
+    fn as_wkb(&self) -> Option<&[u8]>;
+
+    /// Returns `i8` if `self` is an integer (or a textual integer) that fits into `i8`.
+    fn as_i8(&self) -> Option<i8>;
+
+    /// Returns `u8` if `self` is an integer (or a textual integer) that fits into `u8`.
+    fn as_u8(&self) -> Option<u8>;
+
+    /// Returns `i16` if `self` is an integer (or a textual integer) that fits into `i16`.
+    fn as_i16(&self) -> Option<i16>;
+
+    /// Returns `u16` if `self` is an integer (or a textual integer) that fits into `u16`.
+    fn as_u16(&self) -> Option<u16>;
+
+    /// Returns `i32` if `self` is an integer (or a textual integer) that fits into `i32`.
+    fn as_i32(&self) -> Option<i32>;
+
+    /// Returns `u32` if `self` is an integer (or a textual integer) that fits into `u32`.
+    fn as_u32(&self) -> Option<u32>;
+
+    /// Returns `i64` if `self` is an integer (or a textual integer) that fits into `i64`.
+    ///
+    /// Note that `BIGINT UNSIGNED` values may not fit, use [`ValueExt::as_u64`] for them
+    /// (see `UNSIGNED_FLAG` of the column).
+    fn as_i64(&self) -> Option<i64>;
+
+    /// Returns `u64` if `self` is an integer (or a textual integer) that fits into `u64`.
+    fn as_u64(&self) -> Option<u64>;
+
+    /// Returns the year if `self` is a valid `YEAR` column value (`0` or `1901..=2155`).
+    ///
+    /// Works for both protocols (`YEAR` is a textual integer in the text protocol).
+    fn as_year(&self) -> Option<u16>;
+
+    /// Returns `f64` if `self` is a number (or a textual number, such as `FLOAT` or `DOUBLE`
+    /// value in the text protocol, including the scientific notation, e.g. `1.23e10`).
+    ///
+    /// Integers are converted with possible loss of precision.
+    fn as_f64(&self) -> Option<f64>;
+
+    /// Same as [`ValueExt::as_f64`] but for `f32`.
+    fn as_f32(&self) -> Option<f32>;
+
+    /// Creates a non-negative `Value::Time` from the given duration.
+    ///
+    /// Same as `Value::from_signed_duration(false, duration)`.
+    fn from_duration(duration: Duration) -> Self
+    where
+        Self: Sized;
+
+    /// Creates a `Value::Time` from the given duration, normalized into the
+    /// days/hours/minutes/seconds/microseconds layout.
+    ///
+    /// Durations, that exceed the MySql `TIME` range, are clamped to `838:59:59`
+    /// (the same way MySql clamps out-of-range `TIME` values). Sub-microsecond
+    /// precision is truncated.
+    ///
+    /// ```
+    /// # use mysql::{prelude::*, Value};
+    /// # use std::time::Duration;
+    /// let value = Value::from_signed_duration(true, Duration::from_secs(90_061));
+    /// assert_eq!(value, Value::Time(true, 1, 1, 1, 1, 0));
+    /// ```
+    fn from_signed_duration(negative: bool, duration: Duration) -> Self
+    where
+        Self: Sized;
+
+    /// Creates a `Value::Date` from the given Unix timestamp (UTC), e.g. one obtained from
+    /// `std::time::SystemTime`, without the `chrono` or `time` dependency.
+    ///
+    /// Timestamps, that exceed the `0000-01-01 00:00:00..=9999-12-31 23:59:59` range,
+    /// are clamped. `usec` greater than `999_999` is clamped to `999_999`.
+    ///
+    /// ```
+    /// # use mysql::{prelude::*, Value};
+    /// let value = Value::from_unix_timestamp(1_700_000_000, 5);
+    /// assert_eq!(value, Value::Date(2023, 11, 14, 22, 13, 20, 5));
+    /// ```
+    fn from_unix_timestamp(secs: i64, usec: u32) -> Self
+    where
+        Self: Sized;
+
+    /// Returns the duration if `self` is a non-negative `TIME` value
+    /// (either `Value::Time` or its textual representation).
+    ///
+    /// Use [`ValueExt::as_signed_duration`] to also get negative values.
+    fn as_duration(&self) -> Option<Duration>;
+
+    /// Returns the sign (`true` if negative) and the magnitude of a `TIME` value
+    /// (either `Value::Time` or its textual representation).
+    fn as_signed_duration(&self) -> Option<(bool, Duration)>;
+
+    /// Returns the signed total number of seconds of a `TIME` value (either `Value::Time`
+    /// or its textual representation), i.e. days, hours, minutes and seconds folded together.
+    ///
+    /// Microseconds are truncated.
+    ///
+    /// ```
+    /// # use mysql::{prelude::*, Value};
+    /// assert_eq!(Value::Time(true, 1, 2, 0, 30, 0).total_time_seconds(), Some(-93_630));
+    /// ```
+    fn total_time_seconds(&self) -> Option<i64>;
+
+    /// Returns the signed total number of hours of a `TIME` value (see
+    /// [`ValueExt::total_time_seconds`]), so that `838:59:59` gives `838`.
+    ///
+    /// Minutes and seconds are truncated.
+    fn total_hours(&self) -> Option<i64>;
+
+    /// Converts `self` into a JSON value (e.g. for structured logging):
+    ///
+    /// *   `NULL` is `null`;
+    /// *   `Int`, `UInt`, `Float` and `Double` are numbers (non-finite floats are `null`);
+    /// *   `Bytes` is a string if it is a valid UTF-8, otherwise it is a base64 string;
+    /// *   `Date` is an ISO 8601 date (`2024-01-02`) or date and time
+    ///     (`2024-01-02T03:04:05.000006`);
+    /// *   `Time` is a MySql `TIME` string (`-27:04:05`).
+    ///
+    /// ```
+    /// # use mysql::{prelude::*, Value};
+    /// assert_eq!(Value::Bytes(b"foo".to_vec()).to_json(), "foo");
+    /// assert_eq!(Value::Bytes(vec![0xff]).to_json(), "/w==");
+    /// ```
+    fn to_json(&self) -> serde_json::Value;
+
+    /// Formats `Value::Date` as an ISO 8601 date and time with the `T` separator
+    /// (`2014-02-20T22:00:00.000001`, fractional part is omitted if zero), and `Value::Time`
+    /// as an ISO 8601 duration (`-PT27H4M5.000006S`). Returns `None` for other values.
+    ///
+    /// Values carry no time zone, so append `Z` yourself if you know they are in UTC.
+    /// Use [`Value::as_sql`] to get an SQL literal.
+    ///
+    /// ```
+    /// # use mysql::{prelude::*, Value};
+    /// let value = Value::Date(2014, 2, 20, 22, 0, 0, 1);
+    /// assert_eq!(value.to_iso8601().as_deref(), Some("2014-02-20T22:00:00.000001"));
+    /// let value = Value::Time(false, 1, 2, 0, 30, 0);
+    /// assert_eq!(value.to_iso8601().as_deref(), Some("PT26H0M30S"));
+    /// ```
+    fn to_iso8601(&self) -> Option<String>;
+
+    /// Encodes `Value::Bytes` using the standard base64 alphabet with padding
+    /// (returns `None` for other values).
+    ///
+    /// See [`value_from_base64`] for the reverse conversion.
+    ///
+    /// ```
+    /// # use mysql::{prelude::*, Value};
+    /// assert_eq!(Value::Bytes(b"foo".to_vec()).to_base64().as_deref(), Some("Zm9v"));
+    /// assert_eq!(Value::Int(1).to_base64(), None);
+    /// ```
+    fn to_base64(&self) -> Option<String>;
+
+    /// Total ordering of values, that is suitable for sorting query results.
+    ///
+    /// Unlike the derived `PartialOrd` implementation, this one doesn't compare
+    /// values by variant first:
+    ///
+    /// *   `NULL` is less than any other value (as in MySql `ORDER BY`);
+    /// *   numeric values (`Int`, `UInt`, `Float` and `Double`) are compared numerically
+    ///     with each other, integers are never rounded to floats (`NaN` is greater than any
+    ///     other number);
+    /// *   `Bytes` are compared lexicographically;
+    /// *   `Date` and `Time` values are compared chronologically;
+    /// *   values of different kinds are ordered as follows:
+    ///     `NULL < numeric < Bytes < Date < Time`.
+    ///
+    /// ```
+    /// # use mysql::{prelude::*, Value};
+    /// let mut values = vec![Value::Bytes(b"1".to_vec()), Value::Int(5), Value::NULL];
+    /// values.sort_by(Value::mysql_cmp);
+    /// assert_eq!(values, vec![Value::NULL, Value::Int(5), Value::Bytes(b"1".to_vec())]);
+    /// ```
+    fn mysql_cmp(&self, other: &Self) -> Ordering;
+
+    /// Returns an object that displays `self` as text, rendering `Bytes` according
+    /// to `mode` (e.g. for logging):
+    ///
+    /// *   `NULL` is `NULL`;
+    /// *   `Date` and `Time` are rendered as MySql would (`2024-01-02 03:04:05`, `-27:04:05`);
+    /// *   numbers are rendered as is.
+    ///
+    /// ```
+    /// # use mysql::{prelude::*, BytesDisplay, Value};
+    /// let value = Value::Bytes(b"foo".to_vec());
+    /// assert_eq!(value.display_with(BytesDisplay::Auto).to_string(), "foo");
+    /// assert_eq!(value.display_with(BytesDisplay::HexAlways).to_string(), "0x666F6F");
+    /// ```
+    fn display_with(&self, mode: BytesDisplay) -> ValueDisplay<'_>;
+
+    /// Same as [`Value::as_sql`] but `Bytes` are always rendered as a hex literal,
+    /// even if they are valid UTF-8 (e.g. for binary keys that happen to be ASCII).
+    ///
+    /// ```
+    /// # use mysql::{prelude::*, Value};
+    /// let value = Value::Bytes(b"foo".to_vec());
+    /// assert_eq!(value.as_sql(false), "'foo'");
+    /// assert_eq!(value.as_sql_hex(false), "0x666F6F");
+    /// assert_eq!(Value::Int(1).as_sql_hex(false), "1");
+    /// ```
+    fn as_sql_hex(&self, no_backslash_escape: bool) -> String;
+
+    /// Returns `true` if `self` is `Bytes` equal to `other` ignoring ASCII case
+    /// (similar to `_ci` collations for ASCII text), `false` for any other value.
+    ///
+    /// ```
+    /// # use mysql::{prelude::*, Value};
+    /// assert!(Value::Bytes(b"Hello".to_vec()).eq_ignore_ascii_case("hello"));
+    /// assert!(!Value::Int(1).eq_ignore_ascii_case("1"));
+    /// ```
+    fn eq_ignore_ascii_case(&self, other: &str) -> bool;
+}
+
+/// Rendering mode of `Value::Bytes` (see [`ValueExt::display_with`]).
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BytesDisplay {
+    /// Valid UTF-8 is rendered as text, anything else is rendered as hex.
+    #[default]
+    Auto,
+    /// Rendered as text, invalid UTF-8 sequences are replaced with `U+FFFD`.
+    Utf8Lossy,
+    /// Always rendered as hex (e.g. `0x666F6F`).
+    HexAlways,
+}
+
+/// Displays a value (see [`ValueExt::display_with`]).
+#[derive(Debug, Clone, Copy)]
+pub struct ValueDisplay<'a> {
+    value: &'a Value,
+    mode: BytesDisplay,
+}
+
+impl fmt::Display for ValueDisplay<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.value, self.mode) {
+            (Value::NULL, _) => f.write_str("NULL"),
+            (Value::Bytes(bytes), BytesDisplay::Auto) => match std::str::from_utf8(bytes) {
+                Ok(string) => f.write_str(string),
+                Err(_) => write_hex(f, bytes),
+            },
+            (Value::Bytes(bytes), BytesDisplay::Utf8Lossy) => {
+                f.write_str(&String::from_utf8_lossy(bytes))
+            }
+            (Value::Bytes(bytes), BytesDisplay::HexAlways) => write_hex(f, bytes),
+            // temporal values are quoted by `Value::as_sql`
+            (value @ (Value::Date(..) | Value::Time(..)), _) => {
+                f.write_str(value.as_sql(false).trim_matches('\''))
+            }
+            (value, _) => f.write_str(&value.as_sql(false)),
+        }
+    }
+}
+
+/// Writes `bytes` as a hex literal, e.g. `0x666F6F`.
+fn write_hex(f: &mut fmt::Formatter<'_>, bytes: &[u8]) -> fmt::Result {
+    f.write_str("0x")?;
+    for byte in bytes {
+        write!(f, "{:02X}", byte)?;
+    }
+    Ok(())
+}
+
+/// Converts an integer value to `T` without truncation.
+fn as_int<T>(value: &Value) -> Option<T>
+where
+    T: TryFrom<i64> + TryFrom<u64> + FromStr,
+{
+    match value {
+        Value::Int(x) => <T as TryFrom<i64>>::try_from(*x).ok(),
+        Value::UInt(x) => <T as TryFrom<u64>>::try_from(*x).ok(),
+        Value::Bytes(bytes) => std::str::from_utf8(bytes).ok()?.parse().ok(),
+        _ => None,
+    }
+}
+
+/// Wrapper, that makes [`Value`] usable as a `HashMap` or `HashSet` key
+/// (e.g. to deduplicate rows on the client side).
+///
+/// Values are equal if they are of the same variant with equal payloads, so
+/// `Value::Int(1)` and `Value::UInt(1)` are different keys.
+///
+/// ## Note
+///
+/// Unlike [`Value`]'s `PartialEq`, `Float` and `Double` values follow the total-equality
+/// semantics: any `NaN` is equal to any other `NaN` of the same variant. Other floats are
+/// compared as usual (so `0.0` and `-0.0` are the same key).
+///
+/// ```
+/// # use mysql::{Value, ValueKey};
+/// # use std::collections::HashSet;
+/// let values = vec![
+///     Value::Int(1),
+///     Value::NULL,
+///     Value::Int(1),
+///     Value::Double(f64::NAN),
+///     Value::Double(-f64::NAN),
+/// ];
+/// let unique = values.into_iter().map(ValueKey).collect::<HashSet<_>>();
+/// assert_eq!(unique.len(), 3);
+/// ```
+#[derive(Debug, Clone)]
+pub struct ValueKey(pub Value);
+
+impl ValueKey {
+    /// Returns the wrapped value.
+    pub fn into_inner(self) -> Value {
+        self.0
+    }
+}
+
+impl From<Value> for ValueKey {
+    fn from(value: Value) -> Self {
+        ValueKey(value)
+    }
+}
+
+impl PartialEq for ValueKey {
+    fn eq(&self, other: &Self) -> bool {
+        match (&self.0, &other.0) {
+            (Value::Float(a), Value::Float(b)) => a == b || (a.is_nan() && b.is_nan()),
+            (Value::Double(a), Value::Double(b)) => a == b || (a.is_nan() && b.is_nan()),
+            (a, b) => a == b,
+        }
+    }
+}
+
+impl Eq for ValueKey {}
+
+impl Hash for ValueKey {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        mem::discriminant(&self.0).hash(state);
+        match self.0 {
+            Value::NULL => (),
+            Value::Bytes(ref bytes) => bytes.hash(state),
+            Value::Int(x) => x.hash(state),
+            Value::UInt(x) => x.hash(state),
+            // equal floats must have equal hashes, so `NaN`s and zeros are canonicalized
+            Value::Float(x) if x.is_nan() => f32::NAN.to_bits().hash(state),
+            Value::Float(0.0) => 0_u32.hash(state),
+            Value::Float(x) => x.to_bits().hash(state),
+            Value::Double(x) if x.is_nan() => f64::NAN.to_bits().hash(state),
+            Value::Double(0.0) => 0_u64.hash(state),
+            Value::Double(x) => x.to_bits().hash(state),
+            Value::Date(year, month, day, hour, minute, second, micros) => {
+                (year, month, day, hour, minute, second, micros).hash(state)
+            }
+            Value::Time(negative, days, hours, minutes, seconds, micros) => {
+                (negative, days, hours, minutes, seconds, micros).hash(state)
+            }
+        }
+    }
+}
+
+/// Encodes `bytes` using the standard base64 alphabet with padding.
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut output = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0_u32, |n, (i, byte)| n | (u32::from(*byte) << (16 - 8 * i)));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                output.push(ALPHABET[((n >> (18 - 6 * i)) & 0x3f) as usize] as char);
+            } else {
+                output.push('=');
+            }
+        }
+    }
+    output
+}
+
+/// Decodes standard base64 with padding (see [`base64`]).
+///
+/// Returns `None` if `input` is malformed.
+fn base64_decode(input: &[u8]) -> Option<Vec<u8>> {
+    fn sextet(x: u8) -> Option<u32> {
+        let sextet = match x {
+            b'A'..=b'Z' => x - b'A',
+            b'a'..=b'z' => x - b'a' + 26,
+            b'0'..=b'9' => x - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            _ => return None,
+        };
+        Some(u32::from(sextet))
+    }
+
+    if !input.len().is_multiple_of(4) {
+        return None;
+    }
+
+    let mut output = Vec::with_capacity(input.len() / 4 * 3);
+    let mut chunks = input.chunks(4).peekable();
+    while let Some(chunk) = chunks.next() {
+        let padding = match chunk {
+            [.., b'=', b'='] => 2,
+            [.., b'='] => 1,
+            _ => 0,
+        };
+        if padding > 0 && chunks.peek().is_some() {
+            return None;
+        }
+        let mut n = 0_u32;
+        for (i, x) in chunk[..4 - padding].iter().enumerate() {
+            n |= sextet(*x)? << (18 - 6 * i);
+        }
+        output.extend_from_slice(&n.to_be_bytes()[1..4 - padding]);
+    }
+    Some(output)
+}
+
+/// Decodes standard base64 with padding into `Value::Bytes`
+/// (see [`ValueExt::to_base64`]).
+///
+/// Returns `None` if `input` is not a valid base64 string.
+///
+/// ```
+/// # use mysql::{value_from_base64, Value};
+/// assert_eq!(value_from_base64("Zm9v"), Some(Value::Bytes(b"foo".to_vec())));
+/// assert_eq!(value_from_base64("Zm9"), None);
+/// ```
+pub fn value_from_base64(input: &str) -> Option<Value> {
+    base64_decode(input.as_bytes()).map(Value::Bytes)
+}
+
+/// Converts a text protocol value to the value, that binary protocol would produce
+/// for the given column.
+///
+/// Values, that are already typed or couldn't be parsed, are returned as is.
+pub(crate) fn to_typed(value: Value, column: &Column) -> Value {
+    let bytes = match value {
+        Value::Bytes(ref bytes) => bytes,
+        value => return value,
+    };
+    let unsigned = column.flags().contains(ColumnFlags::UNSIGNED_FLAG);
+    let typed = match column.column_type() {
+        ColumnType::MYSQL_TYPE_TINY
+        | ColumnType::MYSQL_TYPE_SHORT
+        | ColumnType::MYSQL_TYPE_YEAR
+        | ColumnType::MYSQL_TYPE_LONG
+        | ColumnType::MYSQL_TYPE_INT24
+        | ColumnType::MYSQL_TYPE_LONGLONG => {
+            // binary protocol uses `UInt` only for unsigned values out of `i64` range
+            if unsigned {
+                parse::<u64>(bytes)
+                    .map(|x| i64::try_from(x).map(Value::Int).unwrap_or(Value::UInt(x)))
+            } else {
+                parse(bytes).map(Value::Int)
+            }
+        }
+        ColumnType::MYSQL_TYPE_FLOAT => parse(bytes).map(Value::Float),
+        ColumnType::MYSQL_TYPE_DOUBLE => parse(bytes).map(Value::Double),
+        ColumnType::MYSQL_TYPE_TIMESTAMP
+        | ColumnType::MYSQL_TYPE_DATE
+        | ColumnType::MYSQL_TYPE_DATETIME => parse_datetime(bytes),
+        ColumnType::MYSQL_TYPE_TIME => parse_time(bytes),
+        _ => None,
+    };
+    typed.unwrap_or(value)
+}
+
+fn parse<T: FromStr>(bytes: &[u8]) -> Option<T> {
+    std::str::from_utf8(bytes).ok()?.parse().ok()
+}
+
+/// Parses `ss[.ffffff]` into seconds and microseconds.
+fn parse_seconds(bytes: &[u8]) -> Option<(u8, u32)> {
+    match bytes.iter().position(|x| *x == b'.') {
+        Some(dot) => {
+            let frac = &bytes[dot + 1..];
+            if frac.is_empty() || frac.len() > 6 {
+                return None;
+            }
+            let micros = parse::<u32>(frac)? * 10_u32.pow(6 - frac.len() as u32);
+            Some((parse(&bytes[..dot])?, micros))
+        }
+        None => Some((parse(bytes)?, 0)),
+    }
+}
+
+/// Parses `YYYY-MM-DD[ hh:mm:ss[.ffffff]]` into `Value::Date`.
+fn parse_datetime(bytes: &[u8]) -> Option<Value> {
+    let (date, time) = match bytes.iter().position(|x| *x == b' ') {
+        Some(space) => (&bytes[..space], Some(&bytes[space + 1..])),
+        None => (bytes, None),
+    };
+    let mut date = date.split(|x| *x == b'-');
+    let year = parse(date.next()?)?;
+    let month = parse(date.next()?)?;
+    let day = parse(date.next()?)?;
+    if date.next().is_some() {
+        return None;
+    }
+    let (hour, minute, second, micros) = match time {
+        Some(time) => {
+            let mut time = time.splitn(3, |x| *x == b':');
+            let hour = parse(time.next()?)?;
+            let minute = parse(time.next()?)?;
+            let (second, micros) = parse_seconds(time.next()?)?;
+            (hour, minute, second, micros)
+        }
+        None => (0, 0, 0, 0),
+    };
+    Some(Value::Date(year, month, day, hour, minute, second, micros))
+}
+
+/// Parses `[-]hhh:mm:ss[.ffffff]` into `Value::Time`.
+fn parse_time(bytes: &[u8]) -> Option<Value> {
+    let (negative, bytes) = match bytes {
+        [b'-', rest @ ..] => (true, rest),
+        _ => (false, bytes),
+    };
+    let mut time = bytes.splitn(3, |x| *x == b':');
+    let hours: u32 = parse(time.next()?)?;
+    let minutes = parse(time.next()?)?;
+    let (seconds, micros) = parse_seconds(time.next()?)?;
+    Some(Value::Time(
+        negative,
+        hours / 24,
+        (hours % 24) as u8,
+        minutes,
+        seconds,
+        micros,
+    ))
+}
+
+/// Converts the number of days since `1970-01-01` into a proleptic Gregorian
+/// `(year, month, day)` (see Howard Hinnant's `civil_from_days`).
+///
+/// **Requires:** the resulting year is within `0..=9999`.
+fn civil_from_days(days: i64) -> (u16, u8, u8) {
+    // shift the epoch to 0000-03-01, so that the leap day is the last day of a year
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_from_march = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_from_march + 2) / 5 + 1;
+    let month = if month_from_march < 10 {
+        month_from_march + 3
+    } else {
+        month_from_march - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year as u16, month as u8, day as u8)
+}
+
+/// Rank of a value kind in [`ValueExt::mysql_cmp`].
+fn kind_rank(value: &Value) -> u8 {
+    match value {
+        Value::NULL => 0,
+        Value::Int(_) | Value::UInt(_) | Value::Float(_) | Value::Double(_) => 1,
+        Value::Bytes(_) => 2,
+        Value::Date(..) => 3,
+        Value::Time(..) => 4,
+    }
+}
+
+/// Numeric value, that keeps integers exact.
+enum Number {
+    Int(i64),
+    UInt(u64),
+    Float(f64),
+}
+
+impl Number {
+    fn new(value: &Value) -> Option<Self> {
+        match *value {
+            Value::Int(x) => Some(Number::Int(x)),
+            Value::UInt(x) => Some(Number::UInt(x)),
+            Value::Float(x) => Some(Number::Float(f64::from(x))),
+            Value::Double(x) => Some(Number::Float(x)),
+            _ => None,
+        }
+    }
+
+    fn compare(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (Number::Int(a), Number::Int(b)) => a.cmp(b),
+            (Number::UInt(a), Number::UInt(b)) => a.cmp(b),
+            (Number::Int(a), Number::UInt(b)) => match u64::try_from(*a) {
+                Ok(a) => a.cmp(b),
+                Err(_) => Ordering::Less,
+            },
+            (Number::Int(a), Number::Float(b)) => cmp_int_float(i128::from(*a), *b),
+            (Number::UInt(a), Number::Float(b)) => cmp_int_float(i128::from(*a), *b),
+            (Number::Float(a), Number::Float(b)) => match (a.is_nan(), b.is_nan()) {
+                (false, false) => a.partial_cmp(b).unwrap_or(Ordering::Equal),
+                (a_nan, b_nan) => a_nan.cmp(&b_nan),
+            },
+            _ => other.compare(self).reverse(),
+        }
+    }
+}
+
+/// Compares an integer with a float exactly, i.e. without converting the integer
+/// to a float (`NaN` is greater than any other number).
+fn cmp_int_float(a: i128, b: f64) -> Ordering {
+    // 2^64, so that every `i64` and `u64` value is within (-LIMIT, LIMIT)
+    const LIMIT: f64 = 18_446_744_073_709_551_616.0;
+
+    if b.is_nan() || b >= LIMIT {
+        Ordering::Less
+    } else if b <= -LIMIT {
+        Ordering::Greater
+    } else {
+        // exact, because `b` is within the `i128` range
+        let trunc = b.trunc();
+        a.cmp(&(trunc as i128))
+            .then_with(|| trunc.partial_cmp(&b).unwrap_or(Ordering::Equal))
+    }
+}
+
+impl ValueExt for Value {
+    fn as_wkb(&self) -> Option<&[u8]> {
+        match self {
+            Value::Bytes(bytes) if bytes.len() > SRID_LEN => Some(&bytes[SRID_LEN..]),
+            _ => None,
+        }
+    }
+
+    fn as_i8(&self) -> Option<i8> {
+        as_int(self)
+    }
+
+    fn as_u8(&self) -> Option<u8> {
+        as_int(self)
+    }
+
+    fn as_i16(&self) -> Option<i16> {
+        as_int(self)
+    }
+
+    fn as_u16(&self) -> Option<u16> {
+        as_int(self)
+    }
+
+    fn as_i32(&self) -> Option<i32> {
+        as_int(self)
+    }
+
+    fn as_u32(&self) -> Option<u32> {
+        as_int(self)
+    }
+
+    fn as_i64(&self) -> Option<i64> {
+        as_int(self)
+    }
+
+    fn as_u64(&self) -> Option<u64> {
+        as_int(self)
+    }
+
+    fn as_year(&self) -> Option<u16> {
+        match as_int(self)? {
+            year @ (0 | 1901..=2155) => Some(year),
+            _ => None,
+        }
+    }
+
+    fn as_f64(&self) -> Option<f64> {
+        match *self {
+            Value::Float(x) => Some(f64::from(x)),
+            Value::Double(x) => Some(x),
+            Value::Int(x) => Some(x as f64),
+            Value::UInt(x) => Some(x as f64),
+            Value::Bytes(ref bytes) => parse(bytes),
+            _ => None,
+        }
+    }
+
+    fn as_f32(&self) -> Option<f32> {
+        match *self {
+            Value::Float(x) => Some(x),
+            Value::Double(x) => Some(x as f32),
+            Value::Int(x) => Some(x as f32),
+            Value::UInt(x) => Some(x as f32),
+            Value::Bytes(ref bytes) => parse(bytes),
+            _ => None,
+        }
+    }
+
+    fn from_duration(duration: Duration) -> Self {
+        Self::from_signed_duration(false, duration)
+    }
+
+    fn from_signed_duration(negative: bool, duration: Duration) -> Self {
+        let (secs, micros) = if duration.as_secs() > TIME_MAX_SECS {
+            (TIME_MAX_SECS, 0)
+        } else {
+            (duration.as_secs(), duration.subsec_micros())
+        };
+        Value::Time(
+            negative,
+            (secs / (24 * 60 * 60)) as u32,
+            (secs / (60 * 60) % 24) as u8,
+            (secs / 60 % 60) as u8,
+            (secs % 60) as u8,
+            micros,
+        )
+    }
+
+    fn from_unix_timestamp(secs: i64, usec: u32) -> Self {
+        let (secs, usec) = if secs < UNIX_TIMESTAMP_MIN {
+            (UNIX_TIMESTAMP_MIN, 0)
+        } else if secs > UNIX_TIMESTAMP_MAX {
+            (UNIX_TIMESTAMP_MAX, 999_999)
+        } else {
+            (secs, usec.min(999_999))
+        };
+        let days = secs.div_euclid(24 * 60 * 60);
+        let secs = secs.rem_euclid(24 * 60 * 60);
+        let (year, month, day) = civil_from_days(days);
+        Value::Date(
+            year,
+            month,
+            day,
+            (secs / (60 * 60)) as u8,
+            (secs / 60 % 60) as u8,
+            (secs % 60) as u8,
+            usec,
+        )
+    }
+
+    fn as_duration(&self) -> Option<Duration> {
+        match self.as_signed_duration()? {
+            (false, duration) => Some(duration),
+            (true, duration) if duration.is_zero() => Some(duration),
+            (true, _) => None,
+        }
+    }
+
+    fn as_signed_duration(&self) -> Option<(bool, Duration)> {
+        let time = match self {
+            Value::Bytes(bytes) => parse_time(bytes)?,
+            value => value.clone(),
+        };
+        match time {
+            Value::Time(negative, days, hours, minutes, seconds, micros) => {
+                let secs = u64::from(days) * 24 * 60 * 60
+                    + u64::from(hours) * 60 * 60
+                    + u64::from(minutes) * 60
+                    + u64::from(seconds);
+                Some((negative, Duration::new(secs, micros * 1000)))
+            }
+            _ => None,
+        }
+    }
+
+    fn total_time_seconds(&self) -> Option<i64> {
+        let (negative, duration) = self.as_signed_duration()?;
+        // `TIME` range is small enough, so this won't overflow
+        let secs = duration.as_secs() as i64;
+        Some(if negative { -secs } else { secs })
+    }
+
+    fn total_hours(&self) -> Option<i64> {
+        self.total_time_seconds().map(|secs| secs / (60 * 60))
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        use serde_json::Value as Json;
+
+        match *self {
+            Value::NULL => Json::Null,
+            Value::Bytes(ref bytes) => match std::str::from_utf8(bytes) {
+                Ok(string) => Json::from(string),
+                Err(_) => Json::from(base64(bytes)),
+            },
+            Value::Int(x) => Json::from(x),
+            Value::UInt(x) => Json::from(x),
+            // f32 -> f64 conversion is lossy in decimal (1.1 becomes 1.100000023841858)
+            Value::Float(x) => x
+                .to_string()
+                .parse::<f64>()
+                .ok()
+                .and_then(serde_json::Number::from_f64)
+                .map_or(Json::Null, Json::Number),
+            Value::Double(x) => serde_json::Number::from_f64(x).map_or(Json::Null, Json::Number),
+            Value::Date(year, month, day, 0, 0, 0, 0) => {
+                Json::from(format!("{:04}-{:02}-{:02}", year, month, day))
+            }
+            Value::Date(year, month, day, hour, minute, second, 0) => Json::from(format!(
+                "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+                year, month, day, hour, minute, second
+            )),
+            Value::Date(year, month, day, hour, minute, second, micros) => Json::from(format!(
+                "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:06}",
+                year, month, day, hour, minute, second, micros
+            )),
+            Value::Time(negative, days, hours, minutes, seconds, micros) => {
+                let sign = if negative { "-" } else { "" };
+                let hours = u64::from(days) * 24 + u64::from(hours);
+                let mut time = format!("{}{:02}:{:02}:{:02}", sign, hours, minutes, seconds);
+                if micros > 0 {
+                    time.push_str(&format!(".{:06}", micros));
+                }
+                Json::from(time)
+            }
+        }
+    }
+
+    fn display_with(&self, mode: BytesDisplay) -> ValueDisplay<'_> {
+        ValueDisplay { value: self, mode }
+    }
+
+    fn as_sql_hex(&self, no_backslash_escape: bool) -> String {
+        match self {
+            // `0x` is not a valid literal
+            Value::Bytes(bytes) if bytes.is_empty() => "X''".into(),
+            Value::Bytes(_) => self.display_with(BytesDisplay::HexAlways).to_string(),
+            value => value.as_sql(no_backslash_escape),
+        }
+    }
+
+    fn eq_ignore_ascii_case(&self, other: &str) -> bool {
+        match self {
+            Value::Bytes(bytes) => bytes.eq_ignore_ascii_case(other.as_bytes()),
+            _ => false,
+        }
+    }
+
+    fn to_iso8601(&self) -> Option<String> {
+        match *self {
+            Value::Date(year, month, day, hour, minute, second, micros) => {
+                let mut date = format!(
+                    "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+                    year, month, day, hour, minute, second
+                );
+                if micros > 0 {
+                    date.push_str(&format!(".{:06}", micros));
+                }
+                Some(date)
+            }
+            Value::Time(negative, days, hours, minutes, seconds, micros) => {
+                let sign = if negative { "-" } else { "" };
+                let hours = u64::from(days) * 24 + u64::from(hours);
+                let mut time = format!("{}PT{}H{}M{}", sign, hours, minutes, seconds);
+                if micros > 0 {
+                    time.push_str(&format!(".{:06}", micros));
+                }
+                time.push('S');
+                Some(time)
+            }
+            _ => None,
+        }
+    }
+
+    fn to_base64(&self) -> Option<String> {
+        match self {
+            Value::Bytes(bytes) => Some(base64(bytes)),
+            _ => None,
+        }
+    }
+
+    fn mysql_cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (Value::Bytes(a), Value::Bytes(b)) => a.cmp(b),
+            (Value::Date(..), Value::Date(..)) => {
+                self.partial_cmp(other).unwrap_or(Ordering::Equal)
+            }
+            (
+                Value::Time(a_neg, a_d, a_h, a_m, a_s, a_us),
+                Value::Time(b_neg, b_d, b_h, b_m, b_s, b_us),
+            ) => {
+                let a = (a_d, a_h, a_m, a_s, a_us);
+                let b = (b_d, b_h, b_m, b_s, b_us);
+                match (a_neg, b_neg) {
+                    (false, false) => a.cmp(&b),
+                    (true, true) => b.cmp(&a),
+                    _ => b_neg.cmp(a_neg),
+                }
+            }
+            _ => match (Number::new(self), Number::new(other)) {
+                (Some(a), Some(b)) => a.compare(&b),
+                _ => kind_rank(self).cmp(&kind_rank(other)),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{cmp::Ordering, collections::HashSet, time::Duration};
+
+    use super::{
+        to_typed, value_from_base64, BytesDisplay, ValueKey, TIME_MAX_SECS, UNIX_TIMESTAMP_MIN,
+    };
+    use crate::{
+        consts::{ColumnFlags, ColumnType},
+        prelude::*,
+        Column, Value,
+    };
+
+    #[test]
+    fn should_skip_srid_in_as_wkb() {
+        let mut bytes = vec![0, 0, 0, 0, 1, 1, 0, 0, 0];
+        bytes.extend_from_slice(&1_f64.to_le_bytes());
+        bytes.extend_from_slice(&2_f64.to_le_bytes());
+        let value = Value::Bytes(bytes.clone());
+        assert_eq!(value.as_wkb(), Some(&bytes[4..]));
+
+        assert_eq!(Value::Bytes(vec![0, 0, 0, 0]).as_wkb(), None);
+        assert_eq!(Value::Int(1).as_wkb(), None);
+        assert_eq!(Value::NULL.as_wkb(), None);
+    }
+
+    #[test]
+    fn should_order_mixed_values() {
+        let mut values = vec![
+            Value::Time(false, 0, 1, 0, 0, 0),
+            Value::Bytes(b"b".to_vec()),
+            Value::Int(5),
+            Value::Date(2020, 1, 2, 0, 0, 0, 0),
+            Value::UInt(u64::MAX),
+            Value::NULL,
+            Value::Bytes(b"1".to_vec()),
+            Value::Double(5.5),
+            Value::Time(true, 0, 2, 0, 0, 0),
+            Value::Int(-1),
+            Value::Date(2019, 12, 31, 0, 0, 0, 0),
+            Value::Float(-1.5),
+        ];
+        values.sort_by(Value::mysql_cmp);
+        assert_eq!(
+            values,
+            vec![
+                Value::NULL,
+                Value::Float(-1.5),
+                Value::Int(-1),
+                Value::Int(5),
+                Value::Double(5.5),
+                Value::UInt(u64::MAX),
+                Value::Bytes(b"1".to_vec()),
+                Value::Bytes(b"b".to_vec()),
+                Value::Date(2019, 12, 31, 0, 0, 0, 0),
+                Value::Date(2020, 1, 2, 0, 0, 0, 0),
+                Value::Time(true, 0, 2, 0, 0, 0),
+                Value::Time(false, 0, 1, 0, 0, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn should_order_null_lowest() {
+        for value in [
+            Value::Int(i64::MIN),
+            Value::Double(f64::NEG_INFINITY),
+            Value::Bytes(Vec::new()),
+            Value::Date(0, 0, 0, 0, 0, 0, 0),
+            Value::Time(true, 34, 22, 59, 59, 0),
+        ] {
+            assert_eq!(Value::NULL.mysql_cmp(&value), Ordering::Less);
+            assert_eq!(value.mysql_cmp(&Value::NULL), Ordering::Greater);
+        }
+        assert_eq!(Value::NULL.mysql_cmp(&Value::NULL), Ordering::Equal);
+        assert_eq!(Value::Int(1).mysql_cmp(&Value::UInt(1)), Ordering::Equal);
+        assert_eq!(Value::Int(-1).mysql_cmp(&Value::UInt(0)), Ordering::Less);
+    }
+
+    #[test]
+    fn should_compare_integers_with_floats_exactly() {
+        // 2^53 + 1 is not representable as `f64`
+        let int = Value::Int(1 << 53);
+        let next_int = Value::Int((1 << 53) + 1);
+        let double = Value::Double((1_u64 << 53) as f64);
+        assert_eq!(int.mysql_cmp(&double), Ordering::Equal);
+        assert_eq!(next_int.mysql_cmp(&double), Ordering::Greater);
+        assert_eq!(double.mysql_cmp(&next_int), Ordering::Less);
+        assert_eq!(int.mysql_cmp(&next_int), Ordering::Less);
+
+        assert_eq!(
+            Value::UInt(u64::MAX).mysql_cmp(&Value::Double(u64::MAX as f64)),
+            Ordering::Less
+        );
+        assert_eq!(
+            Value::Int(i64::MIN).mysql_cmp(&Value::Double(i64::MIN as f64)),
+            Ordering::Equal
+        );
+        assert_eq!(
+            Value::Int(-1).mysql_cmp(&Value::Double(-0.5)),
+            Ordering::Less
+        );
+        assert_eq!(
+            Value::Int(0).mysql_cmp(&Value::Float(-0.5)),
+            Ordering::Greater
+        );
+        assert_eq!(
+            Value::Int(i64::MAX).mysql_cmp(&Value::Double(f64::INFINITY)),
+            Ordering::Less
+        );
+        assert_eq!(
+            Value::UInt(0).mysql_cmp(&Value::Double(f64::NEG_INFINITY)),
+            Ordering::Greater
+        );
+        assert_eq!(
+            Value::UInt(u64::MAX).mysql_cmp(&Value::Double(f64::NAN)),
+            Ordering::Less
+        );
+    }
+
+    #[test]
+    fn should_parse_text_values() {
+        use super::{parse_datetime, parse_time};
+
+        assert_eq!(
+            parse_datetime(b"2020-01-02"),
+            Some(Value::Date(2020, 1, 2, 0, 0, 0, 0))
+        );
+        assert_eq!(
+            parse_datetime(b"2020-01-02 03:04:05"),
+            Some(Value::Date(2020, 1, 2, 3, 4, 5, 0))
+        );
+        assert_eq!(
+            parse_datetime(b"2020-01-02 03:04:05.12"),
+            Some(Value::Date(2020, 1, 2, 3, 4, 5, 120_000))
+        );
+        assert_eq!(parse_datetime(b"2020-01"), None);
+        assert_eq!(parse_datetime(b"foo"), None);
+
+        assert_eq!(
+            parse_time(b"-838:59:59"),
+            Some(Value::Time(true, 34, 22, 59, 59, 0))
+        );
+        assert_eq!(
+            parse_time(b"01:02:03.000004"),
+            Some(Value::Time(false, 0, 1, 2, 3, 4))
+        );
+        assert_eq!(parse_time(b"01:02"), None);
+    }
+
+    #[test]
+    fn should_convert_negative_interval() {
+        let duration = Duration::from_secs(2 * 24 * 60 * 60 + 3 * 60 * 60 + 4 * 60 + 5);
+        let value = Value::from_signed_duration(true, duration);
+        assert_eq!(value, Value::Time(true, 2, 3, 4, 5, 0));
+        assert_eq!(value.as_signed_duration(), Some((true, duration)));
+        assert_eq!(value.as_duration(), None);
+
+        let text = Value::Bytes(b"-51:04:05".to_vec());
+        assert_eq!(text.as_signed_duration(), Some((true, duration)));
+    }
+
+    #[test]
+    fn should_convert_unix_timestamps() {
+        assert_eq!(
+            Value::from_unix_timestamp(0, 0),
+            Value::Date(1970, 1, 1, 0, 0, 0, 0)
+        );
+        assert_eq!(
+            Value::from_unix_timestamp(1_700_000_000, 123_456),
+            Value::Date(2023, 11, 14, 22, 13, 20, 123_456)
+        );
+
+        // leap years
+        assert_eq!(
+            Value::from_unix_timestamp(951_782_400, 0),
+            Value::Date(2000, 2, 29, 0, 0, 0, 0)
+        );
+        assert_eq!(
+            Value::from_unix_timestamp(951_782_400 + 24 * 60 * 60, 0),
+            Value::Date(2000, 3, 1, 0, 0, 0, 0)
+        );
+
+        // pre-epoch
+        assert_eq!(
+            Value::from_unix_timestamp(-1, 500_000),
+            Value::Date(1969, 12, 31, 23, 59, 59, 500_000)
+        );
+        assert_eq!(
+            Value::from_unix_timestamp(-2_208_988_800, 0),
+            Value::Date(1900, 1, 1, 0, 0, 0, 0)
+        );
+
+        // out of range
+        assert_eq!(
+            Value::from_unix_timestamp(UNIX_TIMESTAMP_MIN, 0),
+            Value::Date(0, 1, 1, 0, 0, 0, 0)
+        );
+        assert_eq!(
+            Value::from_unix_timestamp(i64::MIN, 1),
+            Value::Date(0, 1, 1, 0, 0, 0, 0)
+        );
+        assert_eq!(
+            Value::from_unix_timestamp(i64::MAX, 0),
+            Value::Date(9999, 12, 31, 23, 59, 59, 999_999)
+        );
+        assert_eq!(
+            Value::from_unix_timestamp(0, u32::MAX),
+            Value::Date(1970, 1, 1, 0, 0, 0, 999_999)
+        );
+    }
+
+    #[test]
+    fn should_fold_time_into_total_hours() {
+        let value = Value::Time(false, 1, 2, 0, 0, 0);
+        assert_eq!(value.total_hours(), Some(26));
+        assert_eq!(value.total_time_seconds(), Some(26 * 60 * 60));
+
+        let value = Value::Time(true, 34, 22, 59, 59, 999_999);
+        assert_eq!(value.total_hours(), Some(-838));
+        assert_eq!(value.total_time_seconds(), Some(-3_020_399));
+
+        let text = Value::Bytes(b"-838:59:59".to_vec());
+        assert_eq!(text.total_hours(), Some(-838));
+        assert_eq!(text.total_time_seconds(), Some(-3_020_399));
+
+        assert_eq!(Value::Time(true, 0, 0, 59, 0, 0).total_hours(), Some(0));
+        assert_eq!(Value::Int(1).total_hours(), None);
+        assert_eq!(Value::NULL.total_time_seconds(), None);
+    }
+
+    #[test]
+    fn should_convert_sub_second_interval() {
+        let duration = Duration::from_micros(250_001);
+        let value = Value::from_duration(duration);
+        assert_eq!(value, Value::Time(false, 0, 0, 0, 0, 250_001));
+        assert_eq!(value.as_duration(), Some(duration));
+
+        // sub-microsecond precision is truncated
+        let value = Value::from_duration(Duration::from_nanos(1_999));
+        assert_eq!(value.as_duration(), Some(Duration::from_micros(1)));
+
+        let text = Value::Bytes(b"00:00:00.25".to_vec());
+        assert_eq!(text.as_duration(), Some(Duration::from_millis(250)));
+    }
+
+    #[test]
+    fn should_clamp_duration_to_time_range() {
+        let max = Value::Time(false, 34, 22, 59, 59, 0);
+        assert_eq!(
+            Value::from_duration(Duration::from_secs(TIME_MAX_SECS)),
+            max
+        );
+        assert_eq!(
+            Value::from_duration(Duration::new(TIME_MAX_SECS + 1, 5_000)),
+            max
+        );
+        assert_eq!(Value::from_duration(Duration::from_secs(u64::MAX)), max);
+        assert_eq!(Value::Int(1).as_duration(), None);
+    }
+
+    #[test]
+    fn should_dedup_values_in_hash_set() {
+        let values = vec![
+            Value::NULL,
+            Value::Int(1),
+            Value::UInt(1),
+            Value::Bytes(b"1".to_vec()),
+            Value::Double(1.0),
+            Value::Float(f32::NAN),
+            Value::Date(2024, 1, 2, 3, 4, 5, 6),
+            Value::Time(true, 1, 2, 3, 4, 5),
+        ];
+        let set = values
+            .iter()
+            .chain(values.iter())
+            .cloned()
+            .map(ValueKey)
+            .collect::<HashSet<_>>();
+        assert_eq!(set.len(), values.len());
+        for value in values {
+            assert!(set.contains(&ValueKey(value)));
+        }
+
+        assert_eq!(
+            ValueKey(Value::Double(f64::NAN)),
+            ValueKey(Value::Double(f64::NAN))
+        );
+    }
+
+    #[test]
+    fn should_treat_nans_as_equal_keys() {
+        let nans = [
+            f64::NAN,
+            -f64::NAN,
+            f64::from_bits(f64::NAN.to_bits() | 1),
+            (-1.0_f64).sqrt(),
+        ];
+        for a in nans {
+            for b in nans {
+                assert_eq!(ValueKey(Value::Double(a)), ValueKey(Value::Double(b)));
+                assert_eq!(
+                    ValueKey(Value::Float(a as f32)),
+                    ValueKey(Value::Float(b as f32))
+                );
+            }
+        }
+        let set = nans
+            .iter()
+            .map(|x| ValueKey(Value::Double(*x)))
+            .collect::<HashSet<_>>();
+        assert_eq!(set.len(), 1);
+
+        // NaN is still not equal to a number or to a NaN of another variant
+        assert_ne!(
+            ValueKey(Value::Double(f64::NAN)),
+            ValueKey(Value::Double(1.0))
+        );
+        assert_ne!(
+            ValueKey(Value::Double(f64::NAN)),
+            ValueKey(Value::Float(f32::NAN))
+        );
+
+        // other floats follow the usual equality
+        assert_eq!(ValueKey(Value::Double(0.0)), ValueKey(Value::Double(-0.0)));
+        assert_eq!(ValueKey(Value::Float(0.0)), ValueKey(Value::Float(-0.0)));
+        assert_ne!(ValueKey(Value::Double(0.1)), ValueKey(Value::Double(0.2)));
+        let set = [0.0, -0.0]
+            .iter()
+            .map(|x| ValueKey(Value::Double(*x)))
+            .collect::<HashSet<_>>();
+        assert_eq!(set.len(), 1);
+
+        // equal keys must have equal hashes
+        let state = std::collections::hash_map::RandomState::new();
+        let hash = |key: &ValueKey| {
+            use std::hash::BuildHasher;
+            state.hash_one(key)
+        };
+        assert_eq!(
+            hash(&ValueKey(Value::Double(f64::NAN))),
+            hash(&ValueKey(Value::Double(-f64::NAN)))
+        );
+        assert_eq!(
+            hash(&ValueKey(Value::Float(0.0))),
+            hash(&ValueKey(Value::Float(-0.0)))
+        );
+    }
+
+    #[test]
+    fn should_convert_values_to_json() {
+        let values = vec![
+            Value::NULL,
+            Value::Int(-1),
+            Value::UInt(u64::MAX),
+            Value::Float(1.1),
+            Value::Double(f64::NAN),
+            Value::Bytes("фу".as_bytes().to_vec()),
+            Value::Bytes(vec![0, 0xff, 0xfe, 0x01]),
+            Value::Date(2024, 1, 2, 0, 0, 0, 0),
+            Value::Date(2024, 1, 2, 3, 4, 5, 0),
+            Value::Date(2024, 1, 2, 3, 4, 5, 6),
+            Value::Time(true, 1, 3, 4, 5, 0),
+            Value::Time(false, 0, 0, 0, 1, 500_000),
+        ];
+        let json = values.iter().map(Value::to_json).collect::<Vec<_>>();
+        assert_eq!(
+            serde_json::Value::from(json),
+            serde_json::json!([
+                null,
+                -1,
+                u64::MAX,
+                1.1,
+                null,
+                "фу",
+                "AP/+AQ==",
+                "2024-01-02",
+                "2024-01-02T03:04:05",
+                "2024-01-02T03:04:05.000006",
+                "-27:04:05",
+                "00:00:01.500000",
+            ])
+        );
+
+        assert_eq!(super::base64(b""), "");
+        assert_eq!(super::base64(b"f"), "Zg==");
+        assert_eq!(super::base64(b"fo"), "Zm8=");
+        assert_eq!(super::base64(b"foo"), "Zm9v");
+        assert_eq!(super::base64(b"foob"), "Zm9vYg==");
+    }
+
+    #[test]
+    fn should_format_iso8601() {
+        let value = Value::Date(2014, 2, 20, 22, 0, 0, 1);
+        assert_eq!(value.to_iso8601().unwrap(), "2014-02-20T22:00:00.000001");
+        assert_eq!(value.as_sql(false), "'2014-02-20 22:00:00.000001'");
+
+        let value = Value::Date(2014, 2, 20, 22, 0, 0, 0);
+        assert_eq!(value.to_iso8601().unwrap(), "2014-02-20T22:00:00");
+        let value = Value::Date(2014, 2, 20, 0, 0, 0, 0);
+        assert_eq!(value.to_iso8601().unwrap(), "2014-02-20T00:00:00");
+
+        let value = Value::Time(true, 1, 3, 4, 5, 6);
+        assert_eq!(value.to_iso8601().unwrap(), "-PT27H4M5.000006S");
+        let value = Value::Time(false, 0, 0, 0, 0, 0);
+        assert_eq!(value.to_iso8601().unwrap(), "PT0H0M0S");
+
+        assert_eq!(Value::Bytes(b"2014-02-20".to_vec()).to_iso8601(), None);
+        assert_eq!(Value::NULL.to_iso8601(), None);
+    }
+
+    #[test]
+    fn should_roundtrip_base64() {
+        for bytes in [
+            &b""[..],
+            b"f",
+            b"fo",
+            b"foo",
+            b"foob",
+            b"fooba",
+            b"foobar",
+            &[0x00_u8, 0xff, 0xfe, 0x01],
+        ] {
+            let value = Value::Bytes(bytes.to_vec());
+            let encoded = value.to_base64().unwrap();
+            assert_eq!(value_from_base64(&encoded), Some(value));
+        }
+
+        assert_eq!(
+            value_from_base64("AP/+AQ=="),
+            Some(Value::Bytes(vec![0x00, 0xff, 0xfe, 0x01]))
+        );
+
+        // malformed
+        assert_eq!(value_from_base64("Zm9"), None);
+        assert_eq!(value_from_base64("Zm9v!A=="), None);
+        assert_eq!(value_from_base64("Zg==Zm9v"), None);
+        assert_eq!(value_from_base64("Z==="), None);
+        assert_eq!(value_from_base64("Zm 9"), None);
+    }
+
+    #[test]
+    fn should_not_encode_non_bytes_to_base64() {
+        assert_eq!(Value::NULL.to_base64(), None);
+        assert_eq!(Value::Int(1).to_base64(), None);
+        assert_eq!(Value::Date(2024, 1, 2, 0, 0, 0, 0).to_base64(), None);
+    }
+
+    #[test]
+    fn should_read_year() {
+        assert_eq!(Value::UInt(2024).as_year(), Some(2024));
+        assert_eq!(Value::Int(2024).as_year(), Some(2024));
+        assert_eq!(Value::Bytes(b"2024".to_vec()).as_year(), Some(2024));
+        assert_eq!(Value::Bytes(b"0000".to_vec()).as_year(), Some(0));
+        assert_eq!(Value::UInt(0).as_year(), Some(0));
+
+        assert_eq!(Value::UInt(1900).as_year(), None);
+        assert_eq!(Value::UInt(2156).as_year(), None);
+        assert_eq!(Value::Int(-1).as_year(), None);
+        assert_eq!(Value::NULL.as_year(), None);
+    }
+
+    #[test]
+    fn should_render_bytes_as_sql() {
+        assert_eq!(Value::Bytes(Vec::new()).as_sql(false), "''");
+        assert_eq!(Value::Bytes(vec![0]).as_sql(false), "'\\0'");
+        assert_eq!(
+            Value::Bytes(vec![0xff, 0x00, 0x1f]).as_sql(false),
+            "0xFF001F"
+        );
+    }
+
+    #[test]
+    fn should_render_bytes_as_hex_sql() {
+        let text = Value::Bytes(b"key1".to_vec());
+        assert_eq!(text.as_sql(false), "'key1'");
+        assert_eq!(text.as_sql_hex(false), "0x6B657931");
+        assert_eq!(
+            Value::Bytes(vec![0xff, 0x00, 0x1f]).as_sql_hex(true),
+            "0xFF001F"
+        );
+        assert_eq!(Value::Bytes(Vec::new()).as_sql_hex(false), "X''");
+        assert_eq!(Value::NULL.as_sql_hex(false), "NULL");
+        assert_eq!(Value::Int(-5).as_sql_hex(false), "-5");
+        assert_eq!(
+            Value::Date(2020, 1, 2, 0, 0, 0, 0).as_sql_hex(false),
+            Value::Date(2020, 1, 2, 0, 0, 0, 0).as_sql(false)
+        );
+    }
+
+    #[test]
+    fn should_convert_option_into_null_safe_value() {
+        assert_eq!(Value::from(Some(5_i64)), Value::Int(5));
+        assert_eq!(Value::from(None::<i64>), Value::NULL);
+        assert_eq!(Value::from(Some("foo")), Value::Bytes(b"foo".to_vec()));
+
+        let maybe_id: Option<u32> = None;
+        let params: Vec<Value> = vec![maybe_id.into(), Some(1_u32).into()];
+        assert_eq!(params, vec![Value::NULL, Value::UInt(1)]);
+    }
+
+    #[test]
+    fn should_display_bytes_according_to_mode() {
+        let text = Value::Bytes(b"foo".to_vec());
+        let binary = Value::Bytes(vec![b'f', 0xff, 0x00]);
+
+        assert_eq!(text.display_with(BytesDisplay::Auto).to_string(), "foo");
+        assert_eq!(
+            binary.display_with(BytesDisplay::Auto).to_string(),
+            "0x66FF00"
+        );
+
+        assert_eq!(
+            text.display_with(BytesDisplay::Utf8Lossy).to_string(),
+            "foo"
+        );
+        assert_eq!(
+            binary.display_with(BytesDisplay::Utf8Lossy).to_string(),
+            "f\u{FFFD}\0"
+        );
+
+        assert_eq!(
+            text.display_with(BytesDisplay::HexAlways).to_string(),
+            "0x666F6F"
+        );
+        assert_eq!(
+            binary.display_with(BytesDisplay::HexAlways).to_string(),
+            "0x66FF00"
+        );
+
+        for mode in [
+            BytesDisplay::Auto,
+            BytesDisplay::Utf8Lossy,
+            BytesDisplay::HexAlways,
+        ] {
+            assert_eq!(Value::NULL.display_with(mode).to_string(), "NULL");
+            assert_eq!(Value::Int(-5).display_with(mode).to_string(), "-5");
+            assert_eq!(
+                Value::Date(2020, 1, 2, 3, 4, 5, 0)
+                    .display_with(mode)
+                    .to_string(),
+                "2020-01-02 03:04:05"
+            );
+        }
+    }
+
+    #[test]
+    fn should_compare_bytes_ignoring_ascii_case() {
+        let value = Value::Bytes(b"Hello".to_vec());
+        assert!(value.eq_ignore_ascii_case("hello"));
+        assert!(value.eq_ignore_ascii_case("HELLO"));
+        assert!(!value.eq_ignore_ascii_case("help"));
+        assert!(!value.eq_ignore_ascii_case("hello!"));
+
+        assert!(!Value::NULL.eq_ignore_ascii_case(""));
+        assert!(!Value::Int(1).eq_ignore_ascii_case("1"));
+    }
+
+    #[test]
+    fn should_coerce_floats() {
+        assert_eq!(Value::Bytes(b"1.5".to_vec()).as_f64(), Some(1.5));
+        assert_eq!(Value::Bytes(b"-1.23e10".to_vec()).as_f64(), Some(-1.23e10));
+        assert_eq!(Value::Bytes(b"1.23e-5".to_vec()).as_f32(), Some(1.23e-5));
+        assert_eq!(Value::Bytes(b"1.5x".to_vec()).as_f64(), None);
+        assert_eq!(Value::Bytes(b"".to_vec()).as_f64(), None);
+
+        assert_eq!(Value::Float(1.1).as_f32(), Some(1.1));
+        assert_eq!(Value::Float(0.5).as_f64(), Some(0.5));
+        assert_eq!(Value::Double(2.5).as_f64(), Some(2.5));
+
+        assert_eq!(Value::Int(-3).as_f64(), Some(-3.0));
+        assert_eq!(Value::UInt(3).as_f32(), Some(3.0));
+
+        assert_eq!(Value::NULL.as_f64(), None);
+        assert_eq!(Value::Date(2020, 1, 2, 0, 0, 0, 0).as_f64(), None);
+    }
+
+    #[test]
+    fn should_coerce_integers_within_range() {
+        assert_eq!(Value::Int(42).as_i32(), Some(42));
+        assert_eq!(Value::UInt(42).as_u8(), Some(42));
+        assert_eq!(Value::Bytes(b"-42".to_vec()).as_i16(), Some(-42));
+        assert_eq!(Value::Int(i64::from(i32::MAX)).as_i32(), Some(i32::MAX));
+
+        assert_eq!(Value::Int(i64::from(i32::MAX) + 1).as_i32(), None);
+        assert_eq!(Value::UInt(256).as_u8(), None);
+        assert_eq!(Value::Bytes(b"70000".to_vec()).as_u16(), None);
+
+        assert_eq!(Value::Int(-1).as_u32(), None);
+        assert_eq!(Value::Bytes(b"-1".to_vec()).as_u8(), None);
+
+        assert_eq!(Value::UInt(u64::MAX).as_u64(), Some(u64::MAX));
+        assert_eq!(Value::UInt(u64::MAX).as_i64(), None);
+        let max = Value::Bytes(b"18446744073709551615".to_vec());
+        assert_eq!(max.as_u64(), Some(u64::MAX));
+        assert_eq!(max.as_i64(), None);
+        assert_eq!(
+            Value::Bytes(b"18446744073709551616".to_vec()).as_u64(),
+            None
+        );
+        assert_eq!(
+            Value::Bytes(b"-9223372036854775808".to_vec()).as_i64(),
+            Some(i64::MIN)
+        );
+        assert_eq!(Value::Int(i64::MIN).as_u64(), None);
+
+        assert_eq!(Value::Bytes(b"foo".to_vec()).as_i8(), None);
+        assert_eq!(Value::Double(1.0).as_i32(), None);
+        assert_eq!(Value::NULL.as_i32(), None);
+    }
+
+    #[test]
+    fn should_type_unsigned_integers_as_binary_protocol_does() {
+        let unsigned =
+            |column_type| Column::new(column_type).with_flags(ColumnFlags::UNSIGNED_FLAG);
+        let text = |x: &str| Value::Bytes(x.as_bytes().to_vec());
+
+        let column = unsigned(ColumnType::MYSQL_TYPE_LONG);
+        assert_eq!(
+            to_typed(text("4294967295"), &column),
+            Value::Int(4294967295)
+        );
+        let column = unsigned(ColumnType::MYSQL_TYPE_YEAR);
+        assert_eq!(to_typed(text("2024"), &column), Value::Int(2024));
+        let column = unsigned(ColumnType::MYSQL_TYPE_LONGLONG);
+        assert_eq!(
+            to_typed(text("9223372036854775807"), &column),
+            Value::Int(i64::MAX)
+        );
+        assert_eq!(
+            to_typed(text("18446744073709551615"), &column),
+            Value::UInt(u64::MAX)
+        );
+        let column = Column::new(ColumnType::MYSQL_TYPE_LONGLONG);
+        assert_eq!(to_typed(text("-1"), &column), Value::Int(-1));
+    }
+}